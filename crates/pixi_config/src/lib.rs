@@ -5,6 +5,7 @@ use std::{
     path::{Path, PathBuf},
     process::{Command, Stdio},
     str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use clap::{ArgAction, Parser};
@@ -16,7 +17,7 @@ use rattler_conda_types::{
     ChannelConfig, NamedChannelOrUrl, Version, VersionBumpType, VersionSpec,
 };
 #[cfg(feature = "rattler_repodata_gateway")]
-use rattler_repodata_gateway::{Gateway, SourceConfig};
+use rattler_repodata_gateway::{fetch::CacheAction, Gateway, SourceConfig};
 #[cfg(feature = "rattler_repodata_gateway")]
 use reqwest_middleware::ClientWithMiddleware;
 use serde::{de::IntoDeserializer, Deserialize, Serialize};
@@ -117,6 +118,10 @@ pub struct ConfigCli {
     pypi_keyring_provider: Option<KeyringProvider>,
 }
 
+/// Set by the global `--offline` flag, which overrides the `offline` setting of
+/// every configuration.
+static FORCE_OFFLINE: AtomicBool = AtomicBool::new(false);
+
 #[derive(Parser, Debug, Clone, Default)]
 pub struct ConfigCliPrompt {
     /// Do not change the PS1 variable when starting a prompt.
@@ -149,6 +154,92 @@ pub struct RepodataConfig {
     pub disable_zstd: Option<bool>,
 }
 
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TaskCacheConfig {
+    /// The location of a cache that is shared between machines and stores the
+    /// outputs of tasks. Either a local directory (`file://`), an HTTP(S)
+    /// server that supports `GET` and `PUT` requests or an S3 bucket
+    /// (`s3://bucket/prefix`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    /// Only restore outputs from the shared cache, never upload them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only: Option<bool>,
+    /// The configuration of the S3 (compatible) store used for `s3://` urls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s3: Option<S3Config>,
+}
+
+impl TaskCacheConfig {
+    /// Merge the given TaskCacheConfig into the current one.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            url: other.url.or(self.url),
+            read_only: other.read_only.or(self.read_only),
+            s3: match (self.s3, other.s3) {
+                (Some(this), Some(other)) => Some(this.merge(other)),
+                (this, other) => other.or(this),
+            },
+        }
+    }
+
+    /// Whether outputs are only restored from the shared cache and never
+    /// uploaded. Defaults to false.
+    pub fn read_only(&self) -> bool {
+        self.read_only.unwrap_or(false)
+    }
+}
+
+#[derive(Clone, Default, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct S3Config {
+    /// The endpoint of the S3 compatible store, defaults to the AWS endpoint
+    /// of the region.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint_url: Option<Url>,
+    /// The region of the bucket, defaults to the `AWS_REGION` environment
+    /// variable or `us-east-1`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Address the bucket in the path of the url instead of the host name,
+    /// which is required by most self-hosted stores.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force_path_style: Option<bool>,
+    /// The access key id, defaults to the `AWS_ACCESS_KEY_ID` environment
+    /// variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    /// The secret access key, defaults to the `AWS_SECRET_ACCESS_KEY`
+    /// environment variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+    /// The session token of temporary credentials, defaults to the
+    /// `AWS_SESSION_TOKEN` environment variable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+}
+
+impl S3Config {
+    /// Merge the given S3Config into the current one.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            endpoint_url: other.endpoint_url.or(self.endpoint_url),
+            region: other.region.or(self.region),
+            force_path_style: other.force_path_style.or(self.force_path_style),
+            access_key_id: other.access_key_id.or(self.access_key_id),
+            secret_access_key: other.secret_access_key.or(self.secret_access_key),
+            session_token: other.session_token.or(self.session_token),
+        }
+    }
+
+    /// Whether the bucket is addressed in the path of the url. Defaults to
+    /// false.
+    pub fn force_path_style(&self) -> bool {
+        self.force_path_style.unwrap_or(false)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum KeyringProvider {
@@ -375,6 +466,12 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tls_no_verify: Option<bool>,
 
+    /// If set to true, pixi will not access the network and only use
+    /// artifacts that are already present in the cache.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+
     #[serde(default)]
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub mirrors: HashMap<Url, Vec<Url>>,
@@ -409,6 +506,10 @@ pub struct Config {
     /// it back to the .pixi folder.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detached_environments: Option<DetachedEnvironments>,
+
+    /// Configuration for the shared cache of task outputs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_cache: Option<TaskCacheConfig>,
 }
 
 impl Default for Config {
@@ -418,6 +519,7 @@ impl Default for Config {
             change_ps1: None,
             authentication_override_file: None,
             tls_no_verify: None,
+            offline: None,
             mirrors: HashMap::new(),
             loaded_from: Vec::new(),
             channel_config: default_channel_config(),
@@ -425,6 +527,7 @@ impl Default for Config {
             pypi_config: PyPIConfig::default(),
             detached_environments: Some(DetachedEnvironments::default()),
             pinning_strategy: Default::default(),
+            task_cache: None,
         }
     }
 }
@@ -465,6 +568,17 @@ impl From<&Config> for rattler_repodata_gateway::ChannelConfig {
             })
             .unwrap_or_default();
 
+        // When offline, only use the repodata that is already in the cache, even
+        // if it is outdated.
+        let default_source_config = if config.offline() {
+            SourceConfig {
+                cache_action: CacheAction::ForceCacheOnly,
+                ..default_source_config
+            }
+        } else {
+            default_source_config
+        };
+
         rattler_repodata_gateway::ChannelConfig {
             default: default_source_config,
             per_channel: Default::default(),
@@ -649,6 +763,7 @@ impl Config {
             "change-ps1",
             "authentication-override-file",
             "tls-no-verify",
+            "offline",
             "mirrors",
             "detached-environments",
             "pinning-strategy",
@@ -660,6 +775,16 @@ impl Config {
             "pypi-config.index-url",
             "pypi-config.extra-index-urls",
             "pypi-config.keyring-provider",
+            "task-cache",
+            "task-cache.url",
+            "task-cache.read-only",
+            "task-cache.s3",
+            "task-cache.s3.endpoint-url",
+            "task-cache.s3.region",
+            "task-cache.s3.force-path-style",
+            "task-cache.s3.access-key-id",
+            "task-cache.s3.secret-access-key",
+            "task-cache.s3.session-token",
         ]
     }
 
@@ -676,6 +801,7 @@ impl Config {
                 other.default_channels
             },
             tls_no_verify: other.tls_no_verify.or(self.tls_no_verify),
+            offline: other.offline.or(self.offline),
             change_ps1: other.change_ps1.or(self.change_ps1),
             authentication_override_file: other
                 .authentication_override_file
@@ -688,6 +814,10 @@ impl Config {
             pypi_config: other.pypi_config.merge(self.pypi_config),
             detached_environments: other.detached_environments.or(self.detached_environments),
             pinning_strategy: other.pinning_strategy.or(self.pinning_strategy),
+            task_cache: match (self.task_cache, other.task_cache) {
+                (Some(this), Some(other)) => Some(this.merge(other)),
+                (this, other) => other.or(this),
+            },
         }
     }
 
@@ -709,6 +839,18 @@ impl Config {
         self.tls_no_verify.unwrap_or(false)
     }
 
+    /// Retrieve the value for the offline field (defaults to false). This is
+    /// always true after [`Config::force_offline`] was called.
+    pub fn offline(&self) -> bool {
+        FORCE_OFFLINE.load(Ordering::Relaxed) || self.offline.unwrap_or(false)
+    }
+
+    /// Forces every configuration into offline mode, regardless of its
+    /// `offline` setting. This is used by the global `--offline` flag.
+    pub fn force_offline() {
+        FORCE_OFFLINE.store(true, Ordering::Relaxed);
+    }
+
     /// Retrieve the value for the change_ps1 field (defaults to true).
     pub fn change_ps1(&self) -> bool {
         self.change_ps1.unwrap_or(true)
@@ -740,6 +882,10 @@ impl Config {
         &self.mirrors
     }
 
+    pub fn task_cache(&self) -> Option<&TaskCacheConfig> {
+        self.task_cache.as_ref()
+    }
+
     /// Retrieve the value for the target_environments_directory field.
     pub fn detached_environments(&self) -> DetachedEnvironments {
         self.detached_environments.clone().unwrap_or_default()
@@ -776,6 +922,9 @@ impl Config {
             "tls-no-verify" => {
                 self.tls_no_verify = value.map(|v| v.parse()).transpose().into_diagnostic()?;
             }
+            "offline" => {
+                self.offline = value.map(|v| v.parse()).transpose().into_diagnostic()?;
+            }
             "mirrors" => {
                 self.mirrors = value
                     .map(|v| serde_json::de::from_str(&v))
@@ -869,6 +1018,65 @@ impl Config {
                     _ => return Err(err),
                 }
             }
+            key if key.starts_with("task-cache") => {
+                if key == "task-cache" {
+                    self.task_cache = value
+                        .map(|v| serde_json::de::from_str(&v))
+                        .transpose()
+                        .into_diagnostic()?;
+                    return Ok(());
+                } else if !key.starts_with("task-cache.") {
+                    return Err(err);
+                }
+
+                let subkey = key.strip_prefix("task-cache.").unwrap();
+                match subkey {
+                    "url" => {
+                        self.task_cache
+                            .get_or_insert(TaskCacheConfig::default())
+                            .url = value
+                            .map(|v| Url::parse(&v))
+                            .transpose()
+                            .into_diagnostic()?;
+                    }
+                    "read-only" => {
+                        self.task_cache
+                            .get_or_insert(TaskCacheConfig::default())
+                            .read_only = value.map(|v| v.parse()).transpose().into_diagnostic()?;
+                    }
+                    "s3" => {
+                        self.task_cache.get_or_insert(TaskCacheConfig::default()).s3 = value
+                            .map(|v| serde_json::de::from_str(&v))
+                            .transpose()
+                            .into_diagnostic()?;
+                    }
+                    subkey if subkey.starts_with("s3.") => {
+                        let s3 = self
+                            .task_cache
+                            .get_or_insert(TaskCacheConfig::default())
+                            .s3
+                            .get_or_insert(S3Config::default());
+                        match subkey.strip_prefix("s3.").unwrap() {
+                            "endpoint-url" => {
+                                s3.endpoint_url = value
+                                    .map(|v| Url::parse(&v))
+                                    .transpose()
+                                    .into_diagnostic()?;
+                            }
+                            "region" => s3.region = value,
+                            "force-path-style" => {
+                                s3.force_path_style =
+                                    value.map(|v| v.parse()).transpose().into_diagnostic()?;
+                            }
+                            "access-key-id" => s3.access_key_id = value,
+                            "secret-access-key" => s3.secret_access_key = value,
+                            "session-token" => s3.session_token = value,
+                            _ => return Err(err),
+                        }
+                    }
+                    _ => return Err(err),
+                }
+            }
             _ => return Err(err),
         }
 
@@ -1020,6 +1228,25 @@ UNUSED = "unused"
         );
     }
 
+    #[test]
+    fn test_offline_config() {
+        let (config, _) = Config::from_toml("offline = true").unwrap();
+        assert!(config.offline());
+        assert!(!Config::default().offline());
+
+        let merged = config.merge_config(Config::default());
+        assert_eq!(merged.offline, Some(true));
+
+        #[cfg(feature = "rattler_repodata_gateway")]
+        {
+            let channel_config = rattler_repodata_gateway::ChannelConfig::from(&merged);
+            assert!(matches!(
+                channel_config.default.cache_action,
+                CacheAction::ForceCacheOnly
+            ));
+        }
+    }
+
     #[test]
     fn test_pypi_config_parse() {
         let toml = r#"
@@ -1251,6 +1478,46 @@ UNUSED = "unused"
             Some(KeyringProvider::Subprocess)
         );
 
+        config
+            .set(
+                "task-cache.url",
+                Some("https://cache.example.com/tasks".to_string()),
+            )
+            .unwrap();
+        config
+            .set("task-cache.read-only", Some("true".to_string()))
+            .unwrap();
+        let task_cache = config.task_cache().unwrap();
+        assert_eq!(
+            task_cache.url,
+            Some(Url::parse("https://cache.example.com/tasks").unwrap())
+        );
+        assert!(task_cache.read_only());
+
+        config
+            .set(
+                "task-cache.s3.endpoint-url",
+                Some("http://localhost:9000".to_string()),
+            )
+            .unwrap();
+        config
+            .set("task-cache.s3.force-path-style", Some("true".to_string()))
+            .unwrap();
+        config
+            .set("task-cache.s3.region", Some("eu-central-1".to_string()))
+            .unwrap();
+        let s3 = config.task_cache().unwrap().s3.as_ref().unwrap();
+        assert_eq!(
+            s3.endpoint_url,
+            Some(Url::parse("http://localhost:9000").unwrap())
+        );
+        assert_eq!(s3.region.as_deref(), Some("eu-central-1"));
+        assert!(s3.force_path_style());
+        config.set("task-cache.s3.bucket", None).unwrap_err();
+
+        config.set("offline", Some("true".to_string())).unwrap();
+        assert!(config.offline());
+
         config.set("change-ps1", None).unwrap();
         assert_eq!(config.change_ps1, None);
 
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use indexmap::IndexMap;
 use serde::Deserialize;
 
@@ -7,4 +9,8 @@ pub struct Activation {
     pub scripts: Option<Vec<String>>,
     /// Environment variables to set before running the scripts.
     pub env: Option<IndexMap<String, String>>,
+    /// Dotenv files to load before running the scripts. Variables in `env`
+    /// take precedence over the variables in these files.
+    #[serde(rename = "env-file")]
+    pub env_file: Option<Vec<PathBuf>>,
 }
@@ -0,0 +1,69 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use thiserror::Error;
+
+/// A point in time after which packages are excluded from a solve. Packages
+/// that were published after this time are ignored by both the conda solver
+/// and the pypi resolver.
+///
+/// It is written either as a date (`2024-06-01`), which is interpreted as
+/// midnight UTC of that day, or as an RFC 3339 timestamp
+/// (`2024-06-01T12:00:00Z`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExcludeNewer(DateTime<Utc>);
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("`{0}` is not a valid date, expected a date like `2024-06-01` or a timestamp like `2024-06-01T12:00:00Z`")]
+pub struct ParseExcludeNewerError(String);
+
+impl ExcludeNewer {
+    /// Returns the point in time as a UTC timestamp.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+impl From<DateTime<Utc>> for ExcludeNewer {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self(value)
+    }
+}
+
+impl FromStr for ExcludeNewer {
+    type Err = ParseExcludeNewerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+            return Ok(Self(midnight.and_utc()));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|timestamp| Self(timestamp.with_timezone(&Utc)))
+            .map_err(|_| ParseExcludeNewerError(s.to_string()))
+    }
+}
+
+impl Display for ExcludeNewer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exclude_newer() {
+        let date = ExcludeNewer::from_str("2024-06-01").unwrap();
+        assert_eq!(date.to_string(), "2024-06-01T00:00:00Z");
+
+        let timestamp = ExcludeNewer::from_str("2024-06-01T12:30:00+02:00").unwrap();
+        assert_eq!(timestamp.to_string(), "2024-06-01T10:30:00Z");
+
+        assert!(ExcludeNewer::from_str("June 1st").is_err());
+        assert!(ExcludeNewer::from_str("2024-13-01").is_err());
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     collections::HashMap,
     fmt,
     hash::{Hash, Hasher},
+    path::PathBuf,
 };
 
 use indexmap::{IndexMap, IndexSet};
@@ -22,7 +23,8 @@ use crate::{
     target::Targets,
     task::{Task, TaskName},
     utils::PixiSpanned,
-    Activation, PyPiRequirement, SpecType, SystemRequirements, Target, TargetSelector,
+    Activation, PyPiRequirement, SolveStrategy, SpecType, SystemRequirements, Target,
+    TargetSelector,
 };
 
 /// The name of a feature. This is either a string or default for the default
@@ -140,6 +142,10 @@ pub struct Feature {
     /// it will be seen as unset and overwritten by a set one.
     pub channel_priority: Option<ChannelPriority>,
 
+    /// The strategy used to pick package versions, if not set the default is
+    /// used.
+    pub solve_strategy: Option<SolveStrategy>,
+
     /// Additional system requirements
     pub system_requirements: SystemRequirements,
 
@@ -158,6 +164,7 @@ impl Feature {
             platforms: None,
             channels: None,
             channel_priority: None,
+            solve_strategy: None,
             system_requirements: SystemRequirements::default(),
             pypi_options: None,
 
@@ -252,6 +259,60 @@ impl Feature {
             })
     }
 
+    /// Returns the conda constraints of the feature for a given `platform`.
+    ///
+    /// Constraints of more specific targets overwrite the constraints of less
+    /// specific targets. Returns `None` if this feature does not define any
+    /// target with constraints.
+    pub fn constraints(
+        &self,
+        platform: Option<Platform>,
+    ) -> Option<Cow<'_, IndexMap<PackageName, PixiSpec>>> {
+        self.targets
+            .resolve(platform)
+            .rev()
+            .filter_map(|t| t.constraints.as_ref())
+            .filter(|constraints| !constraints.is_empty())
+            .fold(None, |acc, constraints| match acc {
+                None => Some(Cow::Borrowed(constraints)),
+                Some(mut acc) => {
+                    acc.to_mut().extend(
+                        constraints
+                            .iter()
+                            .map(|(name, spec)| (name.clone(), spec.clone())),
+                    );
+                    Some(acc)
+                }
+            })
+    }
+
+    /// Returns the PyPi constraints of the feature for a given `platform`.
+    ///
+    /// Constraints of more specific targets overwrite the constraints of less
+    /// specific targets. Returns `None` if this feature does not define any
+    /// target with constraints.
+    pub fn pypi_constraints(
+        &self,
+        platform: Option<Platform>,
+    ) -> Option<Cow<'_, IndexMap<PyPiPackageName, PyPiRequirement>>> {
+        self.targets
+            .resolve(platform)
+            .rev()
+            .filter_map(|t| t.pypi_constraints.as_ref())
+            .filter(|constraints| !constraints.is_empty())
+            .fold(None, |acc, constraints| match acc {
+                None => Some(Cow::Borrowed(constraints)),
+                Some(mut acc) => {
+                    acc.to_mut().extend(
+                        constraints
+                            .iter()
+                            .map(|(name, spec)| (name.clone(), spec.clone())),
+                    );
+                    Some(acc)
+                }
+            })
+    }
+
     /// Returns the activation scripts for the most specific target that matches
     /// the given `platform`.
     ///
@@ -278,6 +339,19 @@ impl Feature {
             .next()
     }
 
+    /// Returns the dotenv files of the activation for the most specific target
+    /// that matches the given `platform`.
+    ///
+    /// Returns `None` if this feature does not define any target with
+    /// activation dotenv files.
+    pub fn activation_env_files(&self, platform: Option<Platform>) -> Option<&Vec<PathBuf>> {
+        self.targets
+            .resolve(platform)
+            .filter_map(|t| t.activation.as_ref())
+            .filter_map(|a| a.env_file.as_ref())
+            .next()
+    }
+
     /// Returns true if the feature contains any reference to a pypi
     /// dependencies.
     pub fn has_pypi_dependencies(&self) -> bool {
@@ -308,6 +382,8 @@ impl<'de> Deserialize<'de> for Feature {
             #[serde(default)]
             channel_priority: Option<ChannelPriority>,
             #[serde(default)]
+            solve_strategy: Option<SolveStrategy>,
+            #[serde(default)]
             system_requirements: SystemRequirements,
             #[serde(default)]
             target: IndexMap<PixiSpanned<TargetSelector>, Target>,
@@ -324,6 +400,12 @@ impl<'de> Deserialize<'de> for Feature {
             #[serde(default)]
             pypi_dependencies: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
 
+            #[serde(default, deserialize_with = "deserialize_opt_package_map")]
+            constraints: Option<IndexMap<PackageName, PixiSpec>>,
+
+            #[serde(default)]
+            pypi_constraints: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
+
             /// Additional information to activate an environment.
             #[serde(default)]
             activation: Option<Activation>,
@@ -349,6 +431,8 @@ impl<'de> Deserialize<'de> for Feature {
         let default_target = Target {
             dependencies,
             pypi_dependencies: inner.pypi_dependencies,
+            constraints: inner.constraints,
+            pypi_constraints: inner.pypi_constraints,
             activation: inner.activation,
             tasks: inner.tasks,
         };
@@ -363,6 +447,7 @@ impl<'de> Deserialize<'de> for Feature {
                     .collect()
             }),
             channel_priority: inner.channel_priority,
+            solve_strategy: inner.solve_strategy,
             system_requirements: inner.system_requirements,
             pypi_options: inner.pypi_options,
             targets: Targets::from_default_and_user_defined(default_target, inner.target),
@@ -372,7 +457,7 @@ impl<'de> Deserialize<'de> for Feature {
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::{path::Path, str::FromStr};
 
     use assert_matches::assert_matches;
 
@@ -480,6 +565,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_constraints() {
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = ["linux-64", "osx-64", "win-64"]
+        channels = []
+
+        [constraints]
+        openssl = ">=3"
+
+        [target.linux-64.constraints]
+        openssl = ">=3.2"
+
+        [pypi-constraints]
+        urllib3 = ">=2"
+
+        [feature.bla.constraints]
+        zlib = "1.3.*"
+        "#,
+        )
+        .unwrap();
+
+        let openssl_constraint = |platform| {
+            manifest.default_feature().constraints(platform).unwrap()
+                [&PackageName::from_str("openssl").unwrap()]
+                .as_version_spec()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(openssl_constraint(None), ">=3");
+        assert_eq!(
+            openssl_constraint(Some(Platform::Linux64)),
+            ">=3.2",
+            "should have selected the constraint from the [linux-64] section"
+        );
+
+        assert_eq!(
+            manifest
+                .default_feature()
+                .pypi_constraints(None)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(manifest
+            .feature("bla")
+            .unwrap()
+            .constraints(None)
+            .unwrap()
+            .contains_key(&PackageName::from_str("zlib").unwrap()));
+        assert!(manifest
+            .feature("bla")
+            .unwrap()
+            .dependencies(None, None)
+            .is_none());
+    }
+
     #[test]
     pub fn test_pypi_options_manifest() {
         let manifest = Manifest::from_str(
@@ -4,7 +4,7 @@ use indexmap::IndexSet;
 use rattler_conda_types::{NamedChannelOrUrl, Platform};
 use rattler_solve::ChannelPriority;
 
-use crate::{HasManifestRef, PrioritizedChannel, SpecType};
+use crate::{HasManifestRef, PrioritizedChannel, SolveStrategy, SpecType};
 
 use crate::has_features_iter::HasFeaturesIter;
 use crate::{pypi::pypi_options::PypiOptions, SystemRequirements};
@@ -59,6 +59,18 @@ pub trait FeaturesExt<'source>: HasManifestRef<'source> + HasFeaturesIter<'sourc
         Ok(channel_priority)
     }
 
+    /// Returns the strategy used to pick the versions of packages.
+    ///
+    /// The strategy of the first feature that specifies one is used, so the
+    /// strategy of the default feature is only used if no other feature
+    /// specifies one. Conflicting strategies within a single environment are
+    /// rejected when the manifest is validated.
+    fn solve_strategy(&self) -> SolveStrategy {
+        self.features()
+            .find_map(|feature| feature.solve_strategy)
+            .unwrap_or_default()
+    }
+
     /// Returns the platforms that this collection is compatible with.
     ///
     /// Which platforms a collection support depends on which platforms the selected features of
@@ -129,6 +141,28 @@ pub trait FeaturesExt<'source>: HasManifestRef<'source> + HasFeaturesIter<'sourc
             .into()
     }
 
+    /// Returns the conda constraints for this collection.
+    ///
+    /// The constraints of all features are combined, like the dependencies.
+    /// Constraints do not add packages to the environment, they only restrict
+    /// the versions of packages that are required by something else.
+    fn constraints(&self, platform: Option<Platform>) -> CondaDependencies {
+        self.features()
+            .filter_map(|f| f.constraints(platform))
+            .into()
+    }
+
+    /// Returns the PyPi constraints for this collection.
+    ///
+    /// The constraints of all features are combined, like the dependencies.
+    /// Constraints do not add packages to the environment, they only restrict
+    /// the versions of packages that are required by something else.
+    fn pypi_constraints(&self, platform: Option<Platform>) -> PyPiDependencies {
+        self.features()
+            .filter_map(|f| f.pypi_constraints(platform))
+            .into()
+    }
+
     /// Returns the pypi options for this collection.
     ///
     /// The pypi options of all features are combined. They will be combined in the order
@@ -4,6 +4,7 @@ mod dependencies;
 mod environment;
 mod environments;
 mod error;
+mod exclude_newer;
 mod feature;
 mod features_ext;
 mod has_features_iter;
@@ -14,6 +15,7 @@ mod parsed_manifest;
 pub mod pypi;
 pub mod pyproject;
 mod solve_group;
+mod solve_strategy;
 mod spec_type;
 mod system_requirements;
 mod target;
@@ -29,10 +31,12 @@ pub use manifests::TomlManifest;
 pub use crate::environments::Environments;
 pub use crate::parsed_manifest::{deserialize_package_map, ParsedManifest};
 pub use crate::solve_group::{SolveGroup, SolveGroups};
+pub use crate::solve_strategy::SolveStrategy;
 pub use activation::Activation;
 pub use channel::{PrioritizedChannel, TomlPrioritizedChannelStrOrMap};
 pub use environment::{Environment, EnvironmentName};
 pub use error::TomlError;
+pub use exclude_newer::{ExcludeNewer, ParseExcludeNewerError};
 pub use feature::{Feature, FeatureName};
 use itertools::Itertools;
 pub use metadata::ProjectMetadata;
@@ -726,7 +726,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
-    use crate::channel::PrioritizedChannel;
+    use crate::{channel::PrioritizedChannel, SolveStrategy};
     use glob::glob;
 
     const PROJECT_BOILERPLATE: &str = r#"
@@ -2209,6 +2209,88 @@ bar = "*"
         );
     }
 
+    #[test]
+    pub fn test_exclude_newer_manifest() {
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        exclude-newer = "2024-06-01"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.parsed.project.exclude_newer.unwrap().to_string(),
+            "2024-06-01T00:00:00Z"
+        );
+
+        let error = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        exclude-newer = "yesterday"
+        "#,
+        );
+        assert!(error.is_err());
+    }
+
+    #[test]
+    pub fn test_solve_strategy_manifest() {
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+        solve-strategy = "highest"
+
+        [feature.min]
+        solve-strategy = "lowest-direct"
+
+        [environments]
+        min = ["min"]
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.default_feature().solve_strategy,
+            Some(SolveStrategy::Highest)
+        );
+        assert_eq!(
+            manifest.feature("min").unwrap().solve_strategy,
+            Some(SolveStrategy::LowestDirect)
+        );
+
+        let error = Manifest::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "foo"
+        platforms = []
+        channels = []
+
+        [feature.lowest]
+        solve-strategy = "lowest"
+
+        [feature.lowest-direct]
+        solve-strategy = "lowest-direct"
+
+        [environments]
+        min = ["lowest", "lowest-direct"]
+        "#,
+        );
+        assert!(error.is_err());
+    }
+
     #[test]
     pub fn test_unsupported_pep508_errors() {
         let manifest_error = Manifest::from_str(
@@ -8,7 +8,7 @@ use serde_with::{serde_as, DisplayFromStr};
 use url::Url;
 
 use super::pypi::pypi_options::PypiOptions;
-use crate::utils::PixiSpanned;
+use crate::{utils::PixiSpanned, ExcludeNewer, SolveStrategy};
 
 /// Describes the contents of the `[package]` section of the project manifest.
 #[serde_as]
@@ -36,6 +36,15 @@ pub struct ProjectMetadata {
     #[serde(default)]
     pub channel_priority: Option<ChannelPriority>,
 
+    /// Exclude packages that were published after this date when solving
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub exclude_newer: Option<ExcludeNewer>,
+
+    /// The strategy used to pick package versions for the whole project
+    #[serde(default)]
+    pub solve_strategy: Option<SolveStrategy>,
+
     /// The platforms this project supports
     // TODO: This is actually slightly different from the rattler_conda_types::Platform because it
     //     should not include noarch.
@@ -133,6 +133,12 @@ impl<'de> Deserialize<'de> for ParsedManifest {
             #[serde(default)]
             pypi_dependencies: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
 
+            #[serde(default, deserialize_with = "deserialize_opt_package_map")]
+            constraints: Option<IndexMap<PackageName, PixiSpec>>,
+
+            #[serde(default)]
+            pypi_constraints: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
+
             /// Additional information to activate an environment.
             #[serde(default)]
             activation: Option<Activation>,
@@ -175,6 +181,8 @@ impl<'de> Deserialize<'de> for ParsedManifest {
         let default_target = Target {
             dependencies,
             pypi_dependencies: toml_manifest.pypi_dependencies,
+            constraints: toml_manifest.constraints,
+            pypi_constraints: toml_manifest.pypi_constraints,
             activation: toml_manifest.activation,
             tasks: toml_manifest.tasks,
         };
@@ -190,6 +198,8 @@ impl<'de> Deserialize<'de> for ParsedManifest {
 
             channel_priority: toml_manifest.project.channel_priority,
 
+            solve_strategy: toml_manifest.project.solve_strategy,
+
             system_requirements: toml_manifest.system_requirements,
 
             // Use the pypi-options from the manifest for
@@ -713,6 +723,26 @@ mod tests {
             .contains("duplicate dependency"));
     }
 
+    #[test]
+    fn test_duplicate_target_constraint() {
+        let contents = format!(
+            r#"
+        {PROJECT_BOILERPLATE}
+
+        [target.linux-64.constraints]
+        LibC = "2.12"
+        libc = "2.12"
+        "#
+        );
+        let manifest = ParsedManifest::from_toml_str(&contents);
+
+        assert!(manifest.is_err());
+        assert!(manifest
+            .unwrap_err()
+            .to_string()
+            .contains("duplicate dependency"));
+    }
+
     #[test]
     fn test_tool_deserialization() {
         let contents = r#"
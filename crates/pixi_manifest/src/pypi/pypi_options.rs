@@ -1,5 +1,5 @@
 use crate::consts;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
 use rattler_lock::{FindLinksUrlOrPath, PypiIndexes};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -49,6 +49,9 @@ pub struct PypiOptions {
     pub no_build_isolation: Option<Vec<String>>,
     /// The strategy to use when resolving against multiple index URLs.
     pub index_strategy: Option<IndexStrategy>,
+    /// Named indexes that are only used for the packages that refer to them
+    /// through their `index` key
+    pub indexes: Option<IndexMap<String, Url>>,
 }
 
 /// Clones and deduplicates two iterators of values
@@ -71,6 +74,7 @@ impl PypiOptions {
         flat_indexes: Option<Vec<FindLinksUrlOrPath>>,
         no_build_isolation: Option<Vec<String>>,
         index_strategy: Option<IndexStrategy>,
+        indexes: Option<IndexMap<String, Url>>,
     ) -> Self {
         Self {
             index_url: index,
@@ -78,9 +82,15 @@ impl PypiOptions {
             find_links: flat_indexes,
             no_build_isolation,
             index_strategy,
+            indexes,
         }
     }
 
+    /// Returns the url of the index with the given name.
+    pub fn named_index(&self, name: &str) -> Option<&Url> {
+        self.indexes.as_ref().and_then(|indexes| indexes.get(name))
+    }
+
     /// Return an iterator over all [`Url`] entries.
     /// In order of:
     /// - `find_links`
@@ -174,12 +184,37 @@ impl PypiOptions {
             })
             .or_else(|| other.no_build_isolation.clone());
 
+        // Merge the named indexes, a name may only refer to a single url
+        let indexes = match (&self.indexes, &other.indexes) {
+            (Some(own_indexes), Some(other_indexes)) => {
+                let mut indexes = own_indexes.clone();
+                for (name, url) in other_indexes {
+                    match indexes.get(name) {
+                        Some(own_url) if own_url != url => {
+                            return Err(PypiOptionsMergeError::ConflictingNamedIndexes {
+                                name: name.clone(),
+                                first: own_url.to_string(),
+                                second: url.to_string(),
+                            });
+                        }
+                        Some(_) => {}
+                        None => {
+                            indexes.insert(name.clone(), url.clone());
+                        }
+                    }
+                }
+                Some(indexes)
+            }
+            (own_indexes, other_indexes) => own_indexes.clone().or_else(|| other_indexes.clone()),
+        };
+
         Ok(PypiOptions {
             index_url: index,
             extra_index_urls: extra_indexes,
             find_links: flat_indexes,
             no_build_isolation,
             index_strategy,
+            indexes,
         })
     }
 }
@@ -189,9 +224,14 @@ impl From<PypiOptions> for rattler_lock::PypiIndexes {
         let primary_index = value
             .index_url
             .unwrap_or(consts::DEFAULT_PYPI_INDEX_URL.clone());
+        // The named indexes are recorded after the extra indexes so that the
+        // packages that are pinned to them can be installed.
         Self {
             indexes: iter::once(primary_index)
                 .chain(value.extra_index_urls.into_iter().flatten())
+                .chain(value.indexes.into_iter().flat_map(IndexMap::into_values))
+                .collect::<IndexSet<_>>()
+                .into_iter()
                 .collect(),
             find_links: value.find_links.into_iter().flatten().collect(),
         }
@@ -214,6 +254,14 @@ pub enum PypiOptionsMergeError {
         "multiple index strategies are not supported, found both {first} and {second} across multiple pypi options"
     )]
     MultipleIndexStrategies { first: String, second: String },
+    #[error(
+        "the pypi index '{name}' is defined multiple times with different urls, found both {first} and {second} across multiple pypi options"
+    )]
+    ConflictingNamedIndexes {
+        name: String,
+        first: String,
+        second: String,
+    },
 }
 
 #[cfg(test)]
@@ -221,6 +269,7 @@ mod tests {
     use crate::pypi::pypi_options::IndexStrategy;
 
     use super::PypiOptions;
+    use indexmap::IndexMap;
     use rattler_lock::FindLinksUrlOrPath;
     use url::Url;
 
@@ -236,6 +285,9 @@ mod tests {
 
                  [[find-links]]
                  url = "https://flat.index"
+
+                 [indexes]
+                 internal = "https://pypi.internal.example.com/simple"
              "#;
         let deserialized_options: PypiOptions = toml_edit::de::from_str(toml_str).unwrap();
         assert_eq!(
@@ -249,6 +301,10 @@ mod tests {
                 ]),
                 no_build_isolation: Some(vec!["pkg1".to_string(), "pkg2".to_string()]),
                 index_strategy: None,
+                indexes: Some(IndexMap::from_iter([(
+                    "internal".to_string(),
+                    Url::parse("https://pypi.internal.example.com/simple").unwrap()
+                )])),
             },
         );
     }
@@ -265,6 +321,7 @@ mod tests {
             ]),
             no_build_isolation: Some(vec!["foo".to_string(), "bar".to_string()]),
             index_strategy: None,
+            indexes: None,
         };
 
         // Create the second set of options
@@ -277,6 +334,7 @@ mod tests {
             ]),
             no_build_isolation: Some(vec!["foo".to_string()]),
             index_strategy: None,
+            indexes: None,
         };
 
         // Merge the two options
@@ -294,6 +352,7 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: None,
+            indexes: None,
         };
 
         // Create the second set of options
@@ -303,6 +362,7 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: None,
+            indexes: None,
         };
 
         // Merge the two options
@@ -311,6 +371,61 @@ mod tests {
         insta::assert_snapshot!(merged_opts.err().unwrap());
     }
 
+    #[test]
+    fn test_error_on_conflicting_named_indexes() {
+        let internal = |url: &str| {
+            Some(IndexMap::from_iter([(
+                "internal".to_string(),
+                Url::parse(url).unwrap(),
+            )]))
+        };
+        let opts = PypiOptions {
+            indexes: internal("https://example.com/internal"),
+            ..PypiOptions::default()
+        };
+
+        // The same index may be defined multiple times with the same url
+        let merged_opts = opts.union(&opts.clone()).unwrap();
+        assert_eq!(merged_opts.indexes, opts.indexes);
+
+        // But not with a different url
+        let opts2 = PypiOptions {
+            indexes: internal("https://example.com/other"),
+            ..PypiOptions::default()
+        };
+        let merged_opts = opts.union(&opts2);
+        insta::assert_snapshot!(merged_opts.err().unwrap());
+    }
+
+    #[test]
+    fn test_pypi_indexes_include_named_indexes() {
+        let options = PypiOptions {
+            index_url: Some(Url::parse("https://example.com/pypi").unwrap()),
+            extra_index_urls: Some(vec![Url::parse("https://example.com/extra").unwrap()]),
+            indexes: Some(IndexMap::from_iter([
+                (
+                    "internal".to_string(),
+                    Url::parse("https://pypi.internal.example.com/simple").unwrap(),
+                ),
+                (
+                    "extra".to_string(),
+                    Url::parse("https://example.com/extra").unwrap(),
+                ),
+            ])),
+            ..Default::default()
+        };
+
+        let indexes = rattler_lock::PypiIndexes::from(options);
+        assert_eq!(
+            indexes.indexes,
+            vec![
+                Url::parse("https://example.com/pypi").unwrap(),
+                Url::parse("https://example.com/extra").unwrap(),
+                Url::parse("https://pypi.internal.example.com/simple").unwrap(),
+            ]
+        );
+    }
+
     #[test]
     fn test_error_on_multiple_index_strategies() {
         // Create the first set of options
@@ -320,6 +435,7 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: Some(IndexStrategy::FirstIndex),
+            indexes: None,
         };
 
         // Create the second set of options
@@ -329,6 +445,7 @@ mod tests {
             find_links: None,
             no_build_isolation: None,
             index_strategy: Some(IndexStrategy::UnsafeBestMatch),
+            indexes: None,
         };
 
         // Merge the two options
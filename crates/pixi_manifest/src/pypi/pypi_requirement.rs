@@ -2,7 +2,7 @@ use super::{pypi_requirement_types::GitRevParseError, GitRev, VersionOrStar};
 use crate::utils::extract_directory_from_url;
 use crate::PyPiRequirement::RawVersion;
 use pep440_rs::VersionSpecifiers;
-use pep508_rs::ExtraName;
+use pep508_rs::{ExtraName, MarkerTree};
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
@@ -68,27 +68,60 @@ pub enum PyPiRequirement {
         url: ParsedGitUrl,
         #[serde(default)]
         extras: Vec<ExtraName>,
+        #[serde(
+            skip_serializing_if = "MarkerTree::is_true",
+            serialize_with = "serialize_markers"
+        )]
+        markers: MarkerTree,
     },
     Path {
         path: PathBuf,
         editable: Option<bool>,
         #[serde(default)]
         extras: Vec<ExtraName>,
+        #[serde(
+            skip_serializing_if = "MarkerTree::is_true",
+            serialize_with = "serialize_markers"
+        )]
+        markers: MarkerTree,
     },
     Url {
         url: Url,
         subdirectory: Option<String>,
         #[serde(default)]
         extras: Vec<ExtraName>,
+        #[serde(
+            skip_serializing_if = "MarkerTree::is_true",
+            serialize_with = "serialize_markers"
+        )]
+        markers: MarkerTree,
     },
     Version {
         version: VersionOrStar,
         #[serde(default)]
         extras: Vec<ExtraName>,
+        /// The name of the index in the `pypi-options` to take the package
+        /// from.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        index: Option<String>,
+        #[serde(
+            skip_serializing_if = "MarkerTree::is_true",
+            serialize_with = "serialize_markers"
+        )]
+        markers: MarkerTree,
     },
     RawVersion(VersionOrStar),
 }
 
+/// Serializes the environment markers of a requirement as a PEP 508 marker
+/// expression.
+fn serialize_markers<S: serde::Serializer>(
+    markers: &MarkerTree,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&markers.try_to_string().unwrap_or_default())
+}
+
 /// Returns a more helpful message when a version requirement is used incorrectly.
 fn version_requirement_error<T: Into<String>>(input: T) -> Option<impl Display> {
     let input = input.into();
@@ -123,6 +156,14 @@ struct RawPyPiRequirement {
     #[serde(default)]
     extras: Vec<ExtraName>,
 
+    /// The environment markers that determine whether the requirement applies
+    /// (e.g. `sys_platform == 'linux' and python_version < '3.11'`)
+    #[serde_as(as = "Option<serde_with::DisplayFromStr>")]
+    pub markers: Option<MarkerTree>,
+
+    // Version only
+    pub index: Option<String>,
+
     // Path Only
     pub path: Option<PathBuf>,
     pub editable: Option<bool>,
@@ -171,6 +212,12 @@ impl<'de> Deserialize<'de> for PyPiRequirement {
                 let is_path = raw_req.path.is_some();
                 let is_url = raw_req.url.is_some();
 
+                if raw_req.index.is_some() && (is_git || is_path || is_url) {
+                    return Err(serde_untagged::de::Error::custom(
+                        "`index` can only be used with version requirements",
+                    ));
+                }
+
                 let git_key = is_git.then_some("`git`");
                 let path_key = is_path.then_some("`path`");
                 let url_key = is_url.then_some("`url`");
@@ -186,16 +233,19 @@ impl<'de> Deserialize<'de> for PyPiRequirement {
                     )));
                 }
 
+                let markers = raw_req.markers.unwrap_or_default();
                 let req = match (raw_req.url, raw_req.path, raw_req.git, raw_req.extras) {
                     (Some(url), None, None, extras) => PyPiRequirement::Url {
                         url,
                         extras,
                         subdirectory: raw_req.subdirectory,
+                        markers,
                     },
                     (None, Some(path), None, extras) => PyPiRequirement::Path {
                         path,
                         editable: raw_req.editable,
                         extras,
+                        markers,
                     },
                     (None, None, Some(git), extras) => PyPiRequirement::Git {
                         url: ParsedGitUrl {
@@ -206,14 +256,19 @@ impl<'de> Deserialize<'de> for PyPiRequirement {
                             subdirectory: raw_req.subdirectory,
                         },
                         extras,
+                        markers,
                     },
                     (None, None, None, extras) => PyPiRequirement::Version {
                         version: raw_req.version.unwrap_or(VersionOrStar::Star),
                         extras,
+                        index: raw_req.index,
+                        markers,
                     },
                     (_, _, _, extras) if !extras.is_empty() => PyPiRequirement::Version {
                         version: raw_req.version.unwrap_or(VersionOrStar::Star),
                         extras,
+                        index: raw_req.index,
+                        markers,
                     },
                     _ => {
                         return Err(serde_untagged::de::Error::custom(
@@ -278,17 +333,43 @@ impl From<PyPiRequirement> for toml_edit::Value {
             }
         }
 
+        fn insert_markers(table: &mut toml_edit::InlineTable, markers: &MarkerTree) {
+            if let Some(markers) = markers.try_to_string() {
+                table.insert(
+                    "markers",
+                    toml_edit::Value::String(toml_edit::Formatted::new(markers)),
+                );
+            }
+        }
+
         match &val {
-            PyPiRequirement::Version { version, extras } if extras.is_empty() => {
+            PyPiRequirement::Version {
+                version,
+                extras,
+                index: None,
+                markers,
+            } if extras.is_empty() && markers.is_true() => {
                 toml_edit::Value::from(version.to_string())
             }
-            PyPiRequirement::Version { version, extras } => {
+            PyPiRequirement::Version {
+                version,
+                extras,
+                index,
+                markers,
+            } => {
                 let mut table = toml_edit::Table::new().into_inline_table();
                 table.insert(
                     "version",
                     toml_edit::Value::String(toml_edit::Formatted::new(version.to_string())),
                 );
                 insert_extras(&mut table, extras);
+                if let Some(index) = index {
+                    table.insert(
+                        "index",
+                        toml_edit::Value::String(toml_edit::Formatted::new(index.clone())),
+                    );
+                }
+                insert_markers(&mut table, markers);
                 toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::Git {
@@ -301,6 +382,7 @@ impl From<PyPiRequirement> for toml_edit::Value {
                         subdirectory,
                     },
                 extras,
+                markers,
             } => {
                 let mut table = toml_edit::Table::new().into_inline_table();
                 table.insert(
@@ -334,12 +416,14 @@ impl From<PyPiRequirement> for toml_edit::Value {
                     );
                 }
                 insert_extras(&mut table, extras);
+                insert_markers(&mut table, markers);
                 toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::Path {
                 path,
                 editable,
                 extras,
+                markers,
             } => {
                 let mut table = toml_edit::Table::new().into_inline_table();
                 table.insert(
@@ -355,12 +439,14 @@ impl From<PyPiRequirement> for toml_edit::Value {
                     );
                 }
                 insert_extras(&mut table, extras);
+                insert_markers(&mut table, markers);
                 toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::Url {
                 url,
                 extras,
                 subdirectory,
+                markers,
             } => {
                 let mut table = toml_edit::Table::new().into_inline_table();
                 table.insert(
@@ -376,6 +462,7 @@ impl From<PyPiRequirement> for toml_edit::Value {
                     );
                 }
                 insert_extras(&mut table, extras);
+                insert_markers(&mut table, markers);
                 toml_edit::Value::InlineTable(table.to_owned())
             }
             PyPiRequirement::RawVersion(version) => {
@@ -418,11 +505,14 @@ impl From<VersionSpecifiers> for VersionOrStar {
 impl TryFrom<pep508_rs::Requirement> for PyPiRequirement {
     type Error = Pep508ToPyPiRequirementError;
     fn try_from(req: pep508_rs::Requirement) -> Result<Self, Self::Error> {
+        let markers = req.marker;
         let converted = if let Some(version_or_url) = req.version_or_url {
             match version_or_url {
                 pep508_rs::VersionOrUrl::VersionSpecifier(v) => PyPiRequirement::Version {
                     version: v.into(),
                     extras: req.extras,
+                    index: None,
+                    markers,
                 },
                 pep508_rs::VersionOrUrl::Url(u) => {
                     let url = u.to_url();
@@ -431,6 +521,7 @@ impl TryFrom<pep508_rs::Requirement> for PyPiRequirement {
                             "git" => Self::Git {
                                 url: ParsedGitUrl::try_from(url)?,
                                 extras: req.extras,
+                                markers,
                             },
                             "bzr" => {
                                 return Err(Pep508ToPyPiRequirementError::UnsupportedUrlPrefix {
@@ -469,6 +560,7 @@ impl TryFrom<pep508_rs::Requirement> for PyPiRequirement {
                         Self::Git {
                             url: parsed_url,
                             extras: req.extras,
+                            markers,
                         }
                     } else if url.scheme().eq_ignore_ascii_case("file") {
                         // Convert the file url to a path.
@@ -479,6 +571,7 @@ impl TryFrom<pep508_rs::Requirement> for PyPiRequirement {
                             path: file,
                             editable: None,
                             extras: req.extras,
+                            markers,
                         }
                     } else {
                         let subdirectory = extract_directory_from_url(&url);
@@ -486,14 +579,17 @@ impl TryFrom<pep508_rs::Requirement> for PyPiRequirement {
                             url,
                             extras: req.extras,
                             subdirectory,
+                            markers,
                         }
                     }
                 }
             }
-        } else if !req.extras.is_empty() {
+        } else if !req.extras.is_empty() || !markers.is_true() {
             PyPiRequirement::Version {
                 version: VersionOrStar::Star,
                 extras: req.extras,
+                index: None,
+                markers,
             }
         } else {
             PyPiRequirement::RawVersion(VersionOrStar::Star)
@@ -527,6 +623,26 @@ impl PyPiRequirement {
         }
     }
 
+    /// Returns the name of the index this requirement is pinned to, if any.
+    pub fn index(&self) -> Option<&str> {
+        match self {
+            PyPiRequirement::Version { index, .. } => index.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the environment markers that determine whether the requirement
+    /// applies. Requirements without markers always apply.
+    pub fn markers(&self) -> MarkerTree {
+        match self {
+            PyPiRequirement::Version { markers, .. }
+            | PyPiRequirement::Git { markers, .. }
+            | PyPiRequirement::Path { markers, .. }
+            | PyPiRequirement::Url { markers, .. } => markers.clone(),
+            PyPiRequirement::RawVersion(_) => MarkerTree::default(),
+        }
+    }
+
     pub fn extras(&self) -> &[ExtraName] {
         match self {
             PyPiRequirement::Version { extras, .. } => extras,
@@ -557,7 +673,7 @@ mod tests {
         let pypi = PyPiRequirement::try_from(req).unwrap();
         assert_eq!(
             pypi.to_string(),
-            "{ version = \"==1.0.0\", extras = [\"testing\"] }"
+            "{ version = \"==1.0.0\", extras = [\"testing\"], markers = \"os_name == 'posix'\" }"
         );
 
         let req = pep508_rs::Requirement::from_str("numpy").unwrap();
@@ -616,6 +732,8 @@ mod tests {
             &PyPiRequirement::Version {
                 version: ">=3.12".parse().unwrap(),
                 extras: vec![ExtraName::from_str("bar").unwrap()],
+                index: None,
+                markers: MarkerTree::default(),
             }
         );
 
@@ -636,6 +754,8 @@ mod tests {
                     ExtraName::from_str("bar").unwrap(),
                     ExtraName::from_str("foo").unwrap(),
                 ],
+                index: None,
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -659,6 +779,8 @@ mod tests {
                     ExtraName::from_str("feature1").unwrap(),
                     ExtraName::from_str("feature2").unwrap()
                 ],
+                index: None,
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -698,6 +820,7 @@ mod tests {
                 path: PathBuf::from("../numpy-test"),
                 editable: None,
                 extras: vec![],
+                markers: MarkerTree::default(),
             },
         );
     }
@@ -715,6 +838,7 @@ mod tests {
                 path: PathBuf::from("../numpy-test"),
                 editable: Some(true),
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -741,6 +865,7 @@ mod tests {
                 url: Url::parse("https://test.url.com").unwrap(),
                 extras: vec![],
                 subdirectory: None,
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -764,6 +889,7 @@ mod tests {
                     subdirectory: None,
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -787,6 +913,7 @@ mod tests {
                     subdirectory: None,
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -810,6 +937,7 @@ mod tests {
                     subdirectory: None,
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -833,6 +961,7 @@ mod tests {
                     subdirectory: None,
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             },
         );
     }
@@ -856,6 +985,7 @@ mod tests {
                     subdirectory: None,
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -886,7 +1016,8 @@ mod tests {
                     rev: None,
                     subdirectory: None,
                 },
-                extras: vec![]
+                extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
 
@@ -904,17 +1035,18 @@ mod tests {
                     )),
                     subdirectory: None,
                 },
-                extras: vec![]
+                extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
 
         let pypi: Requirement = "boltons @ https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl".parse().unwrap();
         let as_pypi_req: PyPiRequirement = pypi.try_into().unwrap();
-        assert_eq!(as_pypi_req, PyPiRequirement::Url { url: Url::parse("https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl").unwrap(), extras: vec![], subdirectory: None });
+        assert_eq!(as_pypi_req, PyPiRequirement::Url { url: Url::parse("https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl").unwrap(), extras: vec![], subdirectory: None, markers: MarkerTree::default() });
 
         let pypi: Requirement = "boltons[nichita] @ https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl".parse().unwrap();
         let as_pypi_req: PyPiRequirement = pypi.try_into().unwrap();
-        assert_eq!(as_pypi_req, PyPiRequirement::Url { url: Url::parse("https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl").unwrap(), extras: vec![ExtraName::new("nichita".to_string()).unwrap()], subdirectory: None });
+        assert_eq!(as_pypi_req, PyPiRequirement::Url { url: Url::parse("https://files.pythonhosted.org/packages/46/35/e50d4a115f93e2a3fbf52438435bb2efcf14c11d4fcd6bdcd77a6fc399c9/boltons-24.0.0-py3-none-any.whl").unwrap(), extras: vec![ExtraName::new("nichita".to_string()).unwrap()], subdirectory: None, markers: MarkerTree::default() });
 
         #[cfg(target_os = "windows")]
         let pypi: Requirement = "boltons @ file:///C:/path/to/boltons".parse().unwrap();
@@ -929,7 +1061,8 @@ mod tests {
             PyPiRequirement::Path {
                 path: PathBuf::from("C:/path/to/boltons"),
                 editable: None,
-                extras: vec![]
+                extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
         #[cfg(not(target_os = "windows"))]
@@ -938,7 +1071,8 @@ mod tests {
             PyPiRequirement::Path {
                 path: PathBuf::from("/path/to/boltons"),
                 editable: None,
-                extras: vec![]
+                extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
@@ -972,15 +1106,51 @@ mod tests {
                     subdirectory: Some("python/ribasim".to_string()),
                 },
                 extras: vec![],
+                markers: MarkerTree::default(),
             }
         );
     }
 
+    #[test]
+    fn test_deserialize_markers() {
+        let requirement: IndexMap<PyPiPackageName, PyPiRequirement> = toml_edit::de::from_str(
+            r#"
+                foo = { version = ">=1.0", markers = "sys_platform == 'linux' and python_version < '3.11'" }
+                bar = { path = "../bar", markers = "python_version >= '3.11'" }
+                baz = ">=2.0"
+            "#,
+        )
+        .unwrap();
+
+        let markers = requirement
+            .values()
+            .map(|req| req.markers())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            markers[0],
+            MarkerTree::from_str("sys_platform == 'linux' and python_version < '3.11'").unwrap()
+        );
+        assert_eq!(
+            markers[1],
+            MarkerTree::from_str("python_version >= '3.11'").unwrap()
+        );
+        assert!(markers[2].is_true());
+
+        // The markers survive a roundtrip through the manifest
+        let roundtrip: PyPiRequirement =
+            toml_edit::de::from_str(&format!("value = {}", requirement[0]))
+                .map(|table: IndexMap<String, PyPiRequirement>| table["value"].clone())
+                .unwrap();
+        assert_eq!(roundtrip, requirement[0]);
+    }
+
     #[test]
     fn test_deserialize_succeeding() {
         let examples = [
             json! { "==1.2.3" },
             json!({ "version": "==1.2.3" }),
+            json!({ "version": "==1.2.3", "index": "internal" }),
+            json!({ "version": "==1.2.3", "markers": "sys_platform == 'linux'" }),
             json! { "*" },
             json!({ "path": "foobar" }),
             json!({ "path": "~/.cache" }),
@@ -1012,6 +1182,7 @@ mod tests {
         let examples = [
             json!({ "ver": "1.2.3" }),
             json!({ "path": "foobar", "version": "==1.2.3" }),
+            json!({ "path": "foobar", "index": "internal" }),
             json!({ "version": "//" }),
             json!({ "git": "https://github.com/conda-forge/21cmfast-feedstock", "branch": "main", "tag": "v1" }),
             json!({ "git": "https://github.com/conda-forge/21cmfast-feedstock", "branch": "main", "tag": "v1", "rev": "123456" }),
@@ -0,0 +1,50 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// The strategy that is used to pick the versions of packages when solving an
+/// environment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SolveStrategy {
+    /// Select the highest compatible version of all packages.
+    #[default]
+    Highest,
+
+    /// Select the lowest compatible version of all packages.
+    Lowest,
+
+    /// Select the lowest compatible version of the direct dependencies and the
+    /// highest compatible version of the transitive dependencies.
+    LowestDirect,
+}
+
+impl Display for SolveStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SolveStrategy::Highest => "highest",
+            SolveStrategy::Lowest => "lowest",
+            SolveStrategy::LowestDirect => "lowest-direct",
+        })
+    }
+}
+
+impl From<SolveStrategy> for rattler_solve::SolveStrategy {
+    fn from(value: SolveStrategy) -> Self {
+        match value {
+            SolveStrategy::Highest => rattler_solve::SolveStrategy::Highest,
+            SolveStrategy::Lowest => rattler_solve::SolveStrategy::LowestVersion,
+            SolveStrategy::LowestDirect => rattler_solve::SolveStrategy::LowestVersionDirect,
+        }
+    }
+}
+
+impl From<SolveStrategy> for rattler_lock::SolveStrategy {
+    fn from(value: SolveStrategy) -> Self {
+        match value {
+            SolveStrategy::Highest => rattler_lock::SolveStrategy::Highest,
+            SolveStrategy::Lowest => rattler_lock::SolveStrategy::LowestVersion,
+            SolveStrategy::LowestDirect => rattler_lock::SolveStrategy::LowestVersionDirect,
+        }
+    }
+}
@@ -10,6 +10,7 @@ use serde_with::serde_as;
 use super::error::DependencyError;
 use crate::{
     activation::Activation,
+    parsed_manifest::deserialize_opt_package_map,
     pypi::PyPiPackageName,
     task::{Task, TaskName},
     utils::PixiSpanned,
@@ -27,6 +28,14 @@ pub struct Target {
     /// Specific python dependencies
     pub pypi_dependencies: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
 
+    /// Constraints on the versions of conda packages. Unlike dependencies,
+    /// these only apply if the package is required by something else.
+    pub constraints: Option<IndexMap<PackageName, PixiSpec>>,
+
+    /// Constraints on the versions of python packages. Unlike dependencies,
+    /// these only apply if the package is required by something else.
+    pub pypi_constraints: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
+
     /// Additional information to activate an environment.
     pub activation: Option<Activation>,
 
@@ -353,6 +362,12 @@ impl<'de> Deserialize<'de> for Target {
             #[serde(default)]
             pypi_dependencies: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
 
+            #[serde(default, deserialize_with = "deserialize_opt_package_map")]
+            constraints: Option<IndexMap<PackageName, PixiSpec>>,
+
+            #[serde(default)]
+            pypi_constraints: Option<IndexMap<PyPiPackageName, PyPiRequirement>>,
+
             /// Additional information to activate an environment.
             #[serde(default)]
             activation: Option<Activation>,
@@ -375,6 +390,8 @@ impl<'de> Deserialize<'de> for Target {
         Ok(Self {
             dependencies,
             pypi_dependencies: target.pypi_dependencies,
+            constraints: target.constraints,
+            pypi_constraints: target.pypi_constraints,
             activation: target.activation,
             tasks: target.tasks,
         })
@@ -1,10 +1,11 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_with::{formats::PreferMany, serde_as, OneOrMany};
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use toml_edit::{Array, Item, Table, Value};
 
 /// Represents a task name
@@ -39,12 +40,196 @@ impl From<TaskName> for String {
     }
 }
 
+/// A dependency of a task on another task, optionally passing values for the
+/// arguments of the other task.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Dependency {
+    /// The name of the task that is depended on.
+    pub task_name: TaskName,
+
+    /// The values of the arguments to pass to the task, in the order in which
+    /// the arguments are declared. `None` if the task is invoked without
+    /// arguments.
+    pub args: Option<Vec<String>>,
+}
+
+impl Dependency {
+    /// Constructs a new dependency on a task with the given arguments.
+    pub fn new(task_name: impl Into<TaskName>, args: Option<Vec<String>>) -> Self {
+        Self {
+            task_name: task_name.into(),
+            args,
+        }
+    }
+
+    /// Returns the name of the task that is depended on.
+    pub fn as_str(&self) -> &str {
+        self.task_name.as_str()
+    }
+}
+
+impl From<TaskName> for Dependency {
+    fn from(task_name: TaskName) -> Self {
+        Self {
+            task_name,
+            args: None,
+        }
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(name: &str) -> Self {
+        TaskName::from(name).into()
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.args {
+            Some(args) => write!(f, "{}({})", self.task_name, args.iter().format(", ")),
+            None => write!(f, "{}", self.task_name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Dependency {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct TomlDependency {
+            task: TaskName,
+            args: Option<Vec<String>>,
+        }
+
+        serde_untagged::UntaggedEnumVisitor::new()
+            .string(|name| Ok(TaskName::from(name).into()))
+            .map(|map| {
+                map.deserialize()
+                    .map(|dependency: TomlDependency| Dependency {
+                        task_name: dependency.task,
+                        args: dependency.args,
+                    })
+            })
+            .expecting("either a task name or a map with `task` and `args`")
+            .deserialize(deserializer)
+    }
+}
+
+/// A named argument of a task. The value of the argument can be used in the
+/// command of the task through templating, e.g. `cmd = "cargo build
+/// --profile {{ profile }}"`.
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TaskArg {
+    /// The name of the argument.
+    pub name: String,
+
+    /// The value of the argument if no value is passed. If there is no
+    /// default the argument is required.
+    pub default: Option<String>,
+}
+
+/// Determines how often a failing task is retried.
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// The number of times the task is retried after it failed.
+    pub count: u32,
+
+    /// The time to wait before the first retry. The time is doubled for every
+    /// subsequent retry.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns the time to wait before the given retry, starting at zero.
+    pub fn backoff_for(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// The time to wait for a service to become ready when no timeout is given.
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Determines when a service is ready to be used by the tasks that depend on
+/// it.
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "TomlReadinessProbe")]
+pub struct ReadinessProbe {
+    /// The check that succeeds once the service is ready.
+    pub check: ReadinessCheck,
+
+    /// The maximum time to wait for the service to become ready.
+    pub timeout: Duration,
+}
+
+/// The check that is used to determine whether a service is ready.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ReadinessCheck {
+    /// The service accepts TCP connections on this port of the local host.
+    Port(u16),
+
+    /// The service printed a line that matches this regular expression.
+    Log(String),
+
+    /// This command exits successfully.
+    Command(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlReadinessProbe {
+    port: Option<u16>,
+    log: Option<String>,
+    cmd: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    timeout: Option<Duration>,
+}
+
+impl TryFrom<TomlReadinessProbe> for ReadinessProbe {
+    type Error = String;
+
+    fn try_from(value: TomlReadinessProbe) -> Result<Self, Self::Error> {
+        let check = match (value.port, value.log, value.cmd) {
+            (Some(port), None, None) => ReadinessCheck::Port(port),
+            (None, Some(log), None) => {
+                regex::Regex::new(&log).map_err(|err| err.to_string())?;
+                ReadinessCheck::Log(log)
+            }
+            (None, None, Some(cmd)) => ReadinessCheck::Command(cmd),
+            _ => return Err("expected exactly one of `port`, `log` or `cmd`".to_string()),
+        };
+        Ok(Self {
+            check,
+            timeout: value.timeout.unwrap_or(DEFAULT_READY_TIMEOUT),
+        })
+    }
+}
+
+/// Deserializes a human readable duration like `10m` or `1h 30m`.
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let duration = String::deserialize(deserializer)?;
+    humantime::parse_duration(&duration).map_err(serde::de::Error::custom)
+}
+
+/// Deserializes an optional human readable duration.
+fn deserialize_optional_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    deserialize_duration(deserializer).map(Some)
+}
+
 /// Represents different types of scripts
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum Task {
     Plain(String),
     Execute(Execute),
+    Service(Service),
     Alias(Alias),
     // We want a way for the deserializer to except a custom task, as they are meant for tasks
     // given in the command line.
@@ -53,11 +238,12 @@ pub enum Task {
 }
 
 impl Task {
-    /// Returns the names of the task that this task depends on
-    pub fn depends_on(&self) -> &[TaskName] {
+    /// Returns the tasks that this task depends on
+    pub fn depends_on(&self) -> &[Dependency] {
         match self {
             Task::Plain(_) | Task::Custom(_) => &[],
             Task::Execute(cmd) => &cmd.depends_on,
+            Task::Service(service) => &service.depends_on,
             Task::Alias(cmd) => &cmd.depends_on,
         }
     }
@@ -78,6 +264,14 @@ impl Task {
         }
     }
 
+    /// If this task is a service, returns the `Service` task.
+    pub fn as_service(&self) -> Option<&Service> {
+        match self {
+            Task::Service(service) => Some(service),
+            _ => None,
+        }
+    }
+
     /// If this command is an alias, returns the `Alias` task.
     pub fn as_alias(&self) -> Option<&Alias> {
         match self {
@@ -89,7 +283,7 @@ impl Task {
     /// Returns true if this task is directly executable
     pub fn is_executable(&self) -> bool {
         match self {
-            Task::Plain(_) | Task::Custom(_) | Task::Execute(_) => true,
+            Task::Plain(_) | Task::Custom(_) | Task::Execute(_) | Task::Service(_) => true,
             Task::Alias(_) => false,
         }
     }
@@ -100,6 +294,7 @@ impl Task {
             Task::Plain(str) => Some(CmdArgs::Single(str.clone())),
             Task::Custom(custom) => Some(custom.cmd.clone()),
             Task::Execute(exe) => Some(exe.cmd.clone()),
+            Task::Service(service) => Some(service.cmd.clone()),
             Task::Alias(_) => None,
         }
     }
@@ -110,6 +305,7 @@ impl Task {
             Task::Plain(str) => Some(Cow::Borrowed(str)),
             Task::Custom(custom) => Some(custom.cmd.as_single()),
             Task::Execute(exe) => Some(exe.cmd.as_single()),
+            Task::Service(service) => Some(service.cmd.as_single()),
             Task::Alias(_) => None,
         }
     }
@@ -120,16 +316,35 @@ impl Task {
             Task::Plain(_) => None,
             Task::Custom(_) => None,
             Task::Execute(exe) => exe.env.as_ref(),
+            Task::Service(service) => service.env.as_ref(),
             Task::Alias(_) => None,
         }
     }
 
+    /// Returns the dotenv files to load before the task runs.
+    pub fn env_files(&self) -> &[PathBuf] {
+        match self {
+            Task::Execute(exe) => exe.env_file.as_deref().unwrap_or_default(),
+            Task::Service(service) => service.env_file.as_deref().unwrap_or_default(),
+            Task::Plain(_) | Task::Custom(_) | Task::Alias(_) => &[],
+        }
+    }
+
+    /// Returns the arguments that can be passed to the task.
+    pub fn args(&self) -> &[TaskArg] {
+        match self {
+            Task::Execute(exe) => &exe.args,
+            Task::Plain(_) | Task::Custom(_) | Task::Service(_) | Task::Alias(_) => &[],
+        }
+    }
+
     /// Returns the working directory for the task to run in.
     pub fn working_directory(&self) -> Option<&Path> {
         match self {
             Task::Plain(_) => None,
             Task::Custom(custom) => custom.cwd.as_deref(),
             Task::Execute(exe) => exe.cwd.as_deref(),
+            Task::Service(service) => service.cwd.as_deref(),
             Task::Alias(_) => None,
         }
     }
@@ -140,6 +355,7 @@ impl Task {
             Task::Plain(_) => None,
             Task::Custom(_) => None,
             Task::Execute(exe) => exe.description.as_deref(),
+            Task::Service(service) => service.description.as_deref(),
             Task::Alias(_) => None,
         }
     }
@@ -155,9 +371,34 @@ impl Task {
             Task::Plain(_) => false,
             Task::Custom(_) => false,
             Task::Execute(execute) => execute.clean_env,
+            Task::Service(service) => service.clean_env,
             Task::Alias(_) => false,
         }
     }
+
+    /// Returns the maximum duration of the task after which it is killed.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            Task::Execute(execute) => execute.timeout,
+            _ => None,
+        }
+    }
+
+    /// Returns how often the task is retried when it fails.
+    pub fn retries(&self) -> Option<&RetryPolicy> {
+        match self {
+            Task::Execute(execute) => execute.retries.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the condition that determines whether the task is run.
+    pub fn condition(&self) -> Option<&str> {
+        match self {
+            Task::Execute(execute) => execute.condition.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 /// A command script executes a single command from the environment
@@ -179,7 +420,12 @@ pub struct Execute {
     // BREAK: Make the remove the alias and force kebab-case
     #[serde(default, alias = "depends_on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
+
+    /// The named arguments of the task. Their values are substituted in the
+    /// command.
+    #[serde(default)]
+    pub args: Vec<TaskArg>,
 
     /// The working directory for the command relative to the root of the project.
     pub cwd: Option<PathBuf>,
@@ -187,12 +433,29 @@ pub struct Execute {
     /// A list of environment variables to set before running the command
     pub env: Option<IndexMap<String, String>>,
 
+    /// Dotenv files to load before running the command. Variables in `env`
+    /// take precedence over the variables in these files.
+    pub env_file: Option<Vec<PathBuf>>,
+
     /// A description of the task
     pub description: Option<String>,
 
     /// Isolate the task from the running machine
     #[serde(default)]
     pub clean_env: bool,
+
+    /// An expression that determines whether the task is run, e.g.
+    /// `env.CI == "true"` or `exists("build/")`. The task is skipped when the
+    /// expression evaluates to false.
+    pub condition: Option<String>,
+
+    /// The maximum duration of the task, e.g. `10m`. The task is killed and
+    /// fails when it runs longer.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+
+    /// How often the task is retried when it fails or times out.
+    pub retries: Option<RetryPolicy>,
 }
 
 impl From<Execute> for Task {
@@ -201,6 +464,49 @@ impl From<Execute> for Task {
     }
 }
 
+/// A service is a long running command, like a database or a development
+/// server. It is started in the background and the tasks that depend on it are
+/// run once it is ready. The service is stopped when these tasks are finished.
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Service {
+    /// A list of arguments, the first argument denotes the command to run. When deserializing both
+    /// an array of strings and a single string are supported.
+    pub cmd: CmdArgs,
+
+    /// Determines when the service is ready to be used.
+    pub ready: ReadinessProbe,
+
+    /// A list of commands that should be run before this one
+    #[serde(default)]
+    #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
+    pub depends_on: Vec<Dependency>,
+
+    /// The working directory for the command relative to the root of the project.
+    pub cwd: Option<PathBuf>,
+
+    /// A list of environment variables to set before running the command
+    pub env: Option<IndexMap<String, String>>,
+
+    /// Dotenv files to load before running the command. Variables in `env`
+    /// take precedence over the variables in these files.
+    pub env_file: Option<Vec<PathBuf>>,
+
+    /// A description of the service
+    pub description: Option<String>,
+
+    /// Isolate the service from the running machine
+    #[serde(default)]
+    pub clean_env: bool,
+}
+
+impl From<Service> for Task {
+    fn from(value: Service) -> Self {
+        Task::Service(value)
+    }
+}
+
 /// A custom command script executes a single command in the environment
 #[derive(Debug, Clone)]
 pub struct Custom {
@@ -262,7 +568,7 @@ pub struct Alias {
     /// A list of commands that should be run before this one
     #[serde(alias = "depends-on")]
     #[serde_as(deserialize_as = "OneOrMany<_, PreferMany>")]
-    pub depends_on: Vec<TaskName>,
+    pub depends_on: Vec<Dependency>,
 
     /// A description of the task.
     pub description: Option<String>,
@@ -278,6 +584,7 @@ impl Display for Task {
                 CmdArgs::Single(cmd) => write!(f, "{}", cmd)?,
                 CmdArgs::Multiple(mult) => write!(f, "{}", mult.join(" "))?,
             },
+            Task::Service(service) => write!(f, "{}", service.cmd.as_single())?,
             _ => {}
         };
 
@@ -345,23 +652,113 @@ impl From<Task> for Item {
                     table.insert(
                         "depends-on",
                         Value::Array(Array::from_iter(
-                            process
-                                .depends_on
-                                .into_iter()
-                                .map(String::from)
-                                .map(Value::from),
+                            process.depends_on.into_iter().map(Value::from),
                         )),
                     );
                 }
+                if !process.args.is_empty() {
+                    table.insert(
+                        "args",
+                        Value::Array(Array::from_iter(process.args.into_iter().map(|arg| {
+                            let mut arg_table = Table::new().into_inline_table();
+                            arg_table.insert("name", arg.name.into());
+                            if let Some(default) = arg.default {
+                                arg_table.insert("default", default.into());
+                            }
+                            Value::InlineTable(arg_table)
+                        }))),
+                    );
+                }
                 if let Some(cwd) = process.cwd {
                     table.insert("cwd", cwd.to_string_lossy().to_string().into());
                 }
                 if let Some(env) = process.env {
                     table.insert("env", Value::InlineTable(env.into_iter().collect()));
                 }
+                if let Some(env_file) = process.env_file {
+                    table.insert("env-file", env_file_value(env_file));
+                }
                 if let Some(description) = process.description {
                     table.insert("description", description.into());
                 }
+                if let Some(condition) = process.condition {
+                    table.insert("condition", condition.into());
+                }
+                if let Some(timeout) = process.timeout {
+                    table.insert(
+                        "timeout",
+                        humantime::format_duration(timeout).to_string().into(),
+                    );
+                }
+                if let Some(retries) = process.retries {
+                    let mut retries_table = Table::new().into_inline_table();
+                    retries_table.insert("count", i64::from(retries.count).into());
+                    if !retries.backoff.is_zero() {
+                        retries_table.insert(
+                            "backoff",
+                            humantime::format_duration(retries.backoff)
+                                .to_string()
+                                .into(),
+                        );
+                    }
+                    table.insert("retries", Value::InlineTable(retries_table));
+                }
+                Item::Value(Value::InlineTable(table))
+            }
+            Task::Service(service) => {
+                let mut table = Table::new().into_inline_table();
+                match service.cmd {
+                    CmdArgs::Single(cmd_str) => {
+                        table.insert("cmd", cmd_str.into());
+                    }
+                    CmdArgs::Multiple(cmd_strs) => {
+                        table.insert("cmd", Value::Array(Array::from_iter(cmd_strs)));
+                    }
+                }
+                let mut ready_table = Table::new().into_inline_table();
+                match service.ready.check {
+                    ReadinessCheck::Port(port) => {
+                        ready_table.insert("port", i64::from(port).into());
+                    }
+                    ReadinessCheck::Log(log) => {
+                        ready_table.insert("log", log.into());
+                    }
+                    ReadinessCheck::Command(cmd) => {
+                        ready_table.insert("cmd", cmd.into());
+                    }
+                }
+                if service.ready.timeout != DEFAULT_READY_TIMEOUT {
+                    ready_table.insert(
+                        "timeout",
+                        humantime::format_duration(service.ready.timeout)
+                            .to_string()
+                            .into(),
+                    );
+                }
+                table.insert("ready", Value::InlineTable(ready_table));
+                if !service.depends_on.is_empty() {
+                    table.insert(
+                        "depends-on",
+                        Value::Array(Array::from_iter(
+                            service.depends_on.into_iter().map(Value::from),
+                        )),
+                    );
+                }
+                if let Some(cwd) = service.cwd {
+                    table.insert("cwd", cwd.to_string_lossy().to_string().into());
+                }
+                if let Some(env) = service.env {
+                    table.insert("env", Value::InlineTable(env.into_iter().collect()));
+                }
+                if let Some(env_file) = service.env_file {
+                    table.insert("env-file", env_file_value(env_file));
+                }
+                if let Some(description) = service.description {
+                    table.insert("description", description.into());
+                }
+                if service.clean_env {
+                    table.insert("clean-env", true.into());
+                }
                 Item::Value(Value::InlineTable(table))
             }
             Task::Alias(alias) => {
@@ -369,11 +766,7 @@ impl From<Task> for Item {
                 table.insert(
                     "depends-on",
                     Value::Array(Array::from_iter(
-                        alias
-                            .depends_on
-                            .into_iter()
-                            .map(String::from)
-                            .map(Value::from),
+                        alias.depends_on.into_iter().map(Value::from),
                     )),
                 );
                 Item::Value(Value::InlineTable(table))
@@ -383,9 +776,33 @@ impl From<Task> for Item {
     }
 }
 
+/// Converts the dotenv files of a task into a TOML array.
+fn env_file_value(env_file: Vec<PathBuf>) -> Value {
+    Value::Array(Array::from_iter(
+        env_file
+            .into_iter()
+            .map(|path| path.to_string_lossy().to_string()),
+    ))
+}
+
+impl From<Dependency> for Value {
+    fn from(dependency: Dependency) -> Self {
+        match dependency.args {
+            None => String::from(dependency.task_name).into(),
+            Some(args) => {
+                let mut table = Table::new().into_inline_table();
+                table.insert("task", String::from(dependency.task_name).into());
+                table.insert("args", Value::Array(Array::from_iter(args)));
+                Value::InlineTable(table)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::quote;
+    use super::{quote, ReadinessCheck, RetryPolicy, Task};
+    use std::time::Duration;
 
     #[test]
     fn test_quote() {
@@ -401,4 +818,81 @@ mod tests {
         );
         assert_eq!(quote("name=[64,64]"), "\"name=[64,64]\"");
     }
+
+    #[test]
+    fn test_timeout_and_retries() {
+        let task: Task = toml_edit::de::from_str(
+            r#"
+            cmd = "pytest"
+            timeout = "10m"
+            retries = { count = 3, backoff = "5s" }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(task.timeout(), Some(Duration::from_secs(600)));
+        let retries = task.retries().unwrap();
+        assert_eq!(
+            retries,
+            &RetryPolicy {
+                count: 3,
+                backoff: Duration::from_secs(5),
+            }
+        );
+        assert_eq!(retries.backoff_for(0), Duration::from_secs(5));
+        assert_eq!(retries.backoff_for(2), Duration::from_secs(20));
+
+        let item = toml_edit::Item::from(task);
+        let table = item.as_inline_table().unwrap();
+        assert_eq!(table.get("timeout").unwrap().as_str(), Some("10m"));
+        let retries = table.get("retries").unwrap().as_inline_table().unwrap();
+        assert_eq!(retries.get("count").unwrap().as_integer(), Some(3));
+        assert_eq!(retries.get("backoff").unwrap().as_str(), Some("5s"));
+
+        assert!(toml_edit::de::from_str::<Task>(
+            r#"cmd = "pytest"
+timeout = "ten minutes""#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_service() {
+        let task: Task = toml_edit::de::from_str(
+            r#"
+            cmd = "postgres -D data"
+            ready = { port = 5432, timeout = "30s" }
+            depends-on = ["init-db"]
+            "#,
+        )
+        .unwrap();
+
+        let service = task.as_service().unwrap();
+        assert_eq!(service.ready.check, ReadinessCheck::Port(5432));
+        assert_eq!(service.ready.timeout, Duration::from_secs(30));
+        assert_eq!(task.depends_on().len(), 1);
+        assert!(task.is_executable());
+
+        let task: Task = toml_edit::de::from_str(
+            r#"cmd = "npm run dev"
+ready = { log = "listening on" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            task.as_service().unwrap().ready.check,
+            ReadinessCheck::Log("listening on".to_string())
+        );
+
+        let item = toml_edit::Item::from(task);
+        let table = item.as_inline_table().unwrap();
+        let ready = table.get("ready").unwrap().as_inline_table().unwrap();
+        assert_eq!(ready.get("log").unwrap().as_str(), Some("listening on"));
+        assert!(ready.get("timeout").is_none());
+
+        assert!(toml_edit::de::from_str::<Task>(
+            r#"cmd = "npm run dev"
+ready = { port = 3000, log = "listening on" }"#
+        )
+        .is_err());
+    }
 }
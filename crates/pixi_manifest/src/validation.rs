@@ -199,10 +199,30 @@ impl ParsedManifest {
             ));
         }
 
+        // Check if there are no conflicts in solve strategies between features. The
+        // strategy of the default feature is only used if no other feature sets one.
+        let solve_strategies = features
+            .iter()
+            .filter_map(|feature| feature.solve_strategy)
+            .unique()
+            .collect_vec();
+        if solve_strategies.len() > 1 {
+            return Err(miette::miette!(
+                labels = vec![LabeledSpan::at(
+                    env.features_source_loc.clone().unwrap_or_default(),
+                    "while resolving the solve strategies of features defined here"
+                )],
+                help = "set `solve-strategy` in only one of the features of the environment",
+                "the features of the environment '{}' define conflicting solve strategies: {}",
+                env.name.as_str(),
+                solve_strategies.iter().format(", "),
+            ));
+        }
+
         // Check if there are no conflicts in pypi options between features
-        features
+        let pypi_options = features
             .iter()
-            .chain(default)
+            .chain(default.clone())
             .filter_map(|feature| {
                 if feature.pypi_options().is_none() {
                     // Use the project default features
@@ -214,6 +234,34 @@ impl ParsedManifest {
             .try_fold(PypiOptions::default(), |acc, opts| acc.union(opts))
             .into_diagnostic()?;
 
+        // Check that every pypi requirement that is pinned to an index refers to
+        // an index that is defined in the pypi options of the environment.
+        for feature in features.iter().chain(default) {
+            let requirements = feature.targets.targets().flat_map(|target| {
+                target
+                    .pypi_dependencies
+                    .iter()
+                    .chain(target.pypi_constraints.iter())
+                    .flatten()
+            });
+            for (name, requirement) in requirements {
+                let Some(index) = requirement.index() else {
+                    continue;
+                };
+                if pypi_options.named_index(index).is_none() {
+                    return Err(miette::miette!(
+                        help = format!(
+                            "add the index to the `pypi-options.indexes` table, e.g. `{index} = \"https://...\"`"
+                        ),
+                        "the pypi dependency '{}' refers to the index '{}' which is not defined for the environment '{}'",
+                        name.as_source(),
+                        index,
+                        env.name.as_str(),
+                    ));
+                }
+            }
+        }
+
         Ok(())
     }
 }
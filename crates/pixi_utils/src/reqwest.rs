@@ -9,8 +9,8 @@ use rattler_networking::{
     OciMiddleware,
 };
 
-use reqwest::Client;
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest::{Client, Request, Response};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Middleware, Next};
 use std::collections::HashMap;
 
 use pixi_config::Config;
@@ -76,6 +76,31 @@ pub fn oci_middleware() -> OciMiddleware {
     OciMiddleware
 }
 
+/// An error that is returned for every request made in offline mode.
+#[derive(Debug, thiserror::Error)]
+#[error("cannot download '{0}' because pixi is running in offline mode and it is not available in the cache")]
+pub struct OfflineError(pub url::Url);
+
+/// A middleware that rejects all requests. It is used in offline mode to make
+/// sure that artifacts missing from the cache fail fast instead of being
+/// downloaded.
+pub struct OfflineMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for OfflineMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        _extensions: &mut http::Extensions,
+        _next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        tracing::debug!("rejecting request to {} in offline mode", req.url());
+        Err(reqwest_middleware::Error::middleware(OfflineError(
+            req.url().clone(),
+        )))
+    }
+}
+
 pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMiddleware) {
     let app_user_agent = format!("pixi/{}", consts::PIXI_VERSION);
 
@@ -101,6 +126,11 @@ pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMidd
 
     let mut client_builder = ClientBuilder::new(client.clone());
 
+    if config.offline() {
+        tracing::info!("running in offline mode, all network requests are rejected");
+        client_builder = client_builder.with(OfflineMiddleware);
+    }
+
     if !config.mirror_map().is_empty() {
         client_builder = client_builder
             .with(mirror_middleware(&config))
@@ -117,3 +147,31 @@ pub fn build_reqwest_clients(config: Option<&Config>) -> (Client, ClientWithMidd
 
     (client, authenticated_client)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_offline_client_rejects_requests() {
+        let config = Config {
+            offline: Some(true),
+            ..Config::default()
+        };
+        let (_, client) = build_reqwest_clients(Some(&config));
+
+        let url = "https://conda.anaconda.org/conda-forge/noarch/repodata.json";
+        let err = client.get(url).send().await.unwrap_err();
+        let offline_error = match &err {
+            reqwest_middleware::Error::Middleware(err) => err.downcast_ref::<OfflineError>(),
+            reqwest_middleware::Error::Reqwest(_) => None,
+        };
+        assert_eq!(
+            offline_error.map(|err| err.0.as_str()),
+            Some(url),
+            "expected an offline error, got: {err}"
+        );
+        assert!(err.to_string().contains(url));
+        assert!(err.to_string().contains("offline mode"));
+    }
+}
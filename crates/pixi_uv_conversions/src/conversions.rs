@@ -73,10 +73,24 @@ pub fn pypi_options_to_index_locations(
         vec![]
     };
 
+    // Named indexes are explicit, they are only used for the packages that are
+    // pinned to them.
+    let named_indexes = options
+        .indexes
+        .clone()
+        .into_iter()
+        .flat_map(|indexes| indexes.into_values())
+        .map(|url| {
+            let mut index = Index::from_extra_index_url(IndexUrl::from(VerbatimUrl::from_url(url)));
+            index.explicit = true;
+            index
+        });
+
     // we don't have support for an explicit `no_index` field in the `PypiIndexes`
     // so we only set it if you want to use flat indexes only
     let indexes: Vec<_> = index.chain(extra_indexes).collect();
     let no_index = indexes.is_empty() && !flat_indexes.is_empty();
+    let indexes = indexes.into_iter().chain(named_indexes).collect();
 
     Ok(IndexLocations::new(indexes, flat_indexes, no_index))
 }
@@ -17,7 +17,7 @@ use uv_pep440::VersionSpecifiers;
 use uv_pep508::VerbatimUrl;
 use uv_pypi_types::RequirementSource;
 
-use super::to_git_reference;
+use super::{to_git_reference, to_uv_marker_tree, ConversionError};
 
 /// Create a url that uv can use to install a version
 fn create_uv_url(
@@ -71,6 +71,8 @@ pub enum AsPep508Error {
     ExtensionError(#[from] uv_distribution_filename::ExtensionError),
     #[error("error in parsing version specificers")]
     VersionSpecifiersError(#[from] uv_pep440::VersionSpecifiersParseError),
+    #[error("error in converting the environment markers")]
+    MarkerError(#[from] ConversionError),
 }
 
 /// Convert into a `pypi_types::Requirement`, which is an uv extended
@@ -83,7 +85,7 @@ pub fn as_uv_req(
     let name = PackageName::new(name.to_owned())?;
     let source = match req {
         PyPiRequirement::Version { version, .. } => {
-            // TODO: implement index later
+            // Named indexes are resolved against the pypi-options by the resolver.
             RequirementSource::Registry {
                 specifier: to_version_specificers(version)?,
                 index: None,
@@ -186,7 +188,7 @@ pub fn as_uv_req(
             .iter()
             .map(|e| uv_pep508::ExtraName::new(e.to_string()).expect("conversion failed"))
             .collect(),
-        marker: Default::default(),
+        marker: to_uv_marker_tree(&req.markers())?,
         source,
         origin: None,
     })
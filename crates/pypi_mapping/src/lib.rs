@@ -10,7 +10,7 @@ use custom_pypi_mapping::fetch_mapping_from_path;
 use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
 use pixi_config::get_cache_dir;
 use rattler_conda_types::{PackageRecord, PackageUrl, RepoDataRecord};
-use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Extension};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 use url::Url;
 
@@ -135,6 +135,15 @@ impl PurlSource {
     }
 }
 
+/// Returns a client whose mapping requests are only answered from the local
+/// cache, which is used in offline mode. Requesting a mapping that is not
+/// cached results in an error.
+pub fn cache_only_client(client: ClientWithMiddleware) -> ClientWithMiddleware {
+    ClientBuilder::from_client(client)
+        .with_init(Extension(CacheMode::OnlyIfCached))
+        .build()
+}
+
 pub async fn amend_pypi_purls(
     client: ClientWithMiddleware,
     mapping_source: &MappingSource,
@@ -38,10 +38,11 @@ async fn try_fetch_single_mapping(
     sha256: &Sha256Hash,
 ) -> miette::Result<Option<Package>> {
     let hash_str = format!("{:x}", sha256);
+    let url = format!("{STORAGE_URL}/{HASH_DIR}/{}", hash_str);
 
     // Fetch the mapping from the server
     let response = client
-        .get(format!("{STORAGE_URL}/{HASH_DIR}/{}", hash_str))
+        .get(&url)
         .send()
         .await
         .into_diagnostic()
@@ -52,6 +53,15 @@ async fn try_fetch_single_mapping(
         return Ok(None);
     }
 
+    // A mapping that is not cached is answered with a gateway timeout when the
+    // client may only use the cache.
+    if !response.status().is_success() {
+        miette::bail!(
+            "could not request the pypi name mapping located at {url} ({}), in offline mode it has to be in the cache",
+            response.status()
+        );
+    }
+
     // Otherwise convert the response to a Package struct
     let package: Package = response
         .json()
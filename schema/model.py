@@ -13,6 +13,7 @@ from pydantic import (
     AnyHttpUrl,
     BaseModel,
     Field,
+    NonNegativeInt,
     PositiveFloat,
     StringConstraints,
 )
@@ -88,6 +89,14 @@ class ChannelPriority(str, Enum):
     strict = "strict"
 
 
+class SolveStrategy(str, Enum):
+    """The strategy used to pick the versions of packages."""
+
+    highest = "highest"
+    lowest = "lowest"
+    lowest_direct = "lowest-direct"
+
+
 class Project(StrictBaseModel):
     """The project's metadata information."""
 
@@ -115,6 +124,21 @@ class Project(StrictBaseModel):
         "- 'strict': only take the package from the channel it exist in first."
         "- 'disabled': group all dependencies together as if there is no channel difference.",
     )
+    exclude_newer: NonEmptyStr | None = Field(
+        None,
+        alias="exclude-newer",
+        examples=["2024-06-01", "2024-06-01T12:00:00Z"],
+        description="Exclude packages that were published after this date when solving. Either a date or an RFC 3339 timestamp.",
+    )
+    solve_strategy: SolveStrategy | None = Field(
+        None,
+        alias="solve-strategy",
+        examples=["highest", "lowest", "lowest-direct"],
+        description="The strategy used to pick the versions of packages when solving."
+        "- 'highest': select the highest compatible versions."
+        "- 'lowest': select the lowest compatible versions."
+        "- 'lowest-direct': select the lowest compatible versions of direct dependencies and the highest compatible versions of transitive dependencies.",
+    )
     platforms: list[Platform] = Field(description="The platforms that the project supports")
     license: NonEmptyStr | None = Field(
         None,
@@ -192,6 +216,11 @@ class _PyPIRequirement(StrictBaseModel):
         None,
         description="The [PEP 508 extras](https://peps.python.org/pep-0508/#extras) of the package",
     )
+    markers: NonEmptyStr | None = Field(
+        None,
+        description="The [PEP 508 environment markers](https://peps.python.org/pep-0508/#environment-markers) that determine whether the package is installed",
+        examples=["sys_platform == 'linux' and python_version < '3.11'"],
+    )
 
 
 class _PyPiGitRequirement(_PyPIRequirement):
@@ -241,6 +270,11 @@ class PyPIVersion(_PyPIRequirement):
         None,
         description="The version of the package in [PEP 440](https://www.python.org/dev/peps/pep-0440/) format",
     )
+    index: NonEmptyStr | None = Field(
+        None,
+        description="The name of the index in `pypi-options.indexes` that the package should be taken from",
+        examples=["internal"],
+    )
 
 
 PyPIRequirement = (
@@ -270,6 +304,17 @@ BuildDependenciesField = Field(
     description="The build `conda` dependencies, used in the build process",
 )
 Dependencies = dict[CondaPackageName, MatchSpec] | None
+ConstraintsField = Field(
+    None,
+    description="Constraints on the versions of `conda` packages, which only apply if the package is required by another dependency",
+    examples=[{"openssl": ">=3.2"}],
+)
+PyPIConstraintsField = Field(
+    None,
+    alias="pypi-constraints",
+    description="Constraints on the versions of PyPI packages, which only apply if the package is required by another dependency",
+    examples=[{"urllib3": ">=2"}],
+)
 
 ################
 # Task section #
@@ -277,6 +322,61 @@ Dependencies = dict[CondaPackageName, MatchSpec] | None
 TaskName = Annotated[str, Field(pattern=r"^[^\s\$]+$", description="A valid task name.")]
 
 
+class TaskArg(StrictBaseModel):
+    """A named argument of a task."""
+
+    name: NonEmptyStr = Field(description="The name of the argument")
+    default: str | None = Field(
+        None, description="The value of the argument if no value is passed"
+    )
+
+
+class DependsOn(StrictBaseModel):
+    """A dependency on another task, passing values for its arguments."""
+
+    task: TaskName = Field(description="The name of the task to depend on")
+    args: list[str] | None = Field(
+        None,
+        description="The values of the arguments of the task, in the order in which the arguments are declared",
+    )
+
+
+class RetryPolicy(StrictBaseModel):
+    """Determines how often a failing task is retried."""
+
+    count: NonNegativeInt = Field(
+        description="The number of times the task is retried after it failed"
+    )
+    backoff: NonEmptyStr | None = Field(
+        None,
+        description="The time to wait before the first retry, e.g. `5s`. The time is doubled for every subsequent retry",
+        examples=["5s", "1m"],
+    )
+
+
+class ReadinessProbe(StrictBaseModel):
+    """Determines when a service is ready. Exactly one of `port`, `log` or `cmd` must be set."""
+
+    port: Annotated[int, Field(ge=0, le=65535)] | None = Field(
+        None, description="The service is ready when it accepts TCP connections on this port"
+    )
+    log: NonEmptyStr | None = Field(
+        None,
+        description="The service is ready when it prints a line that matches this regular expression",
+        examples=["listening on"],
+    )
+    cmd: NonEmptyStr | None = Field(
+        None,
+        description="The service is ready when this command exits successfully",
+        examples=["pg_isready"],
+    )
+    timeout: NonEmptyStr | None = Field(
+        None,
+        description="The maximum time to wait for the service to become ready, defaults to `60s`",
+        examples=["30s", "2m"],
+    )
+
+
 class TaskInlineTable(StrictBaseModel):
     """A precise definition of a task."""
 
@@ -291,11 +391,15 @@ class TaskInlineTable(StrictBaseModel):
         alias="depends_on",
         description="The tasks that this task depends on. Environment variables will **not** be expanded. Deprecated in favor of `depends-on` from v0.21.0 onward.",
     )
-    depends_on: list[TaskName] | TaskName | None = Field(
+    depends_on: list[TaskName | DependsOn] | TaskName | DependsOn | None = Field(
         None,
         alias="depends-on",
         description="The tasks that this task depends on. Environment variables will **not** be expanded.",
     )
+    args: list[TaskArg] | None = Field(
+        None,
+        description="The named arguments of the task. Their values can be used in `cmd` with `{{ name }}`.",
+    )
     inputs: list[Glob] | None = Field(
         None,
         description="A list of `.gitignore`-style glob patterns that should be watched for changes before this command is run. Environment variables _will_ be expanded.",
@@ -309,6 +413,12 @@ class TaskInlineTable(StrictBaseModel):
         description="A map of environment variables to values, used in the task, these will be overwritten by the shell.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_file: list[PathNoBackslash] | None = Field(
+        None,
+        alias="env-file",
+        description="Dotenv files, relative to the project root, to load before the task runs. Later files take precedence over earlier ones and `env` takes precedence over all of them.",
+        examples=[[".env", ".env.local"]],
+    )
     description: NonEmptyStr | None = Field(
         None,
         description="A short description of the task",
@@ -319,6 +429,22 @@ class TaskInlineTable(StrictBaseModel):
         alias="clean-env",
         description="Whether to run in a clean environment, removing all environment variables except those defined in `env` and by pixi itself.",
     )
+    condition: NonEmptyStr | None = Field(
+        None,
+        description="An expression that determines whether the task is run, e.g. `env.CI == \"true\"` or `exists(\"build/\")`. The task is skipped when the expression is false.",
+    )
+    timeout: NonEmptyStr | None = Field(
+        None,
+        description="The maximum duration of the task, e.g. `10m`. The task is killed and fails when it runs longer.",
+        examples=["30s", "10m", "1h 30m"],
+    )
+    retries: RetryPolicy | None = Field(
+        None, description="How often the task is retried when it fails or times out"
+    )
+    ready: ReadinessProbe | None = Field(
+        None,
+        description="Makes the task a service that is started in the background. The tasks that depend on it are run once the probe succeeds and the service is stopped when they are finished.",
+    )
 
 
 #######################
@@ -392,6 +518,12 @@ class Activation(StrictBaseModel):
         description="A map of environment variables to values, used in the activation of the environment. These will be set in the shell. Thus these variables are shell specific. Using '$' might not expand to a value in different shells.",
         examples=[{"key": "value"}, {"ARGUMENT": "value"}],
     )
+    env_file: list[PathNoBackslash] | None = Field(
+        None,
+        alias="env-file",
+        description="Dotenv files, relative to the project root, to load when the environment is activated. Later files take precedence over earlier ones and `env` takes precedence over all of them.",
+        examples=[[".env", ".env.local"]],
+    )
 
 
 ##################
@@ -409,6 +541,8 @@ class Target(StrictBaseModel):
     pypi_dependencies: dict[PyPIPackageName, PyPIRequirement] | None = Field(
         None, alias="pypi-dependencies", description="The PyPI dependencies for this target"
     )
+    constraints: Dependencies = ConstraintsField
+    pypi_constraints: dict[PyPIPackageName, PyPIRequirement] | None = PyPIConstraintsField
     tasks: dict[TaskName, TaskInlineTable | NonEmptyStr] | None = Field(
         None, description="The tasks of the target"
     )
@@ -435,6 +569,15 @@ class Feature(StrictBaseModel):
         "- 'strict': only take the package from the channel it exist in first."
         "- 'disabled': group all dependencies together as if there is no channel difference.",
     )
+    solve_strategy: SolveStrategy | None = Field(
+        None,
+        alias="solve-strategy",
+        examples=["highest", "lowest", "lowest-direct"],
+        description="The strategy used to pick the versions of packages when solving environments containing this feature."
+        "- 'highest': select the highest compatible versions."
+        "- 'lowest': select the lowest compatible versions."
+        "- 'lowest-direct': select the lowest compatible versions of direct dependencies and the highest compatible versions of transitive dependencies.",
+    )
     platforms: list[Platform] | None = Field(
         None,
         description="The platforms that the feature supports: a union of all features combined in one environment is used for the environment.",
@@ -445,6 +588,8 @@ class Feature(StrictBaseModel):
     pypi_dependencies: dict[PyPIPackageName, PyPIRequirement] | None = Field(
         None, alias="pypi-dependencies", description="The PyPI dependencies of this feature"
     )
+    constraints: Dependencies = ConstraintsField
+    pypi_constraints: dict[PyPIPackageName, PyPIRequirement] | None = PyPIConstraintsField
     tasks: dict[TaskName, TaskInlineTable | NonEmptyStr] | None = Field(
         None, description="The tasks provided by this feature"
     )
@@ -522,6 +667,11 @@ class PyPIOptions(StrictBaseModel):
         description="The strategy to use when resolving packages from multiple indexes",
         examples=["first-index", "unsafe-first-match", "unsafe-best-match"],
     )
+    indexes: dict[NonEmptyStr, NonEmptyStr] | None = Field(
+        None,
+        description="Named PyPI registries that are only used for the packages that refer to them with `index`",
+        examples=[{"internal": "https://pypi.internal.example.com/simple"}],
+    )
 
 
 #######################
@@ -554,6 +704,8 @@ class BaseManifest(StrictBaseModel):
     pypi_dependencies: dict[PyPIPackageName, PyPIRequirement] | None = Field(
         None, alias="pypi-dependencies", description="The PyPI dependencies"
     )
+    constraints: Dependencies = ConstraintsField
+    pypi_constraints: dict[PyPIPackageName, PyPIRequirement] | None = PyPIConstraintsField
     pypi_options: PyPIOptions | None = Field(
         None, alias="pypi-options", description="Options related to PyPI indexes"
     )
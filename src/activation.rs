@@ -1,17 +1,19 @@
 use indexmap::IndexMap;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use itertools::Itertools;
-use miette::IntoDiagnostic;
+use miette::{Context, IntoDiagnostic};
 use rattler_conda_types::Platform;
 use rattler_shell::{
     activation::{
-        ActivationError, ActivationError::FailedToRunActivationScript, ActivationVariables,
-        Activator, PathModificationBehavior,
+        ActivationError::FailedToRunActivationScript, ActivationVariables, Activator,
+        PathModificationBehavior,
     },
     shell::ShellEnum,
 };
 
+use crate::dotenv::{load_env_files, DotenvError};
 use crate::project::HasProjectRef;
 use crate::{project::Environment, Project};
 use pixi_manifest::EnvironmentName;
@@ -95,10 +97,15 @@ impl Environment<'_> {
 /// This method will create an activator for the environment and add the activation scripts from the project.
 /// The activator will be created for the current platform and the default shell.
 /// The activation scripts from the environment will be checked for existence and the extension will be checked for correctness.
+///
+/// The variables of the `env-file`s are only added when `env_file_base` is given, the variables
+/// referenced in the files are expanded from it. Activations that are exported, e.g. into a pack
+/// or an image, leave them out so that the secrets in the files don't end up in the export.
 pub(crate) fn get_activator<'p>(
     environment: &'p Environment<'p>,
     shell: ShellEnum,
-) -> Result<Activator<ShellEnum>, ActivationError> {
+    env_file_base: Option<&HashMap<String, String>>,
+) -> miette::Result<Activator<ShellEnum>> {
     let platform = Platform::current();
     let additional_activation_scripts = environment.activation_scripts(Some(platform));
 
@@ -127,13 +134,22 @@ pub(crate) fn get_activator<'p>(
     }
 
     let mut activator =
-        Activator::from_path(environment.dir().as_path(), shell, Platform::current())?;
+        Activator::from_path(environment.dir().as_path(), shell, Platform::current())
+            .into_diagnostic()?;
 
     // Add the custom activation scripts from the environment
     activator
         .activation_scripts
         .extend(additional_activation_scripts);
 
+    // Add the environment variables from the dotenv files, the environment variables from the
+    // project take precedence over them.
+    if let Some(base) = env_file_base {
+        activator
+            .env_vars
+            .extend(get_env_file_variables(environment, base)?);
+    }
+
     // Add the environment variables from the project.
     activator
         .env_vars
@@ -142,18 +158,17 @@ pub(crate) fn get_activator<'p>(
     Ok(activator)
 }
 
-/// Runs and caches the activation script.
+/// Runs and caches the activation script. The variables of the `env-file`s are only included when
+/// `include_env_files` is set, they are expanded from the variables returned by
+/// [`get_env_file_base`].
 pub async fn run_activation(
     environment: &Environment<'_>,
     env_var_behavior: &CurrentEnvVarBehavior,
+    include_env_files: bool,
 ) -> miette::Result<HashMap<String, String>> {
-    let activator = get_activator(environment, ShellEnum::default()).map_err(|e| {
-        miette::miette!(format!(
-            "failed to create activator for {:?}\n{}",
-            environment.name(),
-            e
-        ))
-    })?;
+    let env_file_base = include_env_files.then(|| get_env_file_base(env_var_behavior));
+    let activator = get_activator(environment, ShellEnum::default(), env_file_base.as_ref())
+        .wrap_err_with(|| format!("failed to create activator for {:?}", environment.name()))?;
 
     let path_modification_behavior = match env_var_behavior {
         // We need to replace the full environment path with the new one.
@@ -240,6 +255,50 @@ pub(crate) fn get_static_environment_variables<'p>(
         .collect()
 }
 
+/// Loads the dotenv files of the `[activation]` of the environment. Variables that are referenced
+/// in the files but not defined in them are expanded from `base`.
+pub(crate) fn get_env_file_variables(
+    environment: &Environment<'_>,
+    base: &HashMap<String, String>,
+) -> Result<IndexMap<String, String>, DotenvError> {
+    let env_files = environment.activation_env_files(Some(Platform::current()));
+    if env_files.is_empty() {
+        return Ok(IndexMap::new());
+    }
+    load_env_files(
+        env_files.iter().map(PathBuf::as_path),
+        environment.project().root(),
+        base,
+    )
+}
+
+/// Get the environment variables of the current shell that are passed on to a command with the
+/// given behavior.
+pub(crate) fn get_current_shell_variables(
+    env_var_behavior: &CurrentEnvVarBehavior,
+) -> HashMap<String, String> {
+    match env_var_behavior {
+        CurrentEnvVarBehavior::Clean => get_clean_environment_variables(),
+        CurrentEnvVarBehavior::Include => std::env::vars().collect(),
+        CurrentEnvVarBehavior::Exclude => HashMap::new(),
+    }
+}
+
+/// Get the variables that the `env-file`s are expanded from. Unlike
+/// [`get_current_shell_variables`] this is the full current shell when its variables are
+/// excluded, e.g. for `pixi shell` which is started from the current shell, otherwise references
+/// in the files would silently expand to empty values.
+pub(crate) fn get_env_file_base(
+    env_var_behavior: &CurrentEnvVarBehavior,
+) -> HashMap<String, String> {
+    match env_var_behavior {
+        CurrentEnvVarBehavior::Clean => get_clean_environment_variables(),
+        CurrentEnvVarBehavior::Include | CurrentEnvVarBehavior::Exclude => {
+            std::env::vars().collect()
+        }
+    }
+}
+
 /// Get the environment variables that are set in the current shell
 /// and strip them down to the minimal set required to run a command.
 pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
@@ -289,20 +348,18 @@ pub(crate) fn get_clean_environment_variables() -> HashMap<String, String> {
 pub(crate) async fn initialize_env_variables(
     environment: &Environment<'_>,
     env_var_behavior: CurrentEnvVarBehavior,
+    include_env_files: bool,
 ) -> miette::Result<HashMap<String, String>> {
-    let activation_env = run_activation(environment, &env_var_behavior).await?;
+    if cfg!(windows) && matches!(env_var_behavior, CurrentEnvVarBehavior::Clean) {
+        return Err(miette::miette!(
+            "Currently it's not possible to run a `clean-env` option on Windows."
+        ));
+    }
+
+    let activation_env = run_activation(environment, &env_var_behavior, include_env_files).await?;
 
     // Get environment variables from the currently activated shell.
-    let current_shell_env_vars = match env_var_behavior {
-        CurrentEnvVarBehavior::Clean if cfg!(windows) => {
-            return Err(miette::miette!(
-                "Currently it's not possible to run a `clean-env` option on Windows."
-            ));
-        }
-        CurrentEnvVarBehavior::Clean => get_clean_environment_variables(),
-        CurrentEnvVarBehavior::Include => std::env::vars().collect(),
-        CurrentEnvVarBehavior::Exclude => HashMap::new(),
-    };
+    let current_shell_env_vars = get_current_shell_variables(&env_var_behavior);
 
     let all_variables: HashMap<String, String> = current_shell_env_vars
         .into_iter()
@@ -410,6 +467,48 @@ mod tests {
         assert!(env.keys().position(|key| key == "ZZZ") < env.keys().position(|key| key == "ZAB"));
     }
 
+    #[test]
+    fn test_env_file_variables() {
+        let root = tempfile::tempdir().unwrap();
+        fs_err::write(
+            root.path().join(".env"),
+            "URL=http://$HOST/app\nSECRET=hunter2\n",
+        )
+        .unwrap();
+        let project = Project::from_str(
+            &root.path().join("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+        [activation]
+        env-file = [".env"]
+        "#,
+        )
+        .unwrap();
+        let environment = project.default_environment();
+
+        // Referenced variables are expanded from the given base environment only.
+        let base = HashMap::from([("HOST".to_string(), "example.com".to_string())]);
+        let vars = get_env_file_variables(&environment, &base).unwrap();
+        assert_eq!(vars["URL"], "http://example.com/app");
+
+        // Excluding the variables of the current shell still expands the files from it.
+        assert_eq!(
+            get_env_file_base(&CurrentEnvVarBehavior::Exclude),
+            std::env::vars().collect::<HashMap<_, _>>()
+        );
+
+        // The variables are only part of the activation when a base environment is given.
+        let activator =
+            get_activator(&environment, ShellEnum::default(), Some(&HashMap::new())).unwrap();
+        assert_eq!(activator.env_vars["SECRET"], "hunter2");
+        let activator = get_activator(&environment, ShellEnum::default(), None).unwrap();
+        assert!(!activator.env_vars.contains_key("SECRET"));
+    }
+
     #[test]
     #[cfg(target_os = "linux")]
     fn test_get_linux_clean_environment_variables() {
@@ -159,7 +159,7 @@ async fn clean_cache(args: CacheArgs) -> miette::Result<()> {
     Ok(())
 }
 
-async fn remove_folder_with_progress(
+pub(crate) async fn remove_folder_with_progress(
     folder: PathBuf,
     warning_non_existent: bool,
 ) -> miette::Result<()> {
@@ -324,6 +324,7 @@ fn partial_config(config: &mut Config, key: &str) -> miette::Result<()> {
         "default-channels" => new.default_channels = config.default_channels.clone(),
         "change-ps1" => new.change_ps1 = config.change_ps1,
         "tls-no-verify" => new.tls_no_verify = config.tls_no_verify,
+        "offline" => new.offline = config.offline,
         "authentication-override-file" => {
             new.authentication_override_file = config.authentication_override_file.clone()
         }
@@ -335,6 +336,7 @@ fn partial_config(config: &mut Config, key: &str) -> miette::Result<()> {
                 "default-channels",
                 "change-ps1",
                 "tls-no-verify",
+                "offline",
                 "authentication-override-file",
                 "mirrors",
                 "repodata-config",
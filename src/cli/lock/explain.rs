@@ -0,0 +1,282 @@
+use std::ops::Range;
+
+use fancy_display::FancyDisplay;
+use miette::{LabeledSpan, NamedSource, Report};
+use pixi_consts::consts;
+use pixi_manifest::HasFeaturesIter;
+use toml_edit::{ImDocument, Item, TableLike};
+
+use crate::{
+    lock_file::{EnvironmentUnsat, OutdatedEnvironments, OutdatedReason},
+    project::Environment,
+    Project,
+};
+
+/// The tables of a feature that contain package requirements.
+const DEPENDENCY_TABLES: [&str; 6] = [
+    consts::DEPENDENCIES,
+    "host-dependencies",
+    "build-dependencies",
+    consts::PYPI_DEPENDENCIES,
+    "constraints",
+    "pypi-constraints",
+];
+
+/// Constructs a report for every reason why the lock-file of the project is out
+/// of date. If the reason can be attributed to a part of the manifest, the
+/// report points at it.
+pub(crate) fn explain_outdated(project: &Project, outdated: &OutdatedEnvironments) -> Vec<Report> {
+    let contents = &project.manifest.contents;
+    let document = ImDocument::parse(contents.as_str()).ok();
+    let root = document.as_ref().and_then(|document| {
+        if project.manifest.file_name() == consts::PYPROJECT_MANIFEST {
+            document
+                .get("tool")
+                .and_then(|tool| tool.get("pixi"))
+                .and_then(Item::as_table_like)
+        } else {
+            Some(document.as_table() as &dyn TableLike)
+        }
+    });
+
+    outdated
+        .reasons
+        .iter()
+        .map(|reason| {
+            let (message, label) = match reason {
+                OutdatedReason::MissingEnvironment(environment) => (
+                    format!(
+                        "environment {} is missing from the lock-file",
+                        environment.name().fancy_display()
+                    ),
+                    root.and_then(|root| {
+                        let span = root
+                            .get("environments")?
+                            .get(environment.name().as_str())?
+                            .span()?;
+                        Some((span, "the environment is defined here"))
+                    }),
+                ),
+                OutdatedReason::Environment(environment, unsat) => {
+                    let (key, label) = match unsat {
+                        EnvironmentUnsat::ChannelsMismatch
+                        | EnvironmentUnsat::InvalidChannel(_) => (
+                            "channels",
+                            "the channels of the environment are defined here",
+                        ),
+                        EnvironmentUnsat::IndexesMismatch(_) => (
+                            "pypi-options",
+                            "the indexes of the environment are defined here",
+                        ),
+                        EnvironmentUnsat::ExcludeNewerMismatch { .. } => {
+                            ("exclude-newer", "the exclude-newer date is defined here")
+                        }
+                        EnvironmentUnsat::SolveStrategyMismatch(_) => (
+                            "solve-strategy",
+                            "the solve strategy of the environment is defined here",
+                        ),
+                    };
+                    (
+                        format!(
+                            "environment {} is out of date because {unsat}",
+                            environment.name().fancy_display()
+                        ),
+                        root.and_then(|root| find_key_span(root, environment, key))
+                            .map(|span| (span, label)),
+                    )
+                }
+                OutdatedReason::Platform(environment, platform, unsat) => (
+                    format!(
+                        "environment {} is out of date for {} because {unsat}",
+                        environment.name().fancy_display(),
+                        consts::PLATFORM_STYLE.apply_to(platform)
+                    ),
+                    root.zip(unsat.package_name())
+                        .and_then(|(root, name)| find_requirement_span(root, environment, &name))
+                        .map(|span| (span, "the requirement is defined here")),
+                ),
+            };
+
+            match label {
+                Some((span, label)) => {
+                    miette::miette!(labels = vec![LabeledSpan::at(span, label)], "{message}")
+                        .with_source_code(NamedSource::new(
+                            project.manifest.file_name(),
+                            contents.clone(),
+                        ))
+                }
+                None => miette::miette!("{message}"),
+            }
+        })
+        .collect()
+}
+
+/// Returns the tables of the manifest that define the features of the
+/// environment, including their target specific tables. The most specific
+/// tables come first.
+fn feature_tables<'a>(
+    root: &'a dyn TableLike,
+    environment: &Environment,
+) -> Vec<&'a dyn TableLike> {
+    let mut tables = Vec::new();
+    for feature in environment.features() {
+        let feature_table = match feature.name.name() {
+            None => Some(root),
+            Some(name) => root
+                .get("feature")
+                .and_then(|features| features.get(name))
+                .and_then(Item::as_table_like),
+        };
+        let Some(feature_table) = feature_table else {
+            continue;
+        };
+
+        if let Some(targets) = feature_table.get("target").and_then(Item::as_table_like) {
+            tables.extend(
+                targets
+                    .iter()
+                    .filter_map(|(_, target)| target.as_table_like()),
+            );
+        }
+        tables.push(feature_table);
+    }
+    tables
+}
+
+/// Finds the span of a key that is defined for the environment, either in one
+/// of its features or in the project table.
+fn find_key_span(
+    root: &dyn TableLike,
+    environment: &Environment,
+    key: &str,
+) -> Option<Range<usize>> {
+    let project_table = ["project", "workspace"]
+        .into_iter()
+        .filter_map(|name| root.get(name).and_then(Item::as_table_like));
+    feature_tables(root, environment)
+        .into_iter()
+        .chain(project_table)
+        .find_map(|table| table.get(key)?.span())
+}
+
+/// Finds the span of a requirement on the package with the given name in one
+/// of the features of the environment.
+fn find_requirement_span(
+    root: &dyn TableLike,
+    environment: &Environment,
+    name: &str,
+) -> Option<Range<usize>> {
+    let name = normalize_name(name);
+    feature_tables(root, environment)
+        .into_iter()
+        .flat_map(|table| {
+            DEPENDENCY_TABLES
+                .iter()
+                .filter_map(move |key| table.get(key).and_then(Item::as_table_like))
+        })
+        .find_map(|dependencies| {
+            dependencies
+                .iter()
+                .find(|(key, _)| normalize_name(key) == name)
+                .and_then(|(_, requirement)| requirement.span())
+        })
+}
+
+/// Normalizes a package name so that conda and PyPI spellings of the same name
+/// compare equal.
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Write, path::Path};
+
+    use itertools::Itertools;
+    use rattler_conda_types::Platform;
+    use rattler_lock::LockFile;
+
+    use super::*;
+    use crate::lock_file::PlatformUnsat;
+
+    /// Loads a project and its lock-file from the `tests/data/explain`
+    /// directory.
+    fn load_project(manifest: &str) -> (Project, LockFile) {
+        let manifest_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/explain")
+            .join(manifest);
+        let project = Project::from_path(&manifest_path).unwrap();
+        let lock_file = LockFile::from_path(&project.lock_file_path()).unwrap();
+        (project, lock_file)
+    }
+
+    /// Renders the message of every report followed by its labels and the
+    /// part of the manifest they point at.
+    fn render(project: &Project, reports: Vec<Report>) -> String {
+        let contents = &project.manifest.contents;
+        reports
+            .iter()
+            .map(|report| {
+                let mut rendered = console::strip_ansi_codes(&report.to_string()).into_owned();
+                for label in report.labels().into_iter().flatten() {
+                    let span = label.offset()..label.offset() + label.len();
+                    write!(
+                        rendered,
+                        "\n  {}: {}",
+                        label.label().unwrap_or_default(),
+                        &contents[span]
+                    )
+                    .unwrap();
+                }
+                rendered
+            })
+            .join("\n")
+    }
+
+    #[test]
+    fn test_explain_features_and_targets() {
+        let (project, lock_file) = load_project("features-and-targets/pixi.toml");
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(&project, &lock_file);
+        insta::assert_snapshot!(render(&project, explain_outdated(&project, &outdated)), @r###"
+        environment dep is out of date for win-64 because the requirement 'pixi <0.15.2' could not be satisfied (required by '<environment>')
+          the requirement is defined here: "<0.15.2"
+        environment chan is out of date because the channels in the lock-file do not match the environments channels
+          the channels of the environment are defined here: ["pixi-channel"]
+        environment missing is missing from the lock-file
+          the environment is defined here: ["dep"]
+        "###);
+    }
+
+    #[test]
+    fn test_explain_pyproject() {
+        let (project, lock_file) = load_project("pyproject/pyproject.toml");
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(&project, &lock_file);
+        insta::assert_snapshot!(render(&project, explain_outdated(&project, &outdated)), @r###"
+        environment default is out of date for win-64 because the requirement 'pixi <0.15.2' could not be satisfied (required by '<environment>')
+          the requirement is defined here: "<0.15.2"
+        "###);
+    }
+
+    #[test]
+    fn test_explain_without_package() {
+        let (project, _) = load_project("pyproject/pyproject.toml");
+        let outdated = OutdatedEnvironments {
+            conda: Default::default(),
+            pypi: Default::default(),
+            disregard_locked_content: Default::default(),
+            reasons: vec![OutdatedReason::Platform(
+                project.default_environment(),
+                Platform::Win64,
+                PlatformUnsat::TooManyCondaPackages,
+            )],
+        };
+        insta::assert_snapshot!(render(&project, explain_outdated(&project, &outdated)), @"environment default is out of date for win-64 because there are more conda packages in the lock-file than are used by the environment");
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Typing_Extensions"), "typing-extensions");
+        assert_eq!(normalize_name("zope.interface"), "zope-interface");
+        assert_eq!(normalize_name("numpy"), "numpy");
+    }
+}
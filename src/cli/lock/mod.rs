@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use pixi_config::ConfigCli;
+use pixi_consts::consts;
+use pixi_manifest::{EnvironmentName, FeaturesExt};
+use rattler_conda_types::Platform;
+
+use crate::{
+    cli::cli_config::ProjectConfig,
+    diff::{LockFileDiff, LockFileJsonDiff},
+    load_lock_file,
+    lock_file::{OutdatedEnvironments, OutdatedReason, StaleContent, UpdateContext},
+    Project,
+};
+
+mod explain;
+pub mod prune;
+
+#[derive(Debug, Parser)]
+pub enum Command {
+    Prune(prune::Args),
+}
+
+/// Solve the environments of the project and update the lock file, without
+/// installing any of them.
+///
+/// Use the subcommands to inspect and clean up the lock file.
+#[derive(Debug, Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[clap(flatten)]
+    pub config: ConfigCli,
+
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The environments to lock. If none is specified, all environments are
+    /// locked.
+    #[clap(long = "environment", short = 'e')]
+    pub environments: Option<Vec<EnvironmentName>>,
+
+    /// The platforms to lock. If none is specified, all platforms are locked.
+    #[clap(long = "platform", short = 'p')]
+    pub platforms: Option<Vec<Platform>>,
+
+    /// Check if the lock file is up-to-date without updating it. Exits with a
+    /// non-zero exit code if the lock file is out of date.
+    ///
+    /// Use `pixi lock prune --check` to check for stale content instead.
+    #[clap(long, conflicts_with = "json")]
+    pub check: bool,
+
+    /// Explain why the lock file is out of date, pointing at the part of the
+    /// manifest that caused it. The lock file is not updated.
+    #[clap(long, conflicts_with = "json")]
+    pub explain: bool,
+
+    /// Output the changes in JSON format.
+    #[clap(long)]
+    pub json: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    match args.command {
+        Some(Command::Prune(args)) => prune::execute(args).await?,
+        None => {
+            if let Some(json_diff) = lock(args).await? {
+                let json =
+                    serde_json::to_string_pretty(&json_diff).expect("failed to convert to json");
+                println!("{}", json);
+            }
+        }
+    };
+    Ok(())
+}
+
+/// Updates the lock file of the project without touching any environment.
+///
+/// Returns the changes to the lock file in JSON format if `--json` was
+/// specified.
+pub async fn lock(args: Args) -> miette::Result<Option<serde_json::Value>> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.config);
+
+    // If the user specified an environment name, check to see if it exists.
+    for env in args.environments.iter().flatten() {
+        if project.environment(env).is_none() {
+            miette::bail!(
+                "could not find an environment named {}",
+                env.fancy_display()
+            )
+        }
+    }
+    let environments = args
+        .environments
+        .map(|envs| envs.into_iter().collect::<HashSet<_>>());
+    let platforms = args
+        .platforms
+        .map(|platforms| platforms.into_iter().collect::<HashSet<_>>());
+    let is_selected = |environment: &EnvironmentName, platform: &Platform| {
+        environments
+            .as_ref()
+            .map_or(true, |envs| envs.contains(environment))
+            && platforms
+                .as_ref()
+                .map_or(true, |platforms| platforms.contains(platform))
+    };
+
+    // Determine which of the selected environments and platforms are out of date.
+    let lock_file = load_lock_file(&project).await?;
+    let mut outdated = OutdatedEnvironments::from_project_and_lock_file(&project, &lock_file);
+    for targets in [&mut outdated.conda, &mut outdated.pypi] {
+        targets.retain(|environment, platforms| {
+            platforms.retain(|platform| is_selected(environment.name(), platform));
+            !platforms.is_empty()
+        });
+    }
+    outdated.reasons.retain(|reason| match reason {
+        OutdatedReason::MissingEnvironment(environment)
+        | OutdatedReason::Environment(environment, _) => environment
+            .platforms()
+            .iter()
+            .any(|platform| is_selected(environment.name(), platform)),
+        OutdatedReason::Platform(environment, platform, _) => {
+            is_selected(environment.name(), platform)
+        }
+    });
+
+    if args.check || args.explain {
+        if outdated.is_empty() {
+            eprintln!(
+                "{}Lock-file is up-to-date",
+                console::style(console::Emoji("✔ ", "")).green()
+            );
+            return Ok(None);
+        }
+        if args.explain {
+            for report in explain::explain_outdated(&project, &outdated) {
+                eprintln!("{:?}", report);
+            }
+        } else {
+            print_outdated_reasons(&outdated);
+        }
+        if args.check {
+            miette::bail!("lock-file not up-to-date with the project");
+        }
+        return Ok(None);
+    }
+
+    // Solve the outdated environments. The environments are never installed,
+    // solving pypi dependencies requires a python interpreter so the conda
+    // packages for those are installed in a temporary directory instead.
+    let updated_lock_file = if outdated.is_empty() {
+        lock_file.clone()
+    } else {
+        let solve_prefix_dir = tempfile::tempdir()
+            .into_diagnostic()
+            .context("failed to create a temporary directory to solve pypi dependencies")?;
+        let updated_lock_file = UpdateContext::builder(&project)
+            .with_lock_file(lock_file.clone())
+            .with_no_install(true)
+            .with_solve_prefix_dir(solve_prefix_dir.path())
+            .with_outdated_environments(outdated)
+            .finish()?
+            .update()
+            .await?;
+        updated_lock_file.write_to_disk()?;
+        updated_lock_file.lock_file
+    };
+
+    // Determine the diff between the old and new lock-file.
+    let diff = LockFileDiff::from_lock_files(&lock_file, &updated_lock_file);
+    if args.json {
+        let json_diff = LockFileJsonDiff::new(&project, diff);
+        return Ok(Some(
+            serde_json::to_value(json_diff).expect("failed to convert to json"),
+        ));
+    } else if diff.is_empty() {
+        eprintln!(
+            "{}Lock-file was already up-to-date",
+            console::style(console::Emoji("✔ ", "")).green()
+        );
+    } else {
+        diff.print()
+            .into_diagnostic()
+            .context("failed to print lock-file diff")?;
+    }
+
+    Ok(None)
+}
+
+/// Prints why the environments and platforms of a project are out of date
+/// with the lock-file to stderr.
+fn print_outdated_reasons(outdated: &OutdatedEnvironments) {
+    let mut explained = HashSet::new();
+    for reason in &outdated.reasons {
+        match reason {
+            OutdatedReason::MissingEnvironment(environment) => {
+                eprintln!(
+                    "{}environment {} is missing from the lock-file",
+                    console::style(console::Emoji("✘ ", "")).red(),
+                    environment.name().fancy_display()
+                );
+                explained.extend(
+                    environment
+                        .platforms()
+                        .into_iter()
+                        .map(|platform| (environment.name().clone(), platform)),
+                );
+            }
+            OutdatedReason::Environment(environment, unsat) => {
+                eprintln!(
+                    "{}environment {} is out of date because {unsat}",
+                    console::style(console::Emoji("✘ ", "")).red(),
+                    environment.name().fancy_display()
+                );
+                explained.extend(
+                    environment
+                        .platforms()
+                        .into_iter()
+                        .map(|platform| (environment.name().clone(), platform)),
+                );
+            }
+            OutdatedReason::Platform(environment, platform, unsat) => {
+                eprintln!(
+                    "{}environment {} is out of date for {} because {unsat}",
+                    console::style(console::Emoji("✘ ", "")).red(),
+                    environment.name().fancy_display(),
+                    consts::PLATFORM_STYLE.apply_to(platform)
+                );
+                explained.insert((environment.name().clone(), *platform));
+            }
+        }
+    }
+
+    // Environments can also become outdated because another environment in the
+    // same solve group changed.
+    let mut unexplained: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for (environment, platforms) in outdated.conda.iter().chain(&outdated.pypi) {
+        for platform in platforms {
+            if !explained.contains(&(environment.name().clone(), *platform)) {
+                unexplained
+                    .entry(environment.name().fancy_display().to_string())
+                    .or_default()
+                    .insert(platform.as_str());
+            }
+        }
+    }
+    for (name, platforms) in unexplained {
+        eprintln!(
+            "{}environment {} is out of date for {} because another environment in its solve group changed",
+            console::style(console::Emoji("✘ ", "")).red(),
+            name,
+            platforms
+                .iter()
+                .map(|platform| consts::PLATFORM_STYLE.apply_to(platform))
+                .format(", ")
+        );
+    }
+}
+
+/// Prints a human readable report of the stale content of a project to
+/// stderr.
+fn print_stale_content(stale: &StaleContent) {
+    for name in &stale.environments {
+        eprintln!(
+            "{}environment {} is no longer defined in the manifest",
+            console::style(console::Emoji("✘ ", "")).red(),
+            consts::ENVIRONMENT_STYLE.apply_to(name)
+        );
+    }
+
+    for (name, platforms) in &stale.platforms {
+        eprintln!(
+            "{}environment {} is locked for platforms that are no longer part of it: {}",
+            console::style(console::Emoji("✘ ", "")).red(),
+            consts::ENVIRONMENT_STYLE.apply_to(name),
+            platforms
+                .iter()
+                .map(|platform| consts::PLATFORM_STYLE.apply_to(platform))
+                .format(", ")
+        );
+    }
+
+    // Group the stale packages per environment and platform.
+    let mut packages: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for package in &stale.packages {
+        packages
+            .entry((package.environment.as_str(), package.platform.as_str()))
+            .or_default()
+            .insert(package.name());
+    }
+    for ((name, platform), packages) in packages {
+        eprintln!(
+            "{}environment {} has packages for {} that are no longer required: {}",
+            console::style(console::Emoji("✘ ", "")).red(),
+            consts::ENVIRONMENT_STYLE.apply_to(name),
+            consts::PLATFORM_STYLE.apply_to(platform),
+            packages.iter().format(", ")
+        );
+    }
+
+    // Merge the outdated conda and pypi platforms per environment.
+    let mut outdated: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for (environment, platforms) in stale.outdated.conda.iter().chain(&stale.outdated.pypi) {
+        outdated
+            .entry(environment.name().fancy_display().to_string())
+            .or_default()
+            .extend(platforms.iter().map(|platform| platform.as_str()));
+    }
+    for (name, platforms) in outdated {
+        eprintln!(
+            "{}environment {} is out of date for: {}",
+            console::style(console::Emoji("✘ ", "")).red(),
+            name,
+            platforms
+                .iter()
+                .map(|platform| consts::PLATFORM_STYLE.apply_to(platform))
+                .format(", ")
+        );
+    }
+
+    for prefix in &stale.orphaned_prefixes {
+        eprintln!(
+            "{}prefix {} does not belong to any environment",
+            console::style(console::Emoji("✘ ", "")).red(),
+            prefix.display()
+        );
+    }
+
+    if !stale.outdated.is_empty() {
+        eprintln!(
+            "{}",
+            console::style("Run `pixi update` or `pixi install` to update the outdated environments, pruning cannot fix them.").dim()
+        );
+    }
+}
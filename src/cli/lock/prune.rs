@@ -0,0 +1,88 @@
+use clap::Parser;
+use miette::{Context, IntoDiagnostic};
+
+use crate::{
+    cli::{clean::remove_folder_with_progress, cli_config::ProjectConfig},
+    diff::LockFileDiff,
+    load_lock_file,
+    lock_file::{prune_lock_file, StaleContent},
+    Project,
+};
+
+/// Remove environments, platforms and packages from the lock file that are no
+/// longer part of the manifest, together with orphaned prefixes in the
+/// environments directory.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// Only report what would be removed, without modifying the lock file or
+    /// the prefixes.
+    #[clap(short = 'n', long)]
+    pub dry_run: bool,
+
+    /// Only check whether there is stale content, without removing it. Exits
+    /// with a non-zero exit code if any stale content is found.
+    #[clap(long, conflicts_with = "dry_run")]
+    pub check: bool,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
+    let lock_file = load_lock_file(&project).await?;
+
+    let stale = StaleContent::from_project_and_lock_file(&project, &lock_file)?;
+    if stale.is_empty() {
+        eprintln!(
+            "{}{}",
+            console::style(console::Emoji("✔ ", "")).green(),
+            if args.check {
+                "Lock-file and environments are up-to-date with the manifest"
+            } else {
+                "Nothing to prune"
+            }
+        );
+        return Ok(());
+    }
+    super::print_stale_content(&stale);
+
+    if args.check {
+        miette::bail!(
+            "the lock-file or the environments of the project contain stale content, run `pixi lock prune` to remove it"
+        );
+    }
+
+    if stale.is_prunable_empty() {
+        return Ok(());
+    }
+
+    let pruned_lock_file = prune_lock_file(&project, &lock_file, &stale.packages);
+    let diff = LockFileDiff::from_lock_files(&lock_file, &pruned_lock_file);
+    if !diff.is_empty() {
+        diff.print()
+            .into_diagnostic()
+            .context("failed to print lock-file diff")?;
+    }
+
+    if args.dry_run {
+        eprintln!(
+            "{}",
+            console::style("Dry run, the lock-file and the prefixes were not modified.").dim()
+        );
+        return Ok(());
+    }
+
+    if !diff.is_empty() {
+        pruned_lock_file
+            .to_path(&project.lock_file_path())
+            .into_diagnostic()
+            .context("failed to write lock-file to disk")?;
+    }
+
+    for prefix in stale.orphaned_prefixes {
+        remove_folder_with_progress(prefix, false).await?;
+    }
+
+    Ok(())
+}
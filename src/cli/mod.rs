@@ -25,6 +25,7 @@ pub mod info;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod lock;
 pub mod project;
 pub mod remove;
 pub mod run;
@@ -83,6 +84,10 @@ struct Args {
     /// Hide all progress bars, always turned on if stderr is not a terminal.
     #[clap(long, default_value = "false", global = true, env = "PIXI_NO_PROGRESS")]
     no_progress: bool,
+
+    /// Do not access the network, only use artifacts that are already cached.
+    #[clap(long, global = true, env = "PIXI_OFFLINE")]
+    offline: bool,
 }
 impl Args {
     /// Whether to show progress bars or not, based on the terminal and the user's preference.
@@ -108,6 +113,7 @@ pub enum Command {
     Install(install::Args),
     Update(update::Args),
     Upgrade(upgrade::Args),
+    Lock(lock::Args),
 
     #[clap(visible_alias = "r")]
     Run(run::Args),
@@ -199,6 +205,11 @@ pub async fn execute() -> miette::Result<()> {
         global_multi_progress().set_draw_target(ProgressDrawTarget::hidden());
     }
 
+    // Every configuration that is loaded by the command is offline.
+    if args.offline {
+        pixi_config::Config::force_offline();
+    }
+
     let (low_level_filter, level_filter, pixi_level) = match args.verbose.log_level_filter() {
         clap_verbosity_flag::LevelFilter::Off => {
             (LevelFilter::OFF, LevelFilter::OFF, LevelFilter::OFF)
@@ -279,7 +290,7 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::Install(cmd) => install::execute(cmd).await,
         Command::Shell(cmd) => shell::execute(cmd).await,
         Command::ShellHook(cmd) => shell_hook::execute(cmd).await,
-        Command::Task(cmd) => task::execute(cmd),
+        Command::Task(cmd) => task::execute(cmd).await,
         Command::Info(cmd) => info::execute(cmd).await,
         Command::Upload(cmd) => upload::execute(cmd).await,
         Command::Search(cmd) => search::execute(cmd).await,
@@ -293,6 +304,7 @@ pub async fn execute_command(command: Command) -> miette::Result<()> {
         Command::Tree(cmd) => tree::execute(cmd).await,
         Command::Update(cmd) => update::execute(cmd).await,
         Command::Upgrade(cmd) => upgrade::execute(cmd).await,
+        Command::Lock(cmd) => lock::execute(cmd).await,
         Command::Exec(args) => exec::execute(args).await,
     }
 }
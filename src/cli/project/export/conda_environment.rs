@@ -43,10 +43,11 @@ fn format_pip_extras(extras: &[ExtraName]) -> String {
 }
 
 fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement) -> String {
-    match requirement {
+    let dependency = match requirement {
         PyPiRequirement::Git {
             url: git_url,
             extras,
+            ..
         } => {
             let mut git_string = format!(
                 "{name}{extras} @ git+{url}",
@@ -73,6 +74,7 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             path,
             editable,
             extras,
+            ..
         } => {
             if let Some(_editable) = editable {
                 format!(
@@ -92,6 +94,7 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             url,
             subdirectory,
             extras,
+            ..
         } => {
             let mut url_string = format!(
                 "{name}{extras} @ {url}",
@@ -106,7 +109,9 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
 
             url_string
         }
-        PyPiRequirement::Version { version, extras } => {
+        PyPiRequirement::Version {
+            version, extras, ..
+        } => {
             format!(
                 "{name}{extras}{version}",
                 name = name.as_normalized(),
@@ -122,6 +127,12 @@ fn format_pip_dependency(name: &PyPiPackageName, requirement: &PyPiRequirement)
             ),
             VersionOrStar::Star => format!("{name}", name = name.as_normalized()),
         },
+    };
+
+    // Append the environment markers of the requirement
+    match requirement.markers().try_to_string() {
+        Some(markers) => format!("{dependency}; {markers}"),
+        None => dependency,
     }
 }
 
@@ -0,0 +1,369 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use clap::Parser;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_digest::{compute_bytes_digest, Sha256};
+use rattler_lock::{Environment, Package, PackageHashes, PypiPackageData, UrlOrPath};
+use serde::Serialize;
+
+use super::requirements_txt::strip_direct_scheme;
+use crate::cli::cli_config::PrefixUpdateConfig;
+use crate::lock_file::UpdateLockFileOptions;
+use crate::Project;
+
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// Explicit path to export the `conda-lock.yml` file to.
+    /// Defaults to printing to stdout.
+    pub output_path: Option<PathBuf>,
+
+    /// The platform to render. Can be repeated for multiple platforms.
+    /// Defaults to all platforms available for the selected environment.
+    #[arg(short, long)]
+    pub platform: Option<Vec<Platform>>,
+
+    /// The environment to render the lock file for.
+    /// Defaults to the default environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// A lock file in the version 1 format of `conda-lock`.
+#[derive(Debug, Serialize)]
+struct CondaLock {
+    version: u32,
+    metadata: CondaLockMetadata,
+    package: Vec<CondaLockPackage>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockMetadata {
+    content_hash: IndexMap<String, String>,
+    channels: Vec<CondaLockChannel>,
+    platforms: Vec<String>,
+    sources: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockChannel {
+    url: String,
+    used_env_vars: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct CondaLockPackage {
+    name: String,
+    version: String,
+    manager: &'static str,
+    platform: String,
+    dependencies: BTreeMap<String, String>,
+    url: String,
+    hash: CondaLockHash,
+    category: &'static str,
+    optional: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct CondaLockHash {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    md5: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+/// Splits a conda dependency like `libzlib >=1.2.13,<2.0a0` into its name and
+/// its version constraint.
+pub(super) fn split_conda_dependency(dependency: &str) -> (String, String) {
+    match dependency.trim().split_once(char::is_whitespace) {
+        Some((name, spec)) => (name.to_string(), spec.trim().to_string()),
+        None => (dependency.trim().to_string(), String::from("*")),
+    }
+}
+
+/// Converts a locked conda package into a `conda-lock` package entry.
+fn conda_package(record: RepoDataRecord, platform: Platform) -> CondaLockPackage {
+    let package_record = record.package_record;
+    CondaLockPackage {
+        name: package_record.name.as_normalized().to_string(),
+        version: package_record.version.to_string(),
+        manager: "conda",
+        platform: platform.to_string(),
+        dependencies: package_record
+            .depends
+            .iter()
+            .map(|dependency| split_conda_dependency(dependency))
+            .collect(),
+        url: record.url.to_string(),
+        hash: CondaLockHash {
+            md5: package_record.md5.map(|md5| format!("{md5:x}")),
+            sha256: package_record.sha256.map(|sha256| format!("{sha256:x}")),
+        },
+        category: "main",
+        optional: false,
+    }
+}
+
+/// Converts a locked PyPI package into a `conda-lock` package entry.
+fn pypi_package(package: &PypiPackageData, platform: Platform) -> miette::Result<CondaLockPackage> {
+    let url = match &package.url_or_path {
+        UrlOrPath::Url(url) => strip_direct_scheme(url).to_string(),
+        UrlOrPath::Path(_) => miette::bail!(
+            "the pypi package '{}' is a local source tree, which cannot be represented in a conda-lock file",
+            package.name
+        ),
+    };
+
+    let hash = match &package.hash {
+        Some(PackageHashes::Sha256(sha256) | PackageHashes::Md5Sha256(_, sha256)) => {
+            CondaLockHash {
+                sha256: Some(format!("{sha256:x}")),
+                ..CondaLockHash::default()
+            }
+        }
+        Some(PackageHashes::Md5(md5)) => CondaLockHash {
+            md5: Some(format!("{md5:x}")),
+            ..CondaLockHash::default()
+        },
+        None => CondaLockHash::default(),
+    };
+
+    // Only dependencies that apply unconditionally are recorded, conditional
+    // dependencies depend on the extras and markers of the requirement.
+    let dependencies = package
+        .requires_dist
+        .iter()
+        .filter(|requirement| requirement.marker.is_true())
+        .map(|requirement| {
+            let spec = match &requirement.version_or_url {
+                Some(pep508_rs::VersionOrUrl::VersionSpecifier(specifiers))
+                    if !specifiers.is_empty() =>
+                {
+                    specifiers.to_string()
+                }
+                _ => String::from("*"),
+            };
+            (requirement.name.to_string(), spec)
+        })
+        .collect();
+
+    Ok(CondaLockPackage {
+        name: package.name.to_string(),
+        version: package.version.to_string(),
+        manager: "pip",
+        platform: platform.to_string(),
+        dependencies,
+        url,
+        hash,
+        category: "main",
+        optional: false,
+    })
+}
+
+/// Renders the packages of an environment for the given platforms as a
+/// `conda-lock.yml` file.
+fn render_conda_lock(
+    environment: &Environment,
+    platforms: &[Platform],
+    sources: Vec<String>,
+) -> miette::Result<String> {
+    let mut content_hash = IndexMap::new();
+    let mut packages = Vec::new();
+    for &platform in platforms {
+        let locked_packages = environment.packages(platform).ok_or_else(|| {
+            miette::miette!("the lock-file does not contain packages for platform '{platform}'")
+        })?;
+
+        let mut platform_packages = Vec::new();
+        for package in locked_packages {
+            match package {
+                Package::Conda(package) => {
+                    let record = RepoDataRecord::try_from(package)
+                        .into_diagnostic()
+                        .with_context(|| "failed to convert conda package to a RepoDataRecord")?;
+                    platform_packages.push(conda_package(record, platform));
+                }
+                Package::Pypi(package) => {
+                    platform_packages.push(pypi_package(&package.data().package, platform)?);
+                }
+            }
+        }
+        platform_packages.sort_by(|a, b| a.manager.cmp(b.manager).then(a.name.cmp(&b.name)));
+
+        // The content hash identifies the locked packages of the platform.
+        let identity = platform_packages
+            .iter()
+            .map(|package| {
+                format!(
+                    "{} {} {}",
+                    package.url,
+                    package.hash.md5.as_deref().unwrap_or_default(),
+                    package.hash.sha256.as_deref().unwrap_or_default()
+                )
+            })
+            .join("\n");
+        content_hash.insert(
+            platform.to_string(),
+            format!("{:x}", compute_bytes_digest::<Sha256>(identity)),
+        );
+        packages.extend(platform_packages);
+    }
+
+    let conda_lock = CondaLock {
+        version: 1,
+        metadata: CondaLockMetadata {
+            content_hash,
+            channels: environment
+                .channels()
+                .iter()
+                .map(|channel| CondaLockChannel {
+                    url: channel.url.clone(),
+                    used_env_vars: Vec::new(),
+                })
+                .collect(),
+            platforms: platforms.iter().map(ToString::to_string).collect(),
+            sources,
+        },
+        package: packages,
+    };
+
+    serde_yaml::to_string(&conda_lock).into_diagnostic()
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| miette::miette!("unknown environment {}", environment.name()))?;
+
+    let available_platforms = locked_environment
+        .platforms()
+        .sorted_by_key(|platform| platform.as_str())
+        .collect_vec();
+    let platforms = match args.platform {
+        Some(platforms) => {
+            for platform in &platforms {
+                if !available_platforms.contains(platform) {
+                    miette::bail!(
+                        "platform '{platform}' is not available for environment {}",
+                        environment.name()
+                    );
+                }
+            }
+            platforms
+        }
+        None => available_platforms,
+    };
+
+    let conda_lock = render_conda_lock(
+        &locked_environment,
+        &platforms,
+        vec![project.manifest.file_name().to_string()],
+    )?;
+
+    if let Some(output_path) = args.output_path {
+        fs_err::write(&output_path, conda_lock)
+            .into_diagnostic()
+            .with_context(|| "failed to write conda-lock.yml")?;
+    } else {
+        print!("{conda_lock}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_lock::LockFile;
+
+    use super::*;
+
+    #[test]
+    fn test_split_conda_dependency() {
+        assert_eq!(
+            split_conda_dependency("libzlib >=1.2.13,<2.0a0"),
+            (String::from("libzlib"), String::from(">=1.2.13,<2.0a0"))
+        );
+        assert_eq!(
+            split_conda_dependency("python_abi 3.12.* *_cp312"),
+            (String::from("python_abi"), String::from("3.12.* *_cp312"))
+        );
+        assert_eq!(
+            split_conda_dependency("tzdata"),
+            (String::from("tzdata"), String::from("*"))
+        );
+    }
+
+    #[test]
+    fn test_render_conda_lock() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mock-projects/test-project-export/pixi.lock");
+        let lock_file = LockFile::from_path(&path).unwrap();
+        let environment = lock_file.environment("default").unwrap();
+        let platforms = [Platform::Linux64, Platform::OsxArm64];
+
+        let conda_lock =
+            render_conda_lock(&environment, &platforms, vec![String::from("pixi.toml")]).unwrap();
+        let document: serde_yaml::Value = serde_yaml::from_str(&conda_lock).unwrap();
+
+        assert_eq!(document["version"].as_u64(), Some(1));
+        assert_eq!(
+            document["metadata"]["platforms"],
+            serde_yaml::to_value(["linux-64", "osx-arm64"]).unwrap()
+        );
+        assert_eq!(
+            document["metadata"]["channels"][0]["url"].as_str(),
+            Some("https://conda.anaconda.org/conda-forge/")
+        );
+        assert!(document["metadata"]["content_hash"]["linux-64"].is_string());
+
+        let packages = document["package"].as_sequence().unwrap();
+        for platform in platforms {
+            let expected = environment.packages(platform).unwrap().count();
+            let locked = packages
+                .iter()
+                .filter(|package| package["platform"].as_str() == Some(platform.as_str()))
+                .count();
+            assert_eq!(locked, expected);
+        }
+
+        let rich = packages
+            .iter()
+            .find(|package| {
+                package["name"].as_str() == Some("rich")
+                    && package["platform"].as_str() == Some("linux-64")
+            })
+            .unwrap();
+        assert_eq!(rich["manager"].as_str(), Some("pip"));
+        assert_eq!(rich["version"].as_str(), Some("13.8.0"));
+        assert!(rich["hash"]["sha256"].is_string());
+
+        let python = packages
+            .iter()
+            .find(|package| {
+                package["name"].as_str() == Some("python")
+                    && package["platform"].as_str() == Some("linux-64")
+            })
+            .unwrap();
+        assert_eq!(python["manager"].as_str(), Some("conda"));
+        assert!(python["hash"]["md5"].is_string());
+        assert!(python["dependencies"]["bzip2"].is_string());
+    }
+}
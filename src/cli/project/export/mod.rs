@@ -1,6 +1,11 @@
 use std::path::PathBuf;
 pub mod conda_environment;
 pub mod conda_explicit_spec;
+pub mod conda_lock;
+pub mod oci;
+pub mod pylock;
+pub mod requirements_txt;
+pub mod sbom;
 
 use crate::Project;
 use clap::Parser;
@@ -23,6 +28,16 @@ pub enum Command {
     CondaExplicitSpec(conda_explicit_spec::Args),
     /// Export project environment to a conda environment.yaml file
     CondaEnvironment(conda_environment::Args),
+    /// Export the PyPI packages of a project environment to a requirements.txt file with hashes
+    RequirementsTxt(requirements_txt::Args),
+    /// Export the PyPI packages of a project environment to a PEP 751 pylock.toml file
+    Pylock(pylock::Args),
+    /// Export project environment to a multi-platform conda-lock.yml file
+    CondaLock(conda_lock::Args),
+    /// Export a software bill of materials of a project environment
+    Sbom(sbom::Args),
+    /// Export project environments as an OCI image
+    Oci(oci::Args),
 }
 
 pub async fn execute(args: Args) -> miette::Result<()> {
@@ -30,6 +45,11 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     match args.command {
         Command::CondaExplicitSpec(args) => conda_explicit_spec::execute(project, args).await?,
         Command::CondaEnvironment(args) => conda_environment::execute(project, args).await?,
+        Command::RequirementsTxt(args) => requirements_txt::execute(project, args).await?,
+        Command::Pylock(args) => pylock::execute(project, args).await?,
+        Command::CondaLock(args) => conda_lock::execute(project, args).await?,
+        Command::Sbom(args) => sbom::execute(project, args).await?,
+        Command::Oci(args) => oci::execute(project, args).await?,
     };
     Ok(())
 }
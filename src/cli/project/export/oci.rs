@@ -0,0 +1,1223 @@
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use pixi_consts::consts;
+use pixi_manifest::{FeaturesExt, TaskName};
+use rattler::package_cache::PackageCache;
+use rattler_conda_types::{PackageRecord, Platform, RepoDataRecord};
+use rattler_digest::{compute_bytes_digest, compute_file_digest, Sha256};
+use rattler_lock::{LockFile, Package};
+use serde_json::{json, Value};
+
+use crate::{
+    activation::get_static_environment_variables,
+    cli::cli_config::PrefixUpdateConfig,
+    lock_file::UpdateLockFileOptions,
+    package_layout::{
+        conda_meta_path, conda_package_paths, python_version, replace_all, replace_prefix,
+        PrefixPath, PrefixPathKind, SCRIPT_PLACEHOLDER,
+    },
+    project::{Environment, HasProjectRef},
+    Project,
+};
+
+/// The directory in the image that contains everything that is added by pixi.
+const IMAGE_ROOT: &str = "/opt/pixi";
+
+/// The directory in the image that contains the activation scripts of the
+/// project.
+const IMAGE_PROJECT_DIR: &str = "/opt/pixi/project";
+
+/// The script that activates an environment before running the command of the
+/// container.
+const ENTRYPOINT_SCRIPT: &str = r#"#!/bin/bash
+# Generated by `pixi project export oci`
+source "/opt/pixi/activate/${PIXI_ENVIRONMENT_NAME}.sh"
+exec "$@"
+"#;
+
+/// The locations of `bash` in the base image, one of them is required by the
+/// entrypoint.
+const BASH_PATHS: [&str; 2] = ["bin/bash", "usr/bin/bash"];
+
+/// Variables that only make sense on the machine that exported the image.
+const SKIPPED_VARIABLES: [&str; 1] = ["PIXI_EXE"];
+
+/// The `PATH` that is used when the base image does not define one.
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+const LAYER_MEDIA_TYPE: &str = "application/vnd.oci.image.layer.v1.tar+gzip";
+const DOCKER_MANIFEST_LIST_MEDIA_TYPE: &str =
+    "application/vnd.docker.distribution.manifest.list.v2+json";
+
+/// Export environments of the project as an OCI image.
+///
+/// The image is written as an OCI image layout archive without the need for a
+/// container daemon. It can be loaded with tools like `podman load` or `skopeo
+/// copy oci-archive:<OUTPUT> ...`. The locked conda packages of the
+/// environments are extracted into the image, nothing is installed locally.
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// The environments to add to the image. Can be repeated, the first
+    /// environment is activated by default. Defaults to the default
+    /// environment.
+    #[arg(short, long)]
+    pub environment: Option<Vec<String>>,
+
+    /// A task of the first environment to use as the default command of the
+    /// image.
+    #[arg(long)]
+    pub task: Option<String>,
+
+    /// The linux platform to export the image for. Defaults to the current
+    /// platform.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// An OCI image layout archive or directory to use as the base of the
+    /// image, e.g. created with `skopeo copy docker://debian:bookworm-slim
+    /// oci-archive:base.tar`. It provides the C library and the `bash` that
+    /// are required by the environments.
+    #[arg(long)]
+    pub base_image: PathBuf,
+
+    /// The tag of the image.
+    #[arg(long, default_value = "latest")]
+    pub tag: String,
+
+    /// The path of the archive to create. Defaults to `<project>-oci.tar` in
+    /// the current directory.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// A layer that has been written to the blob directory.
+struct Layer {
+    /// The descriptor of the compressed layer.
+    descriptor: Value,
+
+    /// The digest of the uncompressed layer.
+    diff_id: String,
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let project = project.with_cli_config(args.prefix_update_config.config.clone());
+
+    let platform = args.platform.unwrap_or_else(Platform::current);
+    let Some((architecture, variant)) = oci_architecture(platform) else {
+        miette::bail!(
+            help = "pass a linux platform with `--platform`, e.g. `--platform linux-64`",
+            "OCI images can only be exported for linux platforms, not for '{platform}'"
+        );
+    };
+
+    let environments = match &args.environment {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                project
+                    .environment(name.as_str())
+                    .ok_or_else(|| miette::miette!("unknown environment '{name}'"))
+            })
+            .collect::<miette::Result<Vec<_>>>()?,
+        None => vec![project.default_environment()],
+    };
+    for environment in &environments {
+        if !environment.platforms().contains(&platform) {
+            miette::bail!(
+                "environment {} does not support the platform {}",
+                environment.name().fancy_display(),
+                consts::PLATFORM_STYLE.apply_to(platform)
+            );
+        }
+    }
+    let task_command = args
+        .task
+        .as_deref()
+        .map(|name| default_command(&environments[0], name, platform))
+        .transpose()?;
+
+    // The entrypoint activates the environments with bash, so the image can only
+    // start if the base image provides it.
+    let base_image = BaseImage::from_path(&args.base_image, architecture)?;
+    if !base_image.contains_any(&BASH_PATHS)? {
+        miette::bail!(
+            help = "use a base image that contains bash, e.g. `debian:bookworm-slim`",
+            "the base image '{}' does not contain '/bin/bash' which is required by the entrypoint of the image",
+            args.base_image.display()
+        );
+    }
+
+    // The environments are not installed, their packages are extracted into the
+    // image instead.
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: true,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+    let environment_records = environments
+        .iter()
+        .map(|environment| locked_conda_records(&lock_file.lock_file, environment, platform))
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    let blobs = tempfile::tempdir()
+        .into_diagnostic()
+        .context("failed to create a temporary directory")?;
+
+    // The base layer contains the entrypoint and the activation scripts of all
+    // environments.
+    let mut base_files = vec![(
+        format!("{IMAGE_ROOT}/entrypoint.sh"),
+        ENTRYPOINT_SCRIPT.as_bytes().to_vec(),
+        0o755,
+    )];
+    for environment in &environments {
+        base_files.push((
+            format!("{IMAGE_ROOT}/activate/{}.sh", environment.name()),
+            activation_script(environment, platform, &image_prefix(environment)).into_bytes(),
+            0o644,
+        ));
+        for script in environment.activation_scripts(Some(platform)) {
+            let path = project.root().join(&script);
+            if path.is_file() {
+                base_files.push((
+                    format!("{IMAGE_PROJECT_DIR}/{}", script.replace('\\', "/")),
+                    fs_err::read(&path).into_diagnostic()?,
+                    0o755,
+                ));
+            }
+        }
+    }
+    let mut layers = vec![(
+        String::from("base"),
+        write_layer(blobs.path(), |builder| {
+            let directories = base_files
+                .iter()
+                .flat_map(|(path, _, _)| parent_directories(path))
+                .collect::<BTreeSet<_>>();
+            for directory in directories {
+                append_file(builder, &directory, &[], 0o755)?;
+            }
+            for (path, content, mode) in &base_files {
+                append_file(builder, path, content, *mode)?;
+            }
+            Ok(())
+        })?,
+    )];
+
+    // Add a layer with the prefix of every environment. The packages are taken
+    // from the package cache, they are only downloaded if they are not cached
+    // yet.
+    let client = project.authenticated_client();
+    let package_cache =
+        PackageCache::new(pixi_config::get_cache_dir()?.join(consts::CONDA_PACKAGE_CACHE_DIR));
+    for (environment, records) in environments.iter().zip(environment_records) {
+        let python_version = python_version(&records);
+        let mut packages = Vec::with_capacity(records.len());
+        for record in records {
+            let cache_lock = package_cache
+                .get_or_fetch_from_url(
+                    &record.package_record,
+                    record.url.clone(),
+                    client.clone(),
+                    None,
+                )
+                .await
+                .into_diagnostic()
+                .with_context(|| format!("failed to fetch '{}'", record.file_name))?;
+            packages.push((record, cache_lock));
+        }
+
+        let image_prefix = image_prefix(environment);
+        let layer = write_layer(blobs.path(), |builder| {
+            let mut directories = BTreeSet::new();
+            append_directories(
+                builder,
+                &mut directories,
+                &format!("{image_prefix}/conda-meta/"),
+            )?;
+            append_file(
+                builder,
+                &format!("{image_prefix}/conda-meta/history"),
+                &[],
+                0o644,
+            )?;
+            for (record, cache_lock) in &packages {
+                // The package is recorded in the prefix like conda does, so tools
+                // that inspect the prefix know about it.
+                let paths = conda_package_paths(cache_lock.path(), record, python_version)?;
+                let conda_meta = conda_meta_path(record, &paths)?;
+                for prefix_path in paths.into_iter().chain([conda_meta]) {
+                    append_prefix_path(builder, &mut directories, prefix_path, &image_prefix)?;
+                }
+            }
+            Ok(())
+        })?;
+        layers.push((environment.name().to_string(), layer));
+    }
+
+    // Create the configuration of the image on top of the configuration of the
+    // base image.
+    let mut config = base_image.config.clone();
+    config["architecture"] = json!(architecture);
+    config["os"] = json!("linux");
+    if let Some(variant) = variant {
+        config["variant"] = json!(variant);
+    }
+    let image_config = &mut config["config"];
+    let mut env = image_config["Env"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .filter_map(|var| var.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<IndexMap<_, _>>();
+    let path = env
+        .get("PATH")
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_PATH.to_string());
+    env.insert(
+        String::from("PATH"),
+        format!("{}/bin:{path}", image_prefix(&environments[0])),
+    );
+    env.insert(
+        String::from("PIXI_ENVIRONMENT_NAME"),
+        environments[0].name().to_string(),
+    );
+    let cmd = match task_command {
+        Some((command, task_env)) => {
+            env.extend(task_env);
+            json!(["bash", "-c", command])
+        }
+        None => image_config
+            .get("Cmd")
+            .filter(|cmd| !cmd.is_null())
+            .cloned()
+            .unwrap_or_else(|| json!(["bash"])),
+    };
+    image_config["Env"] = env
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    image_config["Entrypoint"] = json!([format!("{IMAGE_ROOT}/entrypoint.sh")]);
+    image_config["Cmd"] = cmd;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}-oci.tar", project.name())));
+    write_image(
+        &output,
+        blobs.path(),
+        &base_image,
+        config,
+        &layers,
+        &args.tag,
+    )?;
+
+    eprintln!(
+        "{}Exported an image with {} {} for {} to {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        if environments.len() == 1 {
+            "environment"
+        } else {
+            "environments"
+        },
+        environments
+            .iter()
+            .map(|env| env.name().fancy_display())
+            .join(", "),
+        consts::PLATFORM_STYLE.apply_to(platform),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Writes an OCI image layout archive to `output` that contains the layers of
+/// the base image followed by the given layers. The layers and the
+/// configuration are stored in the `blobs` directory, and each layer is
+/// recorded in the history under the given name.
+fn write_image(
+    output: &Path,
+    blobs: &Path,
+    base_image: &BaseImage,
+    mut config: Value,
+    layers: &[(String, Layer)],
+    tag: &str,
+) -> miette::Result<()> {
+    let mut layer_descriptors = base_image.layers.clone();
+    let mut diff_ids = base_image.config["rootfs"]["diff_ids"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let mut history = base_image.config["history"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    for (name, layer) in layers {
+        layer_descriptors.push(layer.descriptor.clone());
+        diff_ids.push(json!(layer.diff_id));
+        history.push(json!({
+            "created_by": format!("pixi project export oci ({name})"),
+        }));
+    }
+    config["rootfs"] = json!({ "type": "layers", "diff_ids": diff_ids });
+    config["history"] = json!(history);
+
+    let config_descriptor = write_blob(
+        blobs,
+        CONFIG_MEDIA_TYPE,
+        &serde_json::to_vec(&config).into_diagnostic()?,
+    )?;
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "config": config_descriptor,
+        "layers": layer_descriptors,
+    });
+    let mut manifest_descriptor = write_blob(
+        blobs,
+        MANIFEST_MEDIA_TYPE,
+        &serde_json::to_vec(&manifest).into_diagnostic()?,
+    )?;
+    manifest_descriptor["annotations"] = json!({
+        "org.opencontainers.image.ref.name": tag,
+    });
+    let index = json!({
+        "schemaVersion": 2,
+        "mediaType": INDEX_MEDIA_TYPE,
+        "manifests": [manifest_descriptor],
+    });
+
+    let mut archive = tar::Builder::new(
+        fs_err::File::create(output)
+            .into_diagnostic()
+            .context("failed to create the archive")?,
+    );
+    append_file(
+        &mut archive,
+        "oci-layout",
+        br#"{"imageLayoutVersion":"1.0.0"}"#,
+        0o644,
+    )?;
+    append_file(
+        &mut archive,
+        "index.json",
+        &serde_json::to_vec(&index).into_diagnostic()?,
+        0o644,
+    )?;
+    append_file(&mut archive, "blobs/", &[], 0o755)?;
+    append_file(&mut archive, "blobs/sha256/", &[], 0o755)?;
+    let blob_paths = base_image
+        .layers
+        .iter()
+        .map(|layer| base_image.blob_path(&layer["digest"]))
+        .chain(
+            std::iter::once(&config_descriptor)
+                .chain(std::iter::once(&manifest_descriptor))
+                .chain(layers.iter().map(|(_, layer)| &layer.descriptor))
+                .map(|descriptor| blob_path(blobs, &descriptor["digest"])),
+        );
+    for path in blob_paths {
+        let path = path?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        archive
+            .append_path_with_name(&path, format!("blobs/sha256/{name}"))
+            .into_diagnostic()
+            .with_context(|| format!("failed to add '{}' to the archive", path.display()))?;
+    }
+    archive
+        .into_inner()
+        .and_then(|mut file| file.flush())
+        .into_diagnostic()
+        .context("failed to write the archive")
+}
+
+/// Returns the OCI architecture and variant of a platform, or `None` if the
+/// platform is not a linux platform.
+fn oci_architecture(platform: Platform) -> Option<(&'static str, Option<&'static str>)> {
+    match platform.as_str() {
+        "linux-64" => Some(("amd64", None)),
+        "linux-32" => Some(("386", None)),
+        "linux-aarch64" => Some(("arm64", Some("v8"))),
+        "linux-armv6l" => Some(("arm", Some("v6"))),
+        "linux-armv7l" => Some(("arm", Some("v7"))),
+        "linux-ppc64le" => Some(("ppc64le", None)),
+        "linux-ppc64" => Some(("ppc64", None)),
+        "linux-s390x" => Some(("s390x", None)),
+        "linux-riscv64" => Some(("riscv64", None)),
+        _ => None,
+    }
+}
+
+/// Returns the location of the prefix of an environment in the image.
+fn image_prefix(environment: &Environment) -> String {
+    format!("{IMAGE_ROOT}/envs/{}", environment.name())
+}
+
+/// Returns the command and the environment variables of a task that is used as
+/// the default command of the image.
+fn default_command(
+    environment: &Environment,
+    name: &str,
+    platform: Platform,
+) -> miette::Result<(String, IndexMap<String, String>)> {
+    let task = environment
+        .task(&TaskName::from(name), Some(platform))
+        .map_err(|err| miette::miette!("{err}"))?;
+    if !task.depends_on().is_empty() {
+        miette::bail!(
+            "the task '{name}' depends on other tasks, only tasks without dependencies can be used as the default command"
+        );
+    }
+    let command = task.as_single_command().ok_or_else(|| {
+        miette::miette!("the task '{name}' is an alias and can't be used as the default command")
+    })?;
+    Ok((
+        command.into_owned(),
+        task.env().cloned().unwrap_or_default(),
+    ))
+}
+
+/// Renders the bash activation script of an environment for its location in
+/// the image. The activation scripts of the packages are sourced when the
+/// container starts, followed by the activation scripts of the project. The
+/// variables of the env-files are left out, they often contain secrets that
+/// must not be baked into the image.
+fn activation_script(environment: &Environment, platform: Platform, image_prefix: &str) -> String {
+    let prefix = environment.dir().to_string_lossy().into_owned();
+    let project_root = environment.project().root().to_string_lossy().into_owned();
+    let mut variables = get_static_environment_variables(environment);
+    variables.extend(environment.activation_env(Some(platform)));
+
+    let mut script = String::from("# Generated by `pixi project export oci`\n");
+    script.push_str(&format!("export PATH=\"{image_prefix}/bin:${{PATH}}\"\n"));
+    script.push_str(&format!("export CONDA_PREFIX='{image_prefix}'\n"));
+    for (key, value) in variables {
+        if SKIPPED_VARIABLES.contains(&key.as_str()) {
+            continue;
+        }
+        // The prefix is replaced first because it is usually located inside the
+        // project.
+        let value = value
+            .replace(&prefix, image_prefix)
+            .replace(&project_root, IMAGE_PROJECT_DIR)
+            .replace('\'', "'\\''");
+        script.push_str(&format!("export {key}='{value}'\n"));
+    }
+    script.push_str(&format!(
+        "for script in \"{image_prefix}/etc/conda/activate.d/\"*.sh; do\n    if [ -f \"$script\" ]; then . \"$script\"; fi\ndone\n"
+    ));
+    for activation_script in environment.activation_scripts(Some(platform)) {
+        if environment
+            .project()
+            .root()
+            .join(&activation_script)
+            .is_file()
+        {
+            script.push_str(&format!(
+                ". '{IMAGE_PROJECT_DIR}/{}'\n",
+                activation_script.replace('\\', "/")
+            ));
+        }
+    }
+    script
+}
+
+/// Writes a blob to the blob directory and returns its descriptor.
+fn write_blob(dir: &Path, media_type: &str, content: &[u8]) -> miette::Result<Value> {
+    let hash = format!("{:x}", compute_bytes_digest::<Sha256>(content));
+    fs_err::write(dir.join(&hash), content).into_diagnostic()?;
+    Ok(json!({
+        "mediaType": media_type,
+        "digest": format!("sha256:{hash}"),
+        "size": content.len(),
+    }))
+}
+
+/// Returns the path of a blob in the blob directory.
+fn blob_path(dir: &Path, digest: &Value) -> miette::Result<PathBuf> {
+    let hash = digest
+        .as_str()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .ok_or_else(|| miette::miette!("unsupported digest {digest}"))?;
+    Ok(dir.join(hash))
+}
+
+/// Writes a layer to the blob directory. The content of the layer is added by
+/// `build`.
+fn write_layer(
+    dir: &Path,
+    build: impl FnOnce(&mut tar::Builder<fs_err::File>) -> miette::Result<()>,
+) -> miette::Result<Layer> {
+    let tar_path = dir.join("layer.tar");
+    let mut builder = tar::Builder::new(fs_err::File::create(&tar_path).into_diagnostic()?);
+    build(&mut builder)?;
+    builder
+        .into_inner()
+        .and_then(|mut file| file.flush())
+        .into_diagnostic()
+        .context("failed to write the layer")?;
+    let diff_id = compute_file_digest::<Sha256>(&tar_path).into_diagnostic()?;
+
+    // Compress the layer.
+    let gz_path = dir.join("layer.tar.gz");
+    let mut encoder = GzEncoder::new(
+        fs_err::File::create(&gz_path).into_diagnostic()?,
+        Compression::default(),
+    );
+    std::io::copy(
+        &mut fs_err::File::open(&tar_path).into_diagnostic()?,
+        &mut encoder,
+    )
+    .into_diagnostic()?;
+    encoder
+        .finish()
+        .and_then(|mut file| file.flush())
+        .into_diagnostic()
+        .context("failed to compress the layer")?;
+    fs_err::remove_file(&tar_path).into_diagnostic()?;
+
+    let hash = format!(
+        "{:x}",
+        compute_file_digest::<Sha256>(&gz_path).into_diagnostic()?
+    );
+    let size = fs_err::metadata(&gz_path).into_diagnostic()?.len();
+    fs_err::rename(&gz_path, dir.join(&hash)).into_diagnostic()?;
+
+    Ok(Layer {
+        descriptor: json!({
+            "mediaType": LAYER_MEDIA_TYPE,
+            "digest": format!("sha256:{hash}"),
+            "size": size,
+        }),
+        diff_id: format!("sha256:{diff_id:x}"),
+    })
+}
+
+/// Appends a file with the given content to the archive. Paths ending in a
+/// slash are added as directories. Absolute paths are stored relative to the
+/// root of the archive.
+fn append_file<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    content: &[u8],
+    mode: u32,
+) -> miette::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(if path.ends_with('/') {
+        tar::EntryType::Directory
+    } else {
+        tar::EntryType::Regular
+    });
+    header.set_size(content.len() as u64);
+    header.set_mode(mode);
+    builder
+        .append_data(&mut header, path.trim_start_matches('/'), content)
+        .into_diagnostic()
+        .with_context(|| format!("failed to add '{path}' to the archive"))
+}
+
+/// Returns the directories that contain `path`, starting at the root. A path
+/// ending in a slash is included itself.
+fn parent_directories(path: &str) -> Vec<String> {
+    let path = path.trim_start_matches('/');
+    path.match_indices('/')
+        .map(|(idx, _)| path[..=idx].to_string())
+        .collect()
+}
+
+/// Appends the directories that contain `path` to the archive, skipping the
+/// ones that were already added. A path ending in a slash is added itself.
+fn append_directories<W: Write>(
+    builder: &mut tar::Builder<W>,
+    directories: &mut BTreeSet<String>,
+    path: &str,
+) -> miette::Result<()> {
+    for directory in parent_directories(path) {
+        if directories.insert(directory.clone()) {
+            append_file(builder, &directory, &[], 0o755)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the locked conda packages of an environment for a platform, in the
+/// order in which they are installed.
+fn locked_conda_records(
+    lock_file: &LockFile,
+    environment: &Environment,
+    platform: Platform,
+) -> miette::Result<Vec<RepoDataRecord>> {
+    let packages = lock_file
+        .environment(environment.name().as_str())
+        .and_then(|env| env.packages(platform).map(Vec::from_iter))
+        .ok_or_else(|| {
+            miette::miette!(
+                "the lock-file does not contain packages for environment {} on platform {}",
+                environment.name().fancy_display(),
+                consts::PLATFORM_STYLE.apply_to(platform)
+            )
+        })?;
+
+    let mut records = Vec::with_capacity(packages.len());
+    for package in packages {
+        match package {
+            Package::Conda(package) => records.push(
+                RepoDataRecord::try_from(package)
+                    .into_diagnostic()
+                    .context("failed to convert conda packages to RepoDataRecords")?,
+            ),
+            Package::Pypi(package) => miette::bail!(
+                "environment {} contains the pypi package '{}', only conda packages can be exported to an image",
+                environment.name().fancy_display(),
+                package.data().package.name
+            ),
+        }
+    }
+    Ok(PackageRecord::sort_topologically(records))
+}
+
+/// Appends a path of the prefix of an environment to the archive, at the
+/// location of the prefix in the image. The prefix placeholders of the files
+/// are replaced by that location.
+fn append_prefix_path<W: Write>(
+    builder: &mut tar::Builder<W>,
+    directories: &mut BTreeSet<String>,
+    prefix_path: PrefixPath,
+    image_prefix: &str,
+) -> miette::Result<()> {
+    let path = format!("{image_prefix}/{}", prefix_path.relative_path);
+    append_directories(builder, directories, &path)?;
+    match prefix_path.kind {
+        PrefixPathKind::Directory => {
+            append_directories(builder, directories, &format!("{path}/"))?;
+        }
+        PrefixPathKind::Symlink(target) => {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, path.trim_start_matches('/'), &target)
+                .into_diagnostic()
+                .with_context(|| format!("failed to add '{path}' to the layer"))?;
+        }
+        PrefixPathKind::File {
+            source,
+            placeholder,
+        } => {
+            let metadata = fs_err::metadata(&source).into_diagnostic()?;
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata_in_mode(&metadata, tar::HeaderMode::Deterministic);
+            let content = fs_err::read(&source).into_diagnostic()?;
+            let content = match &placeholder {
+                Some(placeholder) => replace_prefix(&content, placeholder, image_prefix)
+                    .with_context(|| format!("failed to relocate '{path}'"))?,
+                None => content,
+            };
+            header.set_size(content.len() as u64);
+            builder
+                .append_data(
+                    &mut header,
+                    path.trim_start_matches('/'),
+                    content.as_slice(),
+                )
+                .into_diagnostic()
+                .with_context(|| format!("failed to add '{path}' to the layer"))?;
+        }
+        PrefixPathKind::Generated {
+            content,
+            executable,
+        } => append_file(
+            builder,
+            &path,
+            &replace_all(
+                &content,
+                SCRIPT_PLACEHOLDER.as_bytes(),
+                image_prefix.as_bytes(),
+            ),
+            if executable { 0o755 } else { 0o644 },
+        )?,
+    }
+    Ok(())
+}
+
+/// The manifest and configuration of an image that is used as the base of the
+/// exported image.
+struct BaseImage {
+    /// Keeps the extracted image layout alive.
+    _extracted: Option<tempfile::TempDir>,
+
+    /// The root of the image layout.
+    root: PathBuf,
+
+    /// The configuration of the image.
+    config: Value,
+
+    /// The descriptors of the layers of the image.
+    layers: Vec<Value>,
+}
+
+impl BaseImage {
+    /// Reads the image for the given architecture from an OCI image layout
+    /// archive or directory.
+    fn from_path(path: &Path, architecture: &str) -> miette::Result<Self> {
+        let (extracted, root) = if path.is_dir() {
+            (None, path.to_path_buf())
+        } else {
+            let extracted = tempfile::tempdir().into_diagnostic()?;
+            tar::Archive::new(
+                fs_err::File::open(path)
+                    .into_diagnostic()
+                    .context("failed to open the base image")?,
+            )
+            .unpack(extracted.path())
+            .into_diagnostic()
+            .with_context(|| format!("failed to extract the base image '{}'", path.display()))?;
+            let root = extracted.path().to_path_buf();
+            (Some(extracted), root)
+        };
+
+        let index = read_json(&root.join("index.json"))
+            .with_context(|| format!("'{}' is not an OCI image layout", path.display()))?;
+        let manifest = find_manifest(&root, &index, architecture)?;
+        let config = read_json(&blob_path(
+            &root.join("blobs").join("sha256"),
+            &manifest["config"]["digest"],
+        )?)?;
+        let layers = manifest["layers"].as_array().cloned().unwrap_or_default();
+
+        Ok(Self {
+            _extracted: extracted,
+            root,
+            config,
+            layers,
+        })
+    }
+
+    /// Returns the path of a blob of the image.
+    fn blob_path(&self, digest: &Value) -> miette::Result<PathBuf> {
+        blob_path(&self.root.join("blobs").join("sha256"), digest)
+    }
+
+    /// Returns true if the file system of the image contains any of the given
+    /// paths, relative to the root. Layers with an unsupported compression are
+    /// skipped.
+    fn contains_any(&self, paths: &[&str]) -> miette::Result<bool> {
+        let mut found = BTreeSet::new();
+        for layer in &self.layers {
+            let path = self.blob_path(&layer["digest"])?;
+            let file = fs_err::File::open(&path).into_diagnostic()?;
+            let reader: Box<dyn Read> = match layer["mediaType"].as_str().unwrap_or_default() {
+                media_type if media_type.ends_with("gzip") => Box::new(GzDecoder::new(file)),
+                media_type if media_type.ends_with("tar") => Box::new(file),
+                media_type => {
+                    tracing::debug!(
+                        "skipping a layer of the base image with media type '{media_type}'"
+                    );
+                    continue;
+                }
+            };
+            let mut archive = tar::Archive::new(reader);
+            for entry in archive.entries().into_diagnostic()? {
+                let entry = entry.into_diagnostic()?;
+                let entry_path = entry.path().into_diagnostic()?;
+                let entry_path = entry_path.to_string_lossy();
+                let entry_path = entry_path.trim_start_matches("./").trim_start_matches('/');
+
+                // A whiteout file removes the path from the lower layers.
+                match entry_path.rsplit_once(".wh.") {
+                    Some((dir, name)) => {
+                        found.remove(&format!("{dir}{name}"));
+                    }
+                    None => {
+                        found.insert(entry_path.to_string());
+                    }
+                }
+            }
+        }
+        Ok(paths.iter().any(|path| found.contains(*path)))
+    }
+}
+
+/// Finds the manifest of the image for the given architecture in an image
+/// index, following nested indices.
+fn find_manifest(root: &Path, index: &Value, architecture: &str) -> miette::Result<Value> {
+    let blobs = root.join("blobs").join("sha256");
+    for descriptor in index["manifests"].as_array().into_iter().flatten() {
+        match descriptor["mediaType"].as_str() {
+            Some(INDEX_MEDIA_TYPE | DOCKER_MANIFEST_LIST_MEDIA_TYPE) => {
+                let nested = read_json(&blob_path(&blobs, &descriptor["digest"])?)?;
+                if let Ok(manifest) = find_manifest(root, &nested, architecture) {
+                    return Ok(manifest);
+                }
+            }
+            _ => {
+                let matches = descriptor
+                    .get("platform")
+                    .map_or(true, |platform| platform["architecture"] == architecture);
+                if matches {
+                    return read_json(&blob_path(&blobs, &descriptor["digest"])?);
+                }
+            }
+        }
+    }
+    miette::bail!("the base image does not contain an image for the '{architecture}' architecture")
+}
+
+/// Reads a json file.
+fn read_json(path: &Path) -> miette::Result<Value> {
+    let content = fs_err::read(path).into_diagnostic()?;
+    serde_json::from_slice(&content)
+        .into_diagnostic()
+        .with_context(|| format!("failed to parse '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activation_script_excludes_env_files() {
+        let root = tempfile::tempdir().unwrap();
+        fs_err::write(root.path().join(".env"), "SECRET=hunter2\n").unwrap();
+        let project = Project::from_str(
+            &root.path().join("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+        [activation]
+        env = { GREETING = "hello" }
+        env-file = [".env"]
+        "#,
+        )
+        .unwrap();
+
+        let script = activation_script(
+            &project.default_environment(),
+            Platform::Linux64,
+            "/opt/pixi/envs/default",
+        );
+        assert!(script.contains("export GREETING='hello'\n"));
+        assert!(script.contains("export CONDA_PREFIX='/opt/pixi/envs/default'\n"));
+        assert!(script.contains("\"/opt/pixi/envs/default/etc/conda/activate.d/\"*.sh"));
+        assert!(!script.contains("SECRET"));
+        assert!(!script.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_append_prefix_path() {
+        let package_dir = tempfile::tempdir().unwrap();
+        let placeholder = "/opt/anaconda1anaconda2anaconda3";
+        fs_err::create_dir_all(package_dir.path().join("info")).unwrap();
+        fs_err::create_dir_all(package_dir.path().join("bin")).unwrap();
+        fs_err::create_dir_all(package_dir.path().join("lib")).unwrap();
+        fs_err::write(
+            package_dir.path().join("bin/tool"),
+            format!("#!{placeholder}/bin/python\n"),
+        )
+        .unwrap();
+        fs_err::write(
+            package_dir.path().join("lib/libfoo.so"),
+            format!("\0{placeholder}/lib\0"),
+        )
+        .unwrap();
+        fs_err::write(
+            package_dir.path().join("lib/data"),
+            format!("{placeholder} is not replaced\n"),
+        )
+        .unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("libfoo.so", package_dir.path().join("lib/libfoo.so.1"))
+            .unwrap();
+        let mut paths = vec![
+            json!({ "_path": "bin/tool", "path_type": "hardlink", "prefix_placeholder": placeholder, "file_mode": "text" }),
+            json!({ "_path": "lib/data", "path_type": "hardlink" }),
+            json!({ "_path": "lib/libfoo.so", "path_type": "hardlink", "prefix_placeholder": placeholder, "file_mode": "binary" }),
+        ];
+        if cfg!(unix) {
+            paths.push(json!({ "_path": "lib/libfoo.so.1", "path_type": "softlink" }));
+        }
+        fs_err::write(
+            package_dir.path().join("info/paths.json"),
+            serde_json::to_vec(&json!({ "paths": paths, "paths_version": 1 })).unwrap(),
+        )
+        .unwrap();
+
+        let record = RepoDataRecord {
+            package_record: PackageRecord::new(
+                "foo".parse().unwrap(),
+                "1.0"
+                    .parse::<rattler_conda_types::VersionWithSource>()
+                    .unwrap(),
+                "h123_0".to_string(),
+            ),
+            file_name: String::from("foo-1.0-h123_0.conda"),
+            url: "https://example.com/foo-1.0-h123_0.conda".parse().unwrap(),
+            channel: String::from("https://example.com"),
+        };
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut directories = BTreeSet::new();
+        for prefix_path in conda_package_paths(package_dir.path(), &record, None).unwrap() {
+            append_prefix_path(
+                &mut builder,
+                &mut directories,
+                prefix_path,
+                "/opt/pixi/envs/default",
+            )
+            .unwrap();
+        }
+
+        let layer = builder.into_inner().unwrap();
+        let mut archive = tar::Archive::new(layer.as_slice());
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let link = entry
+                .link_name()
+                .unwrap()
+                .map(|link| link.to_string_lossy().into_owned());
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            entries.push((path, link, content));
+        }
+        let content = |path: &str| {
+            entries
+                .iter()
+                .find(|(entry, _, _)| entry == path)
+                .map(|(_, _, content)| content.clone())
+                .unwrap()
+        };
+
+        // Only the files with a placeholder in `paths.json` are relocated.
+        assert!(entries
+            .iter()
+            .any(|(path, _, _)| path == "opt/pixi/envs/default/bin/"));
+        assert_eq!(
+            content("opt/pixi/envs/default/bin/tool"),
+            b"#!/opt/pixi/envs/default/bin/python\n"
+        );
+        assert_eq!(
+            content("opt/pixi/envs/default/lib/data"),
+            format!("{placeholder} is not replaced\n").as_bytes()
+        );
+        assert_eq!(
+            content("opt/pixi/envs/default/lib/libfoo.so"),
+            b"\0/opt/pixi/envs/default/lib\0\0\0\0\0\0\0\0\0\0\0"
+        );
+        if cfg!(unix) {
+            assert!(entries.iter().any(|(path, link, _)| path
+                == "opt/pixi/envs/default/lib/libfoo.so.1"
+                && link.as_deref() == Some("libfoo.so")));
+        }
+    }
+
+    #[test]
+    fn test_parent_directories() {
+        assert_eq!(
+            parent_directories("/opt/pixi/envs/default/"),
+            vec![
+                "opt/",
+                "opt/pixi/",
+                "opt/pixi/envs/",
+                "opt/pixi/envs/default/"
+            ]
+        );
+        assert_eq!(
+            parent_directories("/opt/pixi/entrypoint.sh"),
+            vec!["opt/", "opt/pixi/"]
+        );
+    }
+
+    /// Writes an OCI image layout to `root` with a layer for each list of
+    /// files.
+    fn write_base_image(root: &Path, layers: &[&[&str]]) {
+        let blobs = root.join("blobs").join("sha256");
+        fs_err::create_dir_all(&blobs).unwrap();
+        let layers = layers
+            .iter()
+            .map(|files| {
+                write_layer(&blobs, |builder| {
+                    for file in *files {
+                        append_file(builder, file, b"", 0o755)?;
+                    }
+                    Ok(())
+                })
+                .unwrap()
+            })
+            .collect_vec();
+        let config = json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "config": { "Env": ["PATH=/usr/bin:/bin"] },
+            "rootfs": {
+                "type": "layers",
+                "diff_ids": layers.iter().map(|layer| layer.diff_id.clone()).collect_vec(),
+            },
+        });
+        let config = write_blob(
+            &blobs,
+            CONFIG_MEDIA_TYPE,
+            &serde_json::to_vec(&config).unwrap(),
+        )
+        .unwrap();
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": MANIFEST_MEDIA_TYPE,
+            "config": config,
+            "layers": layers.iter().map(|layer| layer.descriptor.clone()).collect_vec(),
+        });
+        let manifest = write_blob(
+            &blobs,
+            MANIFEST_MEDIA_TYPE,
+            &serde_json::to_vec(&manifest).unwrap(),
+        )
+        .unwrap();
+        let index = json!({ "schemaVersion": 2, "manifests": [manifest] });
+        fs_err::write(root.join("index.json"), serde_json::to_vec(&index).unwrap()).unwrap();
+    }
+
+    /// Reads the blob of a descriptor and checks its digest and size.
+    fn read_blob(root: &Path, descriptor: &Value) -> Vec<u8> {
+        let content = fs_err::read(
+            blob_path(&root.join("blobs").join("sha256"), &descriptor["digest"]).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            descriptor["digest"],
+            format!("sha256:{:x}", compute_bytes_digest::<Sha256>(&content))
+        );
+        assert_eq!(descriptor["size"], content.len());
+        content
+    }
+
+    #[test]
+    fn test_base_image_contains_bash() {
+        let with_bash = tempfile::tempdir().unwrap();
+        write_base_image(with_bash.path(), &[&["usr/", "usr/bin/", "usr/bin/bash"]]);
+        let base_image = BaseImage::from_path(with_bash.path(), "amd64").unwrap();
+        assert!(base_image.contains_any(&BASH_PATHS).unwrap());
+
+        let without_bash = tempfile::tempdir().unwrap();
+        write_base_image(without_bash.path(), &[&["bin/", "bin/sh"]]);
+        let base_image = BaseImage::from_path(without_bash.path(), "amd64").unwrap();
+        assert!(!base_image.contains_any(&BASH_PATHS).unwrap());
+
+        let removed_bash = tempfile::tempdir().unwrap();
+        write_base_image(
+            removed_bash.path(),
+            &[&["bin/", "bin/bash"], &["bin/", "bin/.wh.bash"]],
+        );
+        let base_image = BaseImage::from_path(removed_bash.path(), "amd64").unwrap();
+        assert!(!base_image.contains_any(&BASH_PATHS).unwrap());
+    }
+
+    #[test]
+    fn test_write_image() {
+        let base = tempfile::tempdir().unwrap();
+        write_base_image(base.path(), &[&["bin/", "bin/bash"]]);
+        let base_image = BaseImage::from_path(base.path(), "amd64").unwrap();
+
+        let blobs = tempfile::tempdir().unwrap();
+        let layer = write_layer(blobs.path(), |builder| {
+            append_file(
+                builder,
+                "/opt/pixi/entrypoint.sh",
+                ENTRYPOINT_SCRIPT.as_bytes(),
+                0o755,
+            )
+        })
+        .unwrap();
+        let output = blobs.path().join("image.tar");
+        write_image(
+            &output,
+            blobs.path(),
+            &base_image,
+            base_image.config.clone(),
+            &[(String::from("base"), layer)],
+            "latest",
+        )
+        .unwrap();
+
+        let image = tempfile::tempdir().unwrap();
+        tar::Archive::new(fs_err::File::open(&output).unwrap())
+            .unpack(image.path())
+            .unwrap();
+        assert_eq!(
+            read_json(&image.path().join("oci-layout")).unwrap(),
+            json!({ "imageLayoutVersion": "1.0.0" })
+        );
+
+        // Every descriptor matches the digest and size of its blob.
+        let index = read_json(&image.path().join("index.json")).unwrap();
+        let manifests = index["manifests"].as_array().unwrap();
+        assert_eq!(manifests.len(), 1);
+        assert_eq!(
+            manifests[0]["annotations"]["org.opencontainers.image.ref.name"],
+            "latest"
+        );
+        let manifest: Value =
+            serde_json::from_slice(&read_blob(image.path(), &manifests[0])).unwrap();
+        assert_eq!(manifest["mediaType"], MANIFEST_MEDIA_TYPE);
+        let config: Value =
+            serde_json::from_slice(&read_blob(image.path(), &manifest["config"])).unwrap();
+
+        // The layers of the base image come first and every layer has a diff id
+        // that matches its uncompressed content.
+        let layers = manifest["layers"].as_array().unwrap();
+        let diff_ids = config["rootfs"]["diff_ids"].as_array().unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0], base_image.layers[0]);
+        assert_eq!(diff_ids.len(), layers.len());
+        for (layer, diff_id) in layers.iter().zip(diff_ids) {
+            let mut uncompressed = Vec::new();
+            GzDecoder::new(read_blob(image.path(), layer).as_slice())
+                .read_to_end(&mut uncompressed)
+                .unwrap();
+            assert_eq!(
+                *diff_id,
+                format!("sha256:{:x}", compute_bytes_digest::<Sha256>(&uncompressed))
+            );
+        }
+        assert_eq!(
+            config["history"][0]["created_by"],
+            "pixi project export oci (base)"
+        );
+    }
+
+    #[test]
+    fn test_oci_architecture() {
+        assert_eq!(oci_architecture(Platform::Linux64), Some(("amd64", None)));
+        assert_eq!(
+            oci_architecture(Platform::LinuxAarch64),
+            Some(("arm64", Some("v8")))
+        );
+        assert_eq!(oci_architecture(Platform::OsxArm64), None);
+    }
+}
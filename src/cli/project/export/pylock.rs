@@ -0,0 +1,396 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use pypi_modifiers::{pypi_marker_env::determine_marker_environment, pypi_tags::is_python_record};
+use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_lock::{Environment, Package, PackageHashes, PypiPackageData, UrlOrPath};
+use serde::Serialize;
+use url::Url;
+
+use super::requirements_txt::strip_direct_scheme;
+use crate::cli::cli_config::PrefixUpdateConfig;
+use crate::lock_file::UpdateLockFileOptions;
+use crate::Project;
+
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// Explicit path to export the `pylock.toml` file to.
+    /// Defaults to printing to stdout.
+    pub output_path: Option<PathBuf>,
+
+    /// The platform to render the lock file for.
+    /// Defaults to the current platform.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// The environment to render the lock file for.
+    /// Defaults to the default environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// A lock file in the format described by PEP 751.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLock {
+    lock_version: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    environments: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requires_python: Option<String>,
+    created_by: &'static str,
+    packages: Vec<PyLockPackage>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLockPackage {
+    name: String,
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requires_python: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<PyLockDependency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vcs: Option<PyLockVcs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    directory: Option<PyLockDirectory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<PyLockArchive>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sdist: Option<PyLockFile>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    wheels: Vec<PyLockFile>,
+}
+
+#[derive(Debug, Serialize)]
+struct PyLockDependency {
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLockVcs {
+    r#type: &'static str,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subdirectory: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PyLockDirectory {
+    path: String,
+    editable: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PyLockArchive {
+    url: String,
+    hashes: PyLockHashes,
+}
+
+#[derive(Debug, Serialize)]
+struct PyLockFile {
+    name: String,
+    url: String,
+    hashes: PyLockHashes,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct PyLockHashes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    md5: Option<String>,
+}
+
+impl From<&Option<PackageHashes>> for PyLockHashes {
+    fn from(hashes: &Option<PackageHashes>) -> Self {
+        match hashes {
+            Some(PackageHashes::Md5(md5)) => Self {
+                md5: Some(format!("{md5:x}")),
+                ..Self::default()
+            },
+            Some(PackageHashes::Sha256(sha256)) => Self {
+                sha256: Some(format!("{sha256:x}")),
+                ..Self::default()
+            },
+            Some(PackageHashes::Md5Sha256(md5, sha256)) => Self {
+                sha256: Some(format!("{sha256:x}")),
+                md5: Some(format!("{md5:x}")),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Returns the last path segment of a url, which is the file name of an
+/// archive.
+fn file_name(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|name| {
+            percent_encoding::percent_decode_str(name)
+                .decode_utf8_lossy()
+                .into_owned()
+        })
+        .unwrap_or_default()
+}
+
+/// Splits a locked git url of the form `git+<url>@<commit>#subdirectory=<dir>`
+/// into its parts.
+fn parse_git_url(url: &Url) -> PyLockVcs {
+    let subdirectory = url
+        .fragment()
+        .and_then(|fragment| fragment.strip_prefix("subdirectory="))
+        .map(ToOwned::to_owned);
+
+    let mut repository = url.clone();
+    repository.set_fragment(None);
+    repository.set_query(None);
+    let repository = repository.as_str();
+    let repository = repository.strip_prefix("git+").unwrap_or(repository);
+
+    // The commit is appended to the path, an `@` before the path belongs to the
+    // credentials.
+    let path_start = repository.find("://").map_or(0, |index| index + 3);
+    let path_start = repository[path_start..]
+        .find('/')
+        .map_or(repository.len(), |index| path_start + index);
+    let (url, commit_id) = match repository[path_start..].rsplit_once('@') {
+        Some((path, commit)) => (
+            format!("{}{path}", &repository[..path_start]),
+            Some(commit.to_string()),
+        ),
+        None => (repository.to_string(), None),
+    };
+
+    PyLockVcs {
+        r#type: "git",
+        url,
+        commit_id,
+        subdirectory,
+    }
+}
+
+/// Converts a locked PyPI package into a package entry of a `pylock.toml`.
+fn build_package(
+    package: &PypiPackageData,
+    index: Option<&Url>,
+    locked_names: &HashSet<String>,
+) -> PyLockPackage {
+    let mut entry = PyLockPackage {
+        name: package.name.to_string(),
+        version: package.version.to_string(),
+        requires_python: package.requires_python.as_ref().map(ToString::to_string),
+        dependencies: package
+            .requires_dist
+            .iter()
+            .map(|requirement| requirement.name.to_string())
+            .filter(|name| locked_names.contains(name))
+            .unique()
+            .map(|name| PyLockDependency { name })
+            .collect(),
+        index: None,
+        vcs: None,
+        directory: None,
+        archive: None,
+        sdist: None,
+        wheels: Vec::new(),
+    };
+
+    match &package.url_or_path {
+        UrlOrPath::Url(url) if url.scheme().starts_with("git+") => {
+            entry.vcs = Some(parse_git_url(url));
+        }
+        UrlOrPath::Url(url) if url.scheme().starts_with("direct") => {
+            entry.archive = Some(PyLockArchive {
+                url: strip_direct_scheme(url).to_string(),
+                hashes: (&package.hash).into(),
+            });
+        }
+        UrlOrPath::Url(url) => {
+            let file = PyLockFile {
+                name: file_name(url),
+                url: url.to_string(),
+                hashes: (&package.hash).into(),
+            };
+            entry.index = index.map(ToString::to_string);
+            if file.name.ends_with(".whl") {
+                entry.wheels.push(file);
+            } else {
+                entry.sdist = Some(file);
+            }
+        }
+        UrlOrPath::Path(path) => {
+            entry.directory = Some(PyLockDirectory {
+                path: path.to_string_lossy().into_owned(),
+                editable: package.editable,
+            });
+        }
+    }
+
+    entry
+}
+
+/// Renders the PyPI packages of an environment for a platform as a
+/// `pylock.toml` file.
+fn render_pylock(environment: &Environment, platform: Platform) -> miette::Result<String> {
+    let packages = environment
+        .packages(platform)
+        .ok_or_else(|| {
+            miette::miette!("the lock-file does not contain packages for platform '{platform}'")
+        })?
+        .collect_vec();
+
+    // The python interpreter determines the markers of the environment.
+    let python_record = packages
+        .iter()
+        .filter_map(Package::as_conda)
+        .map(|package| RepoDataRecord::try_from(package.clone()))
+        .filter_ok(is_python_record)
+        .next()
+        .transpose()
+        .into_diagnostic()?;
+    let marker_environment = python_record
+        .map(|record| determine_marker_environment(platform, &record.package_record))
+        .transpose()?;
+
+    let pypi_packages = packages
+        .iter()
+        .filter_map(Package::as_pypi)
+        .map(|package| &package.data().package)
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect_vec();
+    let locked_names = pypi_packages
+        .iter()
+        .map(|package| package.name.to_string())
+        .collect::<HashSet<_>>();
+    let index = environment
+        .pypi_indexes()
+        .and_then(|indexes| indexes.indexes.first());
+
+    let pylock = PyLock {
+        lock_version: "1.0",
+        environments: marker_environment
+            .as_ref()
+            .map(|markers| {
+                vec![format!(
+                    "sys_platform == '{}' and platform_machine == '{}'",
+                    markers.sys_platform(),
+                    markers.platform_machine()
+                )]
+            })
+            .unwrap_or_default(),
+        requires_python: marker_environment
+            .as_ref()
+            .map(|markers| format!("=={}.*", markers.python_version())),
+        created_by: "pixi",
+        packages: pypi_packages
+            .into_iter()
+            .map(|package| build_package(package, index, &locked_names))
+            .collect(),
+    };
+
+    toml_edit::ser::to_string_pretty(&pylock).into_diagnostic()
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| miette::miette!("unknown environment {}", environment.name()))?;
+
+    let pylock = render_pylock(&locked_environment, platform)?;
+
+    if let Some(output_path) = args.output_path {
+        fs_err::write(&output_path, pylock)
+            .into_diagnostic()
+            .with_context(|| "failed to write pylock.toml")?;
+    } else {
+        print!("{pylock}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_lock::LockFile;
+
+    use super::*;
+
+    #[test]
+    fn test_render_pylock() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mock-projects/test-project-export/pixi.lock");
+        let lock_file = LockFile::from_path(&path).unwrap();
+        let environment = lock_file.environment("default").unwrap();
+
+        let pylock = render_pylock(&environment, Platform::Linux64).unwrap();
+        let document = pylock.parse::<toml_edit::DocumentMut>().unwrap();
+        assert_eq!(document["lock-version"].as_str(), Some("1.0"));
+        assert_eq!(document["created-by"].as_str(), Some("pixi"));
+        assert_eq!(document["requires-python"].as_str(), Some("==3.12.*"));
+
+        let packages = document["packages"].as_array_of_tables().unwrap();
+        let names = packages
+            .iter()
+            .map(|package| package["name"].as_str().unwrap())
+            .collect_vec();
+        assert_eq!(names, ["markdown-it-py", "mdurl", "pygments", "rich"]);
+
+        let rich = packages.get(3).unwrap();
+        assert_eq!(rich["version"].as_str(), Some("13.8.0"));
+        assert_eq!(rich["index"].as_str(), Some("https://pypi.org/simple"));
+        let wheel = &rich["wheels"][0];
+        assert_eq!(wheel["name"].as_str(), Some("rich-13.8.0-py3-none-any.whl"));
+        assert!(wheel["hashes"]["sha256"].is_str());
+    }
+
+    #[test]
+    fn test_parse_git_url() {
+        let url = "git+https://github.com/facebookresearch/detectron2.git@bcfd464d0c810f0442d91a349c0f6df945467143#subdirectory=python"
+            .parse()
+            .unwrap();
+        let vcs = parse_git_url(&url);
+        assert_eq!(
+            vcs.url,
+            "https://github.com/facebookresearch/detectron2.git"
+        );
+        assert_eq!(
+            vcs.commit_id.as_deref(),
+            Some("bcfd464d0c810f0442d91a349c0f6df945467143")
+        );
+        assert_eq!(vcs.subdirectory.as_deref(), Some("python"));
+
+        let url = "git+ssh://git@github.com/pallets/click.git"
+            .parse()
+            .unwrap();
+        let vcs = parse_git_url(&url);
+        assert_eq!(vcs.url, "ssh://git@github.com/pallets/click.git");
+        assert_eq!(vcs.commit_id, None);
+    }
+}
@@ -0,0 +1,395 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_conda_types::Platform;
+use rattler_lock::{
+    Environment, FindLinksUrlOrPath, Package, PackageHashes, PypiPackageData, UrlOrPath,
+};
+use url::Url;
+
+use crate::cli::cli_config::PrefixUpdateConfig;
+use crate::lock_file::UpdateLockFileOptions;
+use crate::Project;
+
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// Explicit path to export the requirements file to.
+    /// Defaults to printing to stdout.
+    pub output_path: Option<PathBuf>,
+
+    /// The platform to render the requirements file for.
+    /// Defaults to the current platform.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// The environment to render the requirements file for.
+    /// Defaults to the default environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// Do not add the hashes of the packages. Required to export git, path and
+    /// editable packages, which cannot be hashed.
+    #[arg(long)]
+    pub no_hashes: bool,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// Strips the `direct+` prefix that the lock-file adds to direct urls.
+pub(super) fn strip_direct_scheme(url: &Url) -> Url {
+    url.as_str()
+        .strip_prefix("direct+")
+        .and_then(|url| Url::parse(url).ok())
+        .unwrap_or_else(|| url.clone())
+}
+
+/// Returns the `file://` url of a locked path, relative paths are relative to
+/// the project root.
+fn file_url(path: &Path, project_root: &Path) -> miette::Result<Url> {
+    Url::from_file_path(project_root.join(path))
+        .map_err(|_| miette::miette!("failed to convert '{}' to a url", path.display()))
+}
+
+/// Formats a single locked PyPI package as a line in a requirements file.
+///
+/// When `hashes` is set, pip installs the file in hash-checking mode which
+/// requires every package to be hashed. Git, path and editable packages
+/// cannot be hashed and result in an error.
+fn format_requirement(
+    package: &PypiPackageData,
+    project_root: &Path,
+    hashes: bool,
+) -> miette::Result<String> {
+    let unhashable = match &package.url_or_path {
+        UrlOrPath::Url(url) if url.scheme().starts_with("git+") => Some("a git"),
+        UrlOrPath::Path(_) if package.editable => Some("an editable"),
+        UrlOrPath::Path(_) => Some("a path"),
+        UrlOrPath::Url(_) => None,
+    };
+    if let (true, Some(kind)) = (hashes, unhashable) {
+        miette::bail!(
+            help = "export the requirements with `--no-hashes`",
+            "'{}' is {kind} dependency which cannot be hashed, but pip requires every package to be hashed once one of them is",
+            package.name
+        );
+    }
+
+    let mut requirement = match &package.url_or_path {
+        UrlOrPath::Url(url) if url.scheme().starts_with("git+") => {
+            format!("{} @ {url}", package.name)
+        }
+        UrlOrPath::Url(url) if url.scheme().starts_with("direct") => {
+            format!("{} @ {}", package.name, strip_direct_scheme(url))
+        }
+        UrlOrPath::Url(_) => format!("{}=={}", package.name, package.version),
+        UrlOrPath::Path(path) if package.editable => {
+            format!("-e {}", file_url(path, project_root)?)
+        }
+        UrlOrPath::Path(path) => {
+            format!("{} @ {}", package.name, file_url(path, project_root)?)
+        }
+    };
+
+    if hashes {
+        // pip only accepts strong hashes, so md5 hashes are not included.
+        let Some(PackageHashes::Sha256(sha256) | PackageHashes::Md5Sha256(_, sha256)) =
+            &package.hash
+        else {
+            miette::bail!(
+                help = "export the requirements with `--no-hashes`",
+                "the lock-file does not contain a sha256 hash for '{}', but pip requires every package to be hashed once one of them is",
+                package.name
+            );
+        };
+        write!(requirement, " \\\n    --hash=sha256:{sha256:x}").unwrap();
+    }
+
+    Ok(requirement)
+}
+
+/// Renders the PyPI packages of an environment for a platform as a
+/// requirements file, with hashes if `hashes` is set.
+fn render_requirements_txt(
+    environment: &Environment,
+    platform: Platform,
+    project_root: &Path,
+    hashes: bool,
+) -> miette::Result<String> {
+    let packages = environment.packages(platform).ok_or_else(|| {
+        miette::miette!("the lock-file does not contain packages for platform '{platform}'")
+    })?;
+
+    let mut requirements = String::new();
+    requirements.push_str("# Generated by `pixi project export`\n");
+
+    if let Some(indexes) = environment.pypi_indexes() {
+        let mut index_urls = indexes.indexes.iter();
+        if let Some(index_url) = index_urls.next() {
+            writeln!(requirements, "--index-url {index_url}").unwrap();
+        }
+        for extra_index_url in index_urls {
+            writeln!(requirements, "--extra-index-url {extra_index_url}").unwrap();
+        }
+        for find_link in &indexes.find_links {
+            match find_link {
+                FindLinksUrlOrPath::Url(url) => writeln!(requirements, "--find-links {url}"),
+                FindLinksUrlOrPath::Path(path) => {
+                    writeln!(requirements, "--find-links {}", path.to_string_lossy())
+                }
+            }
+            .unwrap();
+        }
+    }
+
+    let pypi_packages = packages
+        .filter_map(|package| match package {
+            Package::Pypi(package) => Some(format_requirement(
+                &package.data().package,
+                project_root,
+                hashes,
+            )),
+            Package::Conda(_) => None,
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
+    for requirement in pypi_packages.into_iter().sorted() {
+        writeln!(requirements, "{requirement}").unwrap();
+    }
+
+    Ok(requirements)
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| miette::miette!("unknown environment {}", environment.name()))?;
+
+    let requirements = render_requirements_txt(
+        &locked_environment,
+        platform,
+        project.root(),
+        !args.no_hashes,
+    )?;
+
+    if let Some(output_path) = args.output_path {
+        fs_err::write(&output_path, requirements)
+            .into_diagnostic()
+            .with_context(|| "failed to write requirements file")?;
+    } else {
+        print!("{requirements}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_lock::{LockFile, PypiPackageEnvironmentData};
+
+    use super::*;
+
+    /// Returns the requirements of a requirements file the way pip reads
+    /// them: comments and options are skipped and continuation lines are
+    /// joined.
+    fn parse_requirements(requirements: &str) -> Vec<String> {
+        requirements
+            .replace("\\\n", "")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("--"))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Asserts that either all or none of the requirements are hashed, as pip
+    /// refuses to install anything else. Returns whether they are hashed.
+    fn assert_hash_checking_consistent(requirements: &str) -> bool {
+        let requirements = parse_requirements(requirements);
+        let hashed = requirements
+            .iter()
+            .filter(|requirement| requirement.contains(" --hash=sha256:"))
+            .count();
+        assert!(
+            hashed == 0 || hashed == requirements.len(),
+            "only {hashed} of the requirements are hashed:\n{}",
+            requirements.join("\n")
+        );
+        hashed > 0
+    }
+
+    /// Returns a locked package with version `1.0.0`.
+    fn package(name: &str, url_or_path: UrlOrPath, hash: Option<PackageHashes>) -> PypiPackageData {
+        PypiPackageData {
+            name: name.parse().unwrap(),
+            version: "1.0.0".parse().unwrap(),
+            url_or_path,
+            hash,
+            requires_dist: vec![],
+            requires_python: None,
+            editable: false,
+        }
+    }
+
+    #[test]
+    fn test_render_requirements_txt() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mock-projects/test-project-export/pixi.lock");
+        let lock_file = LockFile::from_path(&path).unwrap();
+        let environment = lock_file.environment("default").unwrap();
+
+        let requirements =
+            render_requirements_txt(&environment, Platform::Linux64, Path::new("/"), true).unwrap();
+        let lines = requirements.lines().collect_vec();
+        assert_eq!(lines[0], "# Generated by `pixi project export`");
+        assert!(lines.contains(&"rich==13.8.0 \\"));
+        assert!(lines.contains(&"mdurl==0.1.2 \\"));
+
+        // Every registry package is pinned with a hash.
+        let pinned = lines.iter().filter(|line| line.contains("==")).count();
+        let hashes = lines
+            .iter()
+            .filter(|line| line.trim_start().starts_with("--hash=sha256:"))
+            .count();
+        assert_eq!(pinned, 4);
+        assert_eq!(pinned, hashes);
+        assert!(assert_hash_checking_consistent(&requirements));
+
+        // No conda packages end up in the requirements.
+        assert!(!lines.iter().any(|line| line.starts_with("python")));
+    }
+
+    #[test]
+    fn test_render_requirements_txt_git_and_hashed() {
+        let sha256 = rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(
+            "2e85306a063b9492dffc86278197a60cbece75bcb766022f3436f567cae11bdc",
+        )
+        .unwrap();
+        let mut builder = LockFile::builder();
+        builder.add_pypi_package(
+            "default",
+            Platform::Linux64,
+            package(
+                "rich",
+                UrlOrPath::Url(
+                    "https://files.pythonhosted.org/rich-1.0.0-py3-none-any.whl"
+                        .parse()
+                        .unwrap(),
+                ),
+                Some(PackageHashes::Sha256(sha256)),
+            ),
+            PypiPackageEnvironmentData::default(),
+        );
+        builder.add_pypi_package(
+            "default",
+            Platform::Linux64,
+            package(
+                "click",
+                UrlOrPath::Url(
+                    "git+https://github.com/pallets/click.git@0123abc"
+                        .parse()
+                        .unwrap(),
+                ),
+                None,
+            ),
+            PypiPackageEnvironmentData::default(),
+        );
+        let lock_file = builder.finish();
+        let environment = lock_file.environment("default").unwrap();
+
+        // A git package cannot be hashed, so hashes can't be exported.
+        let err = render_requirements_txt(&environment, Platform::Linux64, Path::new("/"), true)
+            .unwrap_err();
+        assert!(err.to_string().contains("'click' is a git dependency"));
+
+        // Without hashes pip installs both packages.
+        let requirements =
+            render_requirements_txt(&environment, Platform::Linux64, Path::new("/"), false)
+                .unwrap();
+        assert!(!assert_hash_checking_consistent(&requirements));
+        assert_eq!(
+            parse_requirements(&requirements),
+            vec![
+                "click @ git+https://github.com/pallets/click.git@0123abc",
+                "rich==1.0.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_requirement() {
+        let root = std::env::temp_dir();
+        let package = package(
+            "click",
+            UrlOrPath::Url(
+                "git+https://github.com/pallets/click.git@0123abc"
+                    .parse()
+                    .unwrap(),
+            ),
+            None,
+        );
+        assert_eq!(
+            format_requirement(&package, &root, false).unwrap(),
+            "click @ git+https://github.com/pallets/click.git@0123abc"
+        );
+        assert!(format_requirement(&package, &root, true).is_err());
+
+        // Paths are exported as file urls, relative to the project root.
+        let package = PypiPackageData {
+            url_or_path: UrlOrPath::Path(PathBuf::from("./src/click")),
+            ..package
+        };
+        assert_eq!(
+            format_requirement(&package, &root, false).unwrap(),
+            format!(
+                "click @ {}",
+                Url::from_file_path(root.join("./src/click")).unwrap()
+            )
+        );
+        assert!(format_requirement(&package, &root, true).is_err());
+
+        let package = PypiPackageData {
+            editable: true,
+            ..package
+        };
+        assert_eq!(
+            format_requirement(&package, &root, false).unwrap(),
+            format!(
+                "-e {}",
+                Url::from_file_path(root.join("./src/click")).unwrap()
+            )
+        );
+        assert!(format_requirement(&package, &root, true).is_err());
+
+        // Registry packages without a sha256 hash can't be exported with hashes.
+        let package = PypiPackageData {
+            url_or_path: UrlOrPath::Url(
+                "https://files.pythonhosted.org/click-1.0.0-py3-none-any.whl"
+                    .parse()
+                    .unwrap(),
+            ),
+            editable: false,
+            ..package
+        };
+        assert_eq!(
+            format_requirement(&package, &root, false).unwrap(),
+            "click==1.0.0"
+        );
+        assert!(format_requirement(&package, &root, true).is_err());
+    }
+}
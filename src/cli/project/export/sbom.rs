@@ -0,0 +1,650 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use chrono::{SecondsFormat, Utc};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use pixi_consts::consts;
+use rattler_conda_types::{Platform, RepoDataRecord};
+use rattler_digest::{compute_bytes_digest, Sha256};
+use rattler_lock::{Environment, Package, PackageHashes, PypiPackageData, UrlOrPath};
+use serde_json::{json, Value};
+
+use super::{conda_lock::split_conda_dependency, requirements_txt::strip_direct_scheme};
+use crate::cli::cli_config::PrefixUpdateConfig;
+use crate::lock_file::UpdateLockFileOptions;
+use crate::Project;
+
+/// Characters that have to be percent-encoded in the components of a package
+/// url.
+const PURL_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'.')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'~');
+
+/// The format of the software bill of materials.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    #[default]
+    Cyclonedx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+#[derive(Debug, Parser)]
+pub struct Args {
+    /// Explicit path to export the SBOM to.
+    /// Defaults to printing to stdout.
+    pub output_path: Option<PathBuf>,
+
+    /// The format of the SBOM.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: SbomFormat,
+
+    /// The platform to render the SBOM for.
+    /// Defaults to the current platform.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// The environment to render the SBOM for.
+    /// Defaults to the default environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+/// The license of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum License {
+    /// A valid SPDX license expression.
+    Expression(String),
+    /// A license that is not a valid SPDX license expression.
+    Name(String),
+}
+
+impl License {
+    /// Validates a license as an SPDX expression. Common deviations, like
+    /// lower-cased operators, are canonicalized.
+    fn parse(license: &str) -> Self {
+        if spdx::Expression::parse(license).is_ok() {
+            return Self::Expression(license.to_string());
+        }
+        match spdx::Expression::canonicalize(license) {
+            Ok(Some(canonical)) if spdx::Expression::parse(&canonical).is_ok() => {
+                Self::Expression(canonical)
+            }
+            _ => Self::Name(license.to_string()),
+        }
+    }
+}
+
+/// A locked package as it is described in the SBOM.
+#[derive(Debug)]
+struct Component {
+    manager: &'static str,
+    name: String,
+    version: String,
+    purl: String,
+    /// The PyPI package urls of a conda package that provides a PyPI package.
+    pypi_purls: Vec<String>,
+    url: Option<String>,
+    md5: Option<String>,
+    sha256: Option<String>,
+    license: Option<License>,
+    dependencies: Vec<String>,
+}
+
+impl Component {
+    /// A reference to the component that is unique within the document.
+    fn spdx_id(&self) -> String {
+        let id = format!("{}-{}-{}", self.manager, self.name, self.version).replace(
+            |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+            "-",
+        );
+        format!("SPDXRef-Package-{id}")
+    }
+}
+
+/// Returns the package url of a conda package.
+fn conda_purl(record: &RepoDataRecord) -> String {
+    let archive_type = if record.file_name.ends_with(".conda") {
+        "conda"
+    } else {
+        "tar.bz2"
+    };
+    let encode = |value: &str| utf8_percent_encode(value, PURL_ENCODE_SET).to_string();
+    format!(
+        "pkg:conda/{}@{}?build={}&channel={}&subdir={}&type={archive_type}",
+        encode(record.package_record.name.as_normalized()),
+        encode(&record.package_record.version.to_string()),
+        encode(&record.package_record.build),
+        encode(&record.channel),
+        encode(&record.package_record.subdir),
+    )
+}
+
+/// Returns the package url of a PyPI package.
+fn pypi_purl(package: &PypiPackageData) -> String {
+    format!(
+        "pkg:pypi/{}@{}",
+        package.name,
+        utf8_percent_encode(&package.version.to_string(), PURL_ENCODE_SET)
+    )
+}
+
+fn conda_component(record: RepoDataRecord) -> Component {
+    let purl = conda_purl(&record);
+    let package_record = record.package_record;
+    Component {
+        manager: "conda",
+        name: package_record.name.as_normalized().to_string(),
+        version: package_record.version.to_string(),
+        purl,
+        pypi_purls: package_record
+            .purls
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect(),
+        url: Some(record.url.to_string()),
+        md5: package_record.md5.map(|md5| format!("{md5:x}")),
+        sha256: package_record.sha256.map(|sha256| format!("{sha256:x}")),
+        license: package_record.license.as_deref().map(License::parse),
+        dependencies: package_record
+            .depends
+            .iter()
+            .map(|dependency| split_conda_dependency(dependency).0)
+            .collect(),
+    }
+}
+
+fn pypi_component(package: &PypiPackageData) -> Component {
+    let (md5, sha256) = match &package.hash {
+        Some(PackageHashes::Md5(md5)) => (Some(format!("{md5:x}")), None),
+        Some(PackageHashes::Sha256(sha256)) => (None, Some(format!("{sha256:x}"))),
+        Some(PackageHashes::Md5Sha256(md5, sha256)) => {
+            (Some(format!("{md5:x}")), Some(format!("{sha256:x}")))
+        }
+        None => (None, None),
+    };
+    Component {
+        manager: "pypi",
+        name: package.name.to_string(),
+        version: package.version.to_string(),
+        purl: pypi_purl(package),
+        pypi_purls: Vec::new(),
+        url: match &package.url_or_path {
+            UrlOrPath::Url(url) => Some(strip_direct_scheme(url).to_string()),
+            UrlOrPath::Path(_) => None,
+        },
+        md5,
+        sha256,
+        // The lock-file does not contain the license of PyPI packages.
+        license: None,
+        dependencies: package
+            .requires_dist
+            .iter()
+            .map(|requirement| requirement.name.to_string())
+            .collect(),
+    }
+}
+
+/// Collects the locked packages of an environment for a platform, sorted by
+/// package manager and name.
+fn collect_components(
+    environment: &Environment,
+    platform: Platform,
+) -> miette::Result<Vec<Component>> {
+    let packages = environment.packages(platform).ok_or_else(|| {
+        miette::miette!("the lock-file does not contain packages for platform '{platform}'")
+    })?;
+
+    let mut components = Vec::new();
+    for package in packages {
+        match package {
+            Package::Conda(package) => {
+                let record = RepoDataRecord::try_from(package)
+                    .into_diagnostic()
+                    .with_context(|| "failed to convert conda package to a RepoDataRecord")?;
+                components.push(conda_component(record));
+            }
+            Package::Pypi(package) => components.push(pypi_component(&package.data().package)),
+        }
+    }
+    components.sort_by(|a, b| a.manager.cmp(b.manager).then(a.name.cmp(&b.name)));
+
+    let invalid_licenses = components
+        .iter()
+        .filter_map(|component| match &component.license {
+            Some(License::Name(license)) => Some(format!("{} ({license})", component.name)),
+            _ => None,
+        })
+        .collect_vec();
+    if !invalid_licenses.is_empty() {
+        tracing::warn!(
+            "the licenses of the following packages are not valid SPDX expressions: {}",
+            invalid_licenses.join(", ")
+        );
+    }
+
+    Ok(components)
+}
+
+/// Resolves the dependencies of every component to the indices of the
+/// components within the same package manager that they refer to.
+fn resolve_dependencies(components: &[Component]) -> Vec<BTreeSet<usize>> {
+    let indices: HashMap<(&str, &str), usize> = components
+        .iter()
+        .enumerate()
+        .map(|(index, component)| ((component.manager, component.name.as_str()), index))
+        .collect();
+    components
+        .iter()
+        .map(|component| {
+            component
+                .dependencies
+                .iter()
+                .filter_map(|name| indices.get(&(component.manager, name.as_str())).copied())
+                .collect()
+        })
+        .collect()
+}
+
+/// Describes the project and environment the SBOM is generated for.
+struct SbomSubject<'a> {
+    name: &'a str,
+    version: Option<String>,
+    environment: &'a str,
+    platform: Platform,
+}
+
+fn render_cyclonedx(subject: &SbomSubject, components: &[Component], timestamp: &str) -> Value {
+    let bom_components = components
+        .iter()
+        .map(|component| {
+            let mut bom_component = json!({
+                "type": "library",
+                "bom-ref": component.purl,
+                "name": component.name,
+                "version": component.version,
+                "purl": component.purl,
+            });
+
+            let hashes = [("MD5", &component.md5), ("SHA-256", &component.sha256)]
+                .into_iter()
+                .filter_map(|(alg, content)| {
+                    content
+                        .as_ref()
+                        .map(|content| json!({ "alg": alg, "content": content }))
+                })
+                .collect_vec();
+            if !hashes.is_empty() {
+                bom_component["hashes"] = json!(hashes);
+            }
+
+            match &component.license {
+                Some(License::Expression(expression)) => {
+                    bom_component["licenses"] = json!([{ "expression": expression }]);
+                }
+                Some(License::Name(name)) => {
+                    bom_component["licenses"] = json!([{ "license": { "name": name } }]);
+                }
+                None => {}
+            }
+
+            if let Some(url) = &component.url {
+                bom_component["externalReferences"] =
+                    json!([{ "type": "distribution", "url": url }]);
+            }
+
+            let properties = component
+                .pypi_purls
+                .iter()
+                .map(|purl| json!({ "name": "pixi:pypi-purl", "value": purl }))
+                .collect_vec();
+            if !properties.is_empty() {
+                bom_component["properties"] = json!(properties);
+            }
+
+            bom_component
+        })
+        .collect_vec();
+
+    let root_ref = format!("{}@{}", subject.name, subject.environment);
+    let mut dependencies = vec![json!({
+        "ref": root_ref,
+        "dependsOn": components.iter().map(|component| &component.purl).collect_vec(),
+    })];
+    dependencies.extend(components.iter().zip(resolve_dependencies(components)).map(
+        |(component, depends_on)| {
+            let depends_on = depends_on
+                .into_iter()
+                .map(|index| &components[index].purl)
+                .collect_vec();
+            json!({ "ref": component.purl, "dependsOn": depends_on })
+        },
+    ));
+
+    let mut root_component = json!({
+        "type": "application",
+        "bom-ref": root_ref,
+        "name": subject.name,
+        "properties": [
+            { "name": "pixi:environment", "value": subject.environment },
+            { "name": "pixi:platform", "value": subject.platform.as_str() },
+        ],
+    });
+    if let Some(version) = &subject.version {
+        root_component["version"] = json!(version);
+    }
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "pixi",
+                    "version": consts::PIXI_VERSION,
+                }],
+            },
+            "component": root_component,
+        },
+        "components": bom_components,
+        "dependencies": dependencies,
+    })
+}
+
+fn render_spdx(subject: &SbomSubject, components: &[Component], timestamp: &str) -> Value {
+    let document_name = format!(
+        "{}-{}-{}",
+        subject.name, subject.environment, subject.platform
+    );
+
+    // The namespace has to be unique for every document, the hash of the
+    // locked packages makes it unique without making the output
+    // non-deterministic.
+    let content_hash =
+        compute_bytes_digest::<Sha256>(components.iter().map(|c| &c.purl).join("\n"));
+    let document_namespace = format!(
+        "https://pixi.sh/spdx/{}-{content_hash:x}",
+        utf8_percent_encode(&document_name, PURL_ENCODE_SET)
+    );
+
+    let packages = components
+        .iter()
+        .map(|component| {
+            let checksums = [("MD5", &component.md5), ("SHA256", &component.sha256)]
+                .into_iter()
+                .filter_map(|(algorithm, value)| {
+                    value
+                        .as_ref()
+                        .map(|value| json!({ "algorithm": algorithm, "checksumValue": value }))
+                })
+                .collect_vec();
+
+            let external_refs = std::iter::once(&component.purl)
+                .chain(&component.pypi_purls)
+                .map(|purl| {
+                    json!({
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": purl,
+                    })
+                })
+                .collect_vec();
+
+            let mut package = json!({
+                "name": component.name,
+                "SPDXID": component.spdx_id(),
+                "versionInfo": component.version,
+                "downloadLocation": component.url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "checksums": checksums,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": match &component.license {
+                    Some(License::Expression(expression)) => expression.as_str(),
+                    _ => "NOASSERTION",
+                },
+                "copyrightText": "NOASSERTION",
+                "externalRefs": external_refs,
+            });
+            if let Some(License::Name(name)) = &component.license {
+                package["licenseComments"] =
+                    json!(format!("The package declares the license '{name}'"));
+            }
+            package
+        })
+        .collect_vec();
+
+    let mut relationships = components
+        .iter()
+        .map(|component| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": component.spdx_id(),
+            })
+        })
+        .collect_vec();
+    for (component, depends_on) in components.iter().zip(resolve_dependencies(components)) {
+        relationships.extend(depends_on.into_iter().map(|index| {
+            json!({
+                "spdxElementId": component.spdx_id(),
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": components[index].spdx_id(),
+            })
+        }));
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": document_name,
+        "documentNamespace": document_namespace,
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: pixi-{}", consts::PIXI_VERSION)],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+pub async fn execute(project: Project, args: Args) -> miette::Result<()> {
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+
+    let lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install,
+            ..UpdateLockFileOptions::default()
+        })
+        .await?
+        .lock_file;
+    let locked_environment = lock_file
+        .environment(environment.name().as_str())
+        .ok_or_else(|| miette::miette!("unknown environment {}", environment.name()))?;
+
+    let components = collect_components(&locked_environment, platform)?;
+    let subject = SbomSubject {
+        name: project.name(),
+        version: project.version().as_ref().map(ToString::to_string),
+        environment: environment.name().as_str(),
+        platform,
+    };
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let sbom = match args.format {
+        SbomFormat::Cyclonedx => render_cyclonedx(&subject, &components, &timestamp),
+        SbomFormat::Spdx => render_spdx(&subject, &components, &timestamp),
+    };
+    let sbom = serde_json::to_string_pretty(&sbom).into_diagnostic()?;
+
+    if let Some(output_path) = args.output_path {
+        fs_err::write(&output_path, sbom)
+            .into_diagnostic()
+            .with_context(|| "failed to write the SBOM")?;
+    } else {
+        println!("{sbom}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use rattler_lock::LockFile;
+
+    use super::*;
+
+    fn test_components() -> Vec<Component> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/data/mock-projects/test-project-export/pixi.lock");
+        let lock_file = LockFile::from_path(&path).unwrap();
+        let environment = lock_file.environment("default").unwrap();
+        collect_components(&environment, Platform::Linux64).unwrap()
+    }
+
+    const SUBJECT: SbomSubject<'static> = SbomSubject {
+        name: "test-project-export",
+        version: None,
+        environment: "default",
+        platform: Platform::Linux64,
+    };
+
+    #[test]
+    fn test_parse_license() {
+        assert_eq!(
+            License::parse("MIT"),
+            License::Expression(String::from("MIT"))
+        );
+        assert_eq!(
+            License::parse("GPL-3.0-only WITH GCC-exception-3.1"),
+            License::Expression(String::from("GPL-3.0-only WITH GCC-exception-3.1"))
+        );
+        assert_eq!(
+            License::parse("MIT and PSF-2.0"),
+            License::Expression(String::from("MIT AND PSF-2.0"))
+        );
+        assert_eq!(License::parse("None"), License::Name(String::from("None")));
+    }
+
+    #[test]
+    fn test_collect_components() {
+        let components = test_components();
+
+        let python = components
+            .iter()
+            .find(|component| component.name == "python")
+            .unwrap();
+        assert_eq!(python.manager, "conda");
+        assert!(python
+            .purl
+            .starts_with("pkg:conda/python@3.12.5?build=h2ad013b_0_cpython&channel="));
+        assert!(python.purl.ends_with("&subdir=linux-64&type=conda"));
+        assert!(python.md5.is_some() && python.sha256.is_some());
+
+        let rich = components
+            .iter()
+            .find(|component| component.name == "rich")
+            .unwrap();
+        assert_eq!(rich.manager, "pypi");
+        assert_eq!(rich.purl, "pkg:pypi/rich@13.8.0");
+        assert!(rich.sha256.is_some());
+
+        // Conda packages that provide a PyPI package carry its package url.
+        let requests = components
+            .iter()
+            .find(|component| component.name == "requests")
+            .unwrap();
+        assert!(requests
+            .pypi_purls
+            .iter()
+            .any(|purl| purl.starts_with("pkg:pypi/requests")));
+    }
+
+    #[test]
+    fn test_render_cyclonedx() {
+        let components = test_components();
+        let bom = render_cyclonedx(&SUBJECT, &components, "2024-01-01T00:00:00Z");
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(
+            bom["components"].as_array().unwrap().len(),
+            components.len()
+        );
+
+        let rich = bom["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|component| component["name"] == "rich")
+            .unwrap();
+        assert_eq!(rich["purl"], "pkg:pypi/rich@13.8.0");
+        assert_eq!(rich["hashes"][0]["alg"], "SHA-256");
+
+        // rich depends on pygments, which is part of the environment.
+        let rich_dependencies = bom["dependencies"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|dependency| dependency["ref"] == "pkg:pypi/rich@13.8.0")
+            .unwrap();
+        assert!(rich_dependencies["dependsOn"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("pkg:pypi/pygments@2.18.0")));
+    }
+
+    #[test]
+    fn test_render_spdx() {
+        let components = test_components();
+        let document = render_spdx(&SUBJECT, &components, "2024-01-01T00:00:00Z");
+
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        let packages = document["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), components.len());
+
+        // Every package has a unique identifier.
+        let ids = packages
+            .iter()
+            .map(|package| package["SPDXID"].as_str().unwrap())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(ids.len(), packages.len());
+
+        let python = packages
+            .iter()
+            .find(|package| package["name"] == "python")
+            .unwrap();
+        assert_eq!(python["licenseDeclared"], "Python-2.0");
+        assert_eq!(
+            python["externalRefs"][0]["referenceLocator"],
+            json!(
+                components
+                    .iter()
+                    .find(|component| component.name == "python")
+                    .unwrap()
+                    .purl
+            )
+        );
+
+        // The document is deterministic for the same lock-file.
+        assert_eq!(
+            document,
+            render_spdx(&SUBJECT, &components, "2024-01-01T00:00:00Z")
+        );
+    }
+}
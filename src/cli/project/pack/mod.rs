@@ -0,0 +1,875 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::Parser;
+use fancy_display::FancyDisplay;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use pixi_consts::consts;
+use pixi_uv_conversions::locked_indexes_to_index_locations;
+use pypi_modifiers::pypi_tags::{get_pypi_tags, is_python_record};
+use rattler::package_cache::PackageCache;
+use rattler_conda_types::{
+    package::{FileMode, PrefixPlaceholder},
+    PackageRecord, Platform, RepoDataRecord,
+};
+use rattler_digest::{compute_file_digest, Sha256};
+use rattler_lock::{Package, PackageHashes, PypiPackageData, UrlOrPath};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use url::Url;
+use uv_distribution::RegistryWheelIndex;
+use uv_distribution_filename::WheelFilename;
+use uv_types::HashStrategy;
+
+use crate::{
+    activation::{get_static_environment_variables, run_activation, CurrentEnvVarBehavior},
+    cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
+    lock_file::{UpdateLockFileOptions, UvResolutionContext},
+    package_layout::{
+        binary_segments, conda_meta_path, conda_package_paths, find, python_version, wheel_paths,
+        PrefixPath, PrefixPathKind, SCRIPT_PLACEHOLDER,
+    },
+    Project,
+};
+
+/// The script that installs a packed environment on the target machine.
+const UNPACK_SCRIPT: &str = include_str!("unpack.sh");
+
+/// The file name of the unpack script in the pack.
+const UNPACK_SCRIPT_FILE: &str = "unpack.sh";
+
+/// The placeholder in the unpack script that is replaced with the platform of
+/// the pack.
+const PLATFORM_PLACEHOLDER: &str = "__PIXI_PACK_PLATFORM__";
+
+/// The placeholder that is replaced with the path of the unpacked
+/// environment.
+const PREFIX_PLACEHOLDER: &str = "__PIXI_PACK_PREFIX__";
+
+/// The directory that contains the files of the environment, as they are
+/// installed into the prefix.
+const ENVIRONMENT_DIR: &str = "env";
+
+/// The file that lists the prefix placeholders that are replaced by the unpack
+/// script, see `unpack.sh` for the format.
+const RELOCATIONS_FILE: &str = "relocate.txt";
+
+/// The file that contains the metadata of the pack.
+const METADATA_FILE: &str = "pixi-pack.json";
+
+/// The file that contains the activation script of the environment.
+const ACTIVATION_SCRIPT: &str = "activate.sh";
+
+/// Variables that only make sense on the machine that created the pack.
+const SKIPPED_VARIABLES: [&str; 4] = [
+    "PIXI_PROJECT_ROOT",
+    "PIXI_PROJECT_MANIFEST",
+    "PIXI_EXE",
+    "PIXI_IN_SHELL",
+];
+
+/// The metadata of a pack, stored in [`METADATA_FILE`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PackMetadata {
+    version: u32,
+    pixi_version: String,
+    environment: String,
+    platform: Platform,
+}
+
+/// Pack the locked conda packages and wheels of an environment into a single
+/// archive that can be installed offline.
+///
+/// The archive contains the files of the environment and an `unpack.sh`
+/// script that installs them into a prefix with standard POSIX tools, without
+/// pixi or network access.
+#[derive(Debug, Parser)]
+pub struct Args {
+    #[clap(flatten)]
+    pub project_config: ProjectConfig,
+
+    /// The environment to pack. Defaults to the default environment.
+    #[arg(short, long)]
+    pub environment: Option<String>,
+
+    /// The platform to pack the environment for. Defaults to the current
+    /// platform.
+    #[arg(short, long)]
+    pub platform: Option<Platform>,
+
+    /// The path of the archive to create. Defaults to
+    /// `<project>-<environment>-<platform>.tar` in the current directory.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub prefix_update_config: PrefixUpdateConfig,
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?
+        .with_cli_config(args.prefix_update_config.config.clone());
+    let environment = project.environment_from_name_or_env_var(args.environment)?;
+    let platform = args.platform.unwrap_or_else(|| environment.best_platform());
+    if platform.is_windows() {
+        miette::bail!("packing environments for '{platform}' is not supported yet");
+    }
+
+    let mut lock_file = project
+        .update_lock_file(UpdateLockFileOptions {
+            lock_file_usage: args.prefix_update_config.lock_file_usage(),
+            no_install: args.prefix_update_config.no_install(),
+            ..UpdateLockFileOptions::default()
+        })
+        .await?;
+
+    // Collect the locked packages of the environment.
+    let locked_environment = lock_file.lock_file.environment(environment.name().as_str());
+    let pypi_indexes = locked_environment
+        .as_ref()
+        .and_then(|env| env.pypi_indexes().cloned());
+    let packages = locked_environment
+        .and_then(|env| env.packages(platform).map(Vec::from_iter))
+        .ok_or_else(|| {
+            miette::miette!(
+                "the lock-file does not contain packages for environment {} on platform {}",
+                environment.name().fancy_display(),
+                consts::PLATFORM_STYLE.apply_to(platform)
+            )
+        })?;
+    let mut conda_records = Vec::new();
+    let mut pypi_packages = Vec::new();
+    for package in packages {
+        match package {
+            Package::Conda(package) => conda_records.push(
+                RepoDataRecord::try_from(package)
+                    .into_diagnostic()
+                    .context("failed to convert conda packages to RepoDataRecords")?,
+            ),
+            Package::Pypi(package) => pypi_packages.push(package.data().package.clone()),
+        }
+    }
+    let conda_records = PackageRecord::sort_topologically(conda_records);
+
+    // Embed the activation of the environment. The activation scripts can only be
+    // run if the environment is installed for the current platform.
+    let activation_variables = if platform == Platform::current()
+        && !args.prefix_update_config.no_install()
+    {
+        lock_file
+            .prefix(&environment, args.prefix_update_config.update_mode())
+            .await?;
+        // The variables of the env-files are left out, they often contain secrets.
+        run_activation(&environment, &CurrentEnvVarBehavior::Exclude, false).await?
+    } else {
+        tracing::warn!(
+                "the activation scripts of environment '{}' can only be embedded when packing for the current platform",
+                environment.name().fancy_display()
+            );
+        let prefix = environment.dir();
+        let mut variables: HashMap<_, _> = get_static_environment_variables(&environment)
+            .into_iter()
+            .collect();
+        variables.insert(
+            String::from("CONDA_PREFIX"),
+            prefix.to_string_lossy().into_owned(),
+        );
+        variables.insert(
+            String::from("PATH"),
+            prefix.join("bin").to_string_lossy().into_owned(),
+        );
+        variables
+    };
+    let activation_script = render_activation_script(activation_variables, &environment.dir());
+
+    let output = args.output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{}-{}-{}.tar",
+            project.name(),
+            environment.name(),
+            platform
+        ))
+    });
+    let root = output
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("pack"));
+    let mut archive = PackArchive::new(
+        fs_err::File::create(&output)
+            .into_diagnostic()
+            .context("failed to create the archive")?,
+        root,
+    );
+
+    // Add the files of the conda packages from the package cache, they are only
+    // downloaded if they are not cached yet.
+    let client = project.authenticated_client();
+    let package_cache =
+        PackageCache::new(pixi_config::get_cache_dir()?.join(consts::CONDA_PACKAGE_CACHE_DIR));
+    let python_version = python_version(&conda_records);
+    for record in &conda_records {
+        let cache_lock = package_cache
+            .get_or_fetch_from_url(
+                &record.package_record,
+                record.url.clone(),
+                client.clone(),
+                None,
+            )
+            .await
+            .into_diagnostic()
+            .with_context(|| format!("failed to fetch '{}'", record.file_name))?;
+        let paths = conda_package_paths(cache_lock.path(), record, python_version)?;
+        let conda_meta = conda_meta_path(record, &paths)?;
+        for prefix_path in paths.into_iter().chain([conda_meta]) {
+            archive.append_prefix_path(prefix_path)?;
+        }
+    }
+    archive.append_prefix_path(PrefixPath {
+        relative_path: String::from("conda-meta/history"),
+        kind: PrefixPathKind::Generated {
+            content: Vec::new(),
+            executable: false,
+        },
+    })?;
+
+    // Add the files of the wheels of the pypi packages, taken from the uv cache
+    // if they were installed before.
+    if !pypi_packages.is_empty() {
+        let python_record = conda_records
+            .iter()
+            .find(|record| is_python_record(record))
+            .ok_or_else(|| miette::miette!("the pypi packages of environment {} can only be packed with a python interpreter", environment.name().fancy_display()))?;
+        let python_version = python_version.ok_or_else(|| {
+            miette::miette!(
+                "failed to determine the version of '{}'",
+                python_record.file_name
+            )
+        })?;
+        let tags = get_pypi_tags(
+            platform,
+            &environment.system_requirements(),
+            &python_record.package_record,
+        )?;
+        let index_locations = pypi_indexes
+            .map(|indexes| locked_indexes_to_index_locations(&indexes, project.root()))
+            .transpose()
+            .into_diagnostic()?
+            .unwrap_or_default();
+        let uv_context = UvResolutionContext::from_project(&project)?;
+        let mut registry_index = RegistryWheelIndex::new(
+            &uv_context.cache,
+            &tags,
+            &index_locations,
+            &HashStrategy::None,
+        );
+
+        for package in &pypi_packages {
+            let file_name = wheel_file_name(package)?;
+            let filename = WheelFilename::from_str(&file_name).into_diagnostic()?;
+            let cached = registry_index
+                .get(&filename.name)
+                .find(|entry| entry.dist.filename == filename)
+                .map(|entry| entry.dist.path.clone());
+            let unpacked = match cached {
+                Some(cached) => {
+                    UnpackedWheel::Cached(fs_err::canonicalize(cached).into_diagnostic()?)
+                }
+                None => {
+                    let wheel = fetch_wheel(client, package, project.root()).await?;
+                    let unpacked = tempfile::tempdir().into_diagnostic()?;
+                    zip::ZipArchive::new(fs_err::File::open(wheel.path()).into_diagnostic()?)
+                        .and_then(|mut wheel| wheel.extract(unpacked.path()))
+                        .into_diagnostic()
+                        .with_context(|| format!("failed to unpack '{file_name}'"))?;
+                    UnpackedWheel::Temporary(unpacked)
+                }
+            };
+            for prefix_path in wheel_paths(unpacked.path(), python_version)
+                .with_context(|| format!("failed to read '{file_name}'"))?
+            {
+                archive.append_prefix_path(prefix_path)?;
+            }
+        }
+    }
+
+    archive.append_activation_script(&activation_script)?;
+    let metadata = PackMetadata {
+        version: 2,
+        pixi_version: consts::PIXI_VERSION.to_string(),
+        environment: environment.name().to_string(),
+        platform,
+    };
+    archive.append_file(
+        METADATA_FILE,
+        serde_json::to_string_pretty(&metadata)
+            .into_diagnostic()?
+            .as_bytes(),
+        0o644,
+    )?;
+    archive.append_file(
+        UNPACK_SCRIPT_FILE,
+        UNPACK_SCRIPT
+            .replace(PLATFORM_PLACEHOLDER, platform.as_str())
+            .as_bytes(),
+        0o755,
+    )?;
+    archive.finish()?;
+
+    eprintln!(
+        "{}Packed {} conda packages and {} wheels of environment {} for {} into {}",
+        console::style(console::Emoji("✔ ", "")).green(),
+        conda_records.len(),
+        pypi_packages.len(),
+        environment.name().fancy_display(),
+        consts::PLATFORM_STYLE.apply_to(platform),
+        output.display()
+    );
+
+    Ok(())
+}
+
+/// Renders a shell script that exports the activation variables of an
+/// environment. References to the prefix are replaced by a placeholder that is
+/// filled in when the environment is unpacked.
+fn render_activation_script(variables: HashMap<String, String>, prefix: &Path) -> String {
+    let prefix = prefix.to_string_lossy();
+    let mut script = String::from("# Generated by `pixi project pack`\n");
+    for (key, value) in variables.into_iter().sorted() {
+        let is_identifier = !key.is_empty()
+            && key.chars().enumerate().all(|(idx, c)| {
+                c == '_' || c.is_ascii_alphabetic() || (idx > 0 && c.is_ascii_digit())
+            });
+        if !is_identifier || SKIPPED_VARIABLES.contains(&key.as_str()) {
+            continue;
+        }
+
+        if key == "PATH" {
+            // Only keep the entries of the environment, the rest of the path is taken
+            // from the machine the environment is unpacked on.
+            let entries = value
+                .split(':')
+                .filter(|entry| entry.starts_with(prefix.as_ref()))
+                .map(|entry| entry.replace(prefix.as_ref(), PREFIX_PLACEHOLDER))
+                .join(":");
+            script.push_str(&format!("export PATH=\"{entries}:$PATH\"\n"));
+            continue;
+        }
+
+        let value = value
+            .replace(prefix.as_ref(), PREFIX_PLACEHOLDER)
+            .replace('\'', "'\\''");
+        script.push_str(&format!("export {key}='{value}'\n"));
+    }
+    script
+}
+
+/// The archive of a pack. The files of the environment are stored in
+/// [`ENVIRONMENT_DIR`], together with the prefix placeholders that the unpack
+/// script replaces.
+struct PackArchive<W: Write> {
+    builder: tar::Builder<W>,
+
+    /// The directory in the archive that contains the pack.
+    root: String,
+
+    /// The directories of the environment that were added to the archive.
+    directories: BTreeSet<String>,
+
+    /// The lines of the [`RELOCATIONS_FILE`] by the path of their file. A file
+    /// of a package that is overwritten by a later package is replaced.
+    relocations: IndexMap<String, Vec<String>>,
+}
+
+impl<W: Write> PackArchive<W> {
+    fn new(writer: W, root: String) -> Self {
+        let mut builder = tar::Builder::new(writer);
+        // Conda packages contain symlinks that have to be preserved.
+        builder.follow_symlinks(false);
+        Self {
+            builder,
+            root,
+            directories: BTreeSet::new(),
+            relocations: IndexMap::new(),
+        }
+    }
+
+    /// Appends a file with the given content to the root of the pack.
+    fn append_file(&mut self, path: &str, content: &[u8], mode: u32) -> miette::Result<()> {
+        let path = format!("{}/{path}", self.root);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        self.builder
+            .append_data(&mut header, &path, content)
+            .into_diagnostic()
+            .with_context(|| format!("failed to add '{path}' to the archive"))
+    }
+
+    /// Appends the directories of the environment that contain `relative_path`,
+    /// a path ending in a slash is added itself.
+    fn append_directories(&mut self, relative_path: &str) -> miette::Result<()> {
+        let directories = std::iter::once("").chain(
+            relative_path
+                .match_indices('/')
+                .map(|(idx, _)| &relative_path[..=idx]),
+        );
+        for directory in directories {
+            if !self.directories.insert(directory.to_string()) {
+                continue;
+            }
+            let path = format!("{}/{ENVIRONMENT_DIR}/{directory}", self.root);
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_size(0);
+            header.set_mode(0o755);
+            self.builder
+                .append_data(&mut header, &path, std::io::empty())
+                .into_diagnostic()
+                .with_context(|| format!("failed to add '{path}' to the archive"))?;
+        }
+        Ok(())
+    }
+
+    /// Appends a path of the environment, the prefix placeholders of files are
+    /// recorded for the unpack script.
+    fn append_prefix_path(&mut self, prefix_path: PrefixPath) -> miette::Result<()> {
+        let relative_path = prefix_path.relative_path;
+        self.append_directories(&relative_path)?;
+        self.relocations.shift_remove(&relative_path);
+        let path = format!("{}/{ENVIRONMENT_DIR}/{relative_path}", self.root);
+        let (content, mode, placeholder) = match prefix_path.kind {
+            PrefixPathKind::Directory => {
+                return self.append_directories(&format!("{relative_path}/"))
+            }
+            PrefixPathKind::Symlink(target) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_mode(0o777);
+                header.set_size(0);
+                return self
+                    .builder
+                    .append_link(&mut header, &path, &target)
+                    .into_diagnostic()
+                    .with_context(|| format!("failed to add '{path}' to the archive"));
+            }
+            PrefixPathKind::File {
+                source,
+                placeholder,
+            } => {
+                let metadata = fs_err::metadata(&source).into_diagnostic()?;
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata_in_mode(&metadata, tar::HeaderMode::Deterministic);
+                let content = fs_err::read(&source).into_diagnostic()?;
+                (content, header.mode().into_diagnostic()?, placeholder)
+            }
+            PrefixPathKind::Generated {
+                content,
+                executable,
+            } => (
+                content,
+                if executable { 0o755 } else { 0o644 },
+                Some(PrefixPlaceholder {
+                    file_mode: FileMode::Text,
+                    placeholder: SCRIPT_PLACEHOLDER.to_string(),
+                }),
+            ),
+        };
+
+        if let Some(placeholder) = placeholder {
+            let lines = relocations(&relative_path, &content, &placeholder);
+            if !lines.is_empty() {
+                self.relocations.insert(relative_path, lines);
+            }
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(mode);
+        self.builder
+            .append_data(&mut header, &path, content.as_slice())
+            .into_diagnostic()
+            .with_context(|| format!("failed to add '{path}' to the archive"))
+    }
+
+    /// Appends the activation script to the environment, its references to the
+    /// prefix are replaced by the unpack script.
+    fn append_activation_script(&mut self, script: &str) -> miette::Result<()> {
+        self.append_directories(ACTIVATION_SCRIPT)?;
+        let path = format!("{}/{ENVIRONMENT_DIR}/{ACTIVATION_SCRIPT}", self.root);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(script.len() as u64);
+        header.set_mode(0o644);
+        self.builder
+            .append_data(&mut header, &path, script.as_bytes())
+            .into_diagnostic()
+            .with_context(|| format!("failed to add '{path}' to the archive"))?;
+        self.relocations.insert(
+            ACTIVATION_SCRIPT.to_string(),
+            vec![format!("text\t{PREFIX_PLACEHOLDER}\t{ACTIVATION_SCRIPT}")],
+        );
+        Ok(())
+    }
+
+    /// Writes the relocations and finishes the archive.
+    fn finish(mut self) -> miette::Result<W> {
+        let relocations = self
+            .relocations
+            .values()
+            .flatten()
+            .map(|line| format!("{line}\n"))
+            .collect::<String>();
+        self.append_file(RELOCATIONS_FILE, relocations.as_bytes(), 0o644)?;
+        let mut writer = self
+            .builder
+            .into_inner()
+            .into_diagnostic()
+            .context("failed to write the archive")?;
+        writer
+            .flush()
+            .into_diagnostic()
+            .context("failed to write the archive")?;
+        Ok(writer)
+    }
+}
+
+/// Returns the lines of the [`RELOCATIONS_FILE`] that replace the placeholder
+/// in a file of the environment. Binary files are relocated by rewriting each
+/// null terminated string that contains the placeholder, its parts are
+/// escaped for `printf`.
+fn relocations(
+    relative_path: &str,
+    content: &[u8],
+    placeholder: &PrefixPlaceholder,
+) -> Vec<String> {
+    let from = placeholder.placeholder.as_bytes();
+    match placeholder.file_mode {
+        FileMode::Text if find(content, from).is_some() => vec![format!(
+            "text\t{}\t{relative_path}",
+            placeholder.placeholder
+        )],
+        FileMode::Text => Vec::new(),
+        FileMode::Binary => binary_segments(content, from)
+            .into_iter()
+            .map(|segment| {
+                let template = segment
+                    .parts
+                    .iter()
+                    .map(|part| part.iter().map(|byte| format!("\\{byte:03o}")).join(""))
+                    .join("%s");
+                format!(
+                    "binary\t{}\t{}\t{}\t{template}\t{relative_path}",
+                    segment.offset,
+                    segment.parts.len() - 1,
+                    from.len()
+                )
+            })
+            .collect(),
+    }
+}
+
+/// An unpacked wheel, either in the uv cache or in a temporary directory.
+enum UnpackedWheel {
+    Cached(PathBuf),
+    Temporary(tempfile::TempDir),
+}
+
+impl UnpackedWheel {
+    fn path(&self) -> &Path {
+        match self {
+            UnpackedWheel::Cached(path) => path,
+            UnpackedWheel::Temporary(dir) => dir.path(),
+        }
+    }
+}
+
+/// Returns the file name of the wheel of a locked pypi package.
+fn wheel_file_name(package: &PypiPackageData) -> miette::Result<String> {
+    let file_name = match &package.url_or_path {
+        UrlOrPath::Url(url) if url.scheme().starts_with("git+") => {
+            miette::bail!(
+                "the pypi package '{}' is a git dependency, only wheels can be packed",
+                package.name
+            );
+        }
+        UrlOrPath::Url(url) => url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|name| {
+                percent_encoding::percent_decode_str(name)
+                    .decode_utf8_lossy()
+                    .into_owned()
+            })
+            .unwrap_or_default(),
+        UrlOrPath::Path(path) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+
+    if !file_name.ends_with(".whl") {
+        miette::bail!(
+            "the pypi package '{}' is locked as a source distribution or source tree, only wheels can be packed",
+            package.name
+        );
+    }
+    Ok(file_name)
+}
+
+/// A wheel on disk, either a local file or a temporary download.
+enum LocalWheel {
+    Path(PathBuf),
+    Downloaded(NamedTempFile),
+}
+
+impl LocalWheel {
+    fn path(&self) -> &Path {
+        match self {
+            LocalWheel::Path(path) => path,
+            LocalWheel::Downloaded(file) => file.path(),
+        }
+    }
+}
+
+/// Returns the wheel of a locked pypi package on disk. Remote wheels are
+/// streamed to a temporary file.
+async fn fetch_wheel(
+    client: &ClientWithMiddleware,
+    package: &PypiPackageData,
+    project_root: &Path,
+) -> miette::Result<LocalWheel> {
+    let wheel = match &package.url_or_path {
+        UrlOrPath::Url(url) => {
+            let url = url
+                .as_str()
+                .strip_prefix("direct+")
+                .and_then(|url| Url::parse(url).ok())
+                .unwrap_or_else(|| url.clone());
+            if url.scheme() == "file" {
+                LocalWheel::Path(
+                    url.to_file_path()
+                        .map_err(|_| miette::miette!("invalid file url '{url}'"))?,
+                )
+            } else {
+                let mut response = client
+                    .get(url.clone())
+                    .send()
+                    .await
+                    .into_diagnostic()
+                    .and_then(|response| response.error_for_status().into_diagnostic())
+                    .with_context(|| format!("failed to download '{url}'"))?;
+                let mut file = NamedTempFile::new().into_diagnostic()?;
+                while let Some(chunk) = response
+                    .chunk()
+                    .await
+                    .into_diagnostic()
+                    .with_context(|| format!("failed to download '{url}'"))?
+                {
+                    file.write_all(&chunk).into_diagnostic()?;
+                }
+                LocalWheel::Downloaded(file)
+            }
+        }
+        UrlOrPath::Path(path) => LocalWheel::Path(project_root.join(path)),
+    };
+
+    if let Some(PackageHashes::Sha256(sha256) | PackageHashes::Md5Sha256(_, sha256)) = &package.hash
+    {
+        let digest = compute_file_digest::<Sha256>(wheel.path())
+            .into_diagnostic()
+            .with_context(|| format!("failed to read '{}'", wheel.path().display()))?;
+        if &digest != sha256 {
+            miette::bail!(
+                "the sha256 hash of the wheel of '{}' does not match the lock-file",
+                package.name
+            );
+        }
+    }
+
+    Ok(wheel)
+}
+
+#[cfg(test)]
+mod tests {
+    use rattler_conda_types::VersionWithSource;
+
+    use super::*;
+
+    #[test]
+    fn test_relocations() {
+        let text = PrefixPlaceholder {
+            file_mode: FileMode::Text,
+            placeholder: String::from("/opt/placeholder"),
+        };
+        assert_eq!(
+            relocations("bin/tool", b"#!/opt/placeholder/bin/python\n", &text),
+            vec!["text\t/opt/placeholder\tbin/tool"]
+        );
+        assert!(relocations("bin/tool", b"#!/usr/bin/env python\n", &text).is_empty());
+
+        let binary = PrefixPlaceholder {
+            file_mode: FileMode::Binary,
+            placeholder: String::from("/p"),
+        };
+        assert_eq!(
+            relocations("lib/libfoo.so", b"\0/p/a:/p\0b%\0", &binary),
+            vec!["binary\t1\t2\t2\t%s\\057\\141\\072%s\tlib/libfoo.so"]
+        );
+    }
+
+    /// Packs an environment with a single package, unpacks it with the unpack
+    /// script and checks that the placeholders are replaced.
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_script() {
+        // A long placeholder like conda-build uses, so that the prefix in the
+        // temporary directory fits.
+        let placeholder = format!("/opt/{}", "placehold_".repeat(20));
+        let package_dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("bin/tool", format!("#!{placeholder}/bin/python\n")),
+            ("lib/libfoo.so", format!("\x7fELF\0{placeholder}/lib:{placeholder}/lib64\0rest\0")),
+            ("share/data", format!("{placeholder}\n")),
+            ("info/paths.json", serde_json::json!({
+                "paths": [
+                    { "_path": "bin/tool", "path_type": "hardlink", "prefix_placeholder": &placeholder, "file_mode": "text" },
+                    { "_path": "lib/libfoo.so", "path_type": "hardlink", "prefix_placeholder": &placeholder, "file_mode": "binary" },
+                    { "_path": "share/data", "path_type": "hardlink" },
+                ],
+                "paths_version": 1,
+            }).to_string()),
+        ];
+        for (path, content) in &files {
+            let path = package_dir.path().join(path);
+            fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+            fs_err::write(path, content).unwrap();
+        }
+        let record = RepoDataRecord {
+            package_record: PackageRecord::new(
+                "foo".parse().unwrap(),
+                VersionWithSource::from_str("1.0").unwrap(),
+                "h123_0".to_string(),
+            ),
+            file_name: String::from("foo-1.0-h123_0.conda"),
+            url: Url::from_str("https://example.com/foo-1.0-h123_0.conda").unwrap(),
+            channel: String::from("https://example.com"),
+        };
+
+        let mut archive = PackArchive::new(Vec::new(), String::from("pack"));
+        for prefix_path in conda_package_paths(package_dir.path(), &record, None).unwrap() {
+            archive.append_prefix_path(prefix_path).unwrap();
+        }
+        archive
+            .append_activation_script("export CONDA_PREFIX='__PIXI_PACK_PREFIX__'\n")
+            .unwrap();
+        archive
+            .append_file(
+                UNPACK_SCRIPT_FILE,
+                UNPACK_SCRIPT
+                    .replace(PLATFORM_PLACEHOLDER, Platform::current().as_str())
+                    .as_bytes(),
+                0o755,
+            )
+            .unwrap();
+        let archive = archive.finish().unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        tar::Archive::new(archive.as_slice())
+            .unpack(target.path())
+            .unwrap();
+        let prefix = target.path().join("unpacked env");
+        let output = std::process::Command::new("sh")
+            .arg(target.path().join("pack").join(UNPACK_SCRIPT_FILE))
+            .arg(&prefix)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let prefix = fs_err::canonicalize(&prefix).unwrap();
+        let prefix_str = prefix.to_string_lossy();
+        assert_eq!(
+            fs_err::read_to_string(prefix.join("bin/tool")).unwrap(),
+            format!("#!{prefix_str}/bin/python\n")
+        );
+        assert_eq!(
+            fs_err::read_to_string(prefix.join("share/data")).unwrap(),
+            format!("{placeholder}\n")
+        );
+        assert_eq!(
+            fs_err::read_to_string(prefix.join(ACTIVATION_SCRIPT)).unwrap(),
+            format!("export CONDA_PREFIX='{prefix_str}'\n")
+        );
+        let binary = fs_err::read(prefix.join("lib/libfoo.so")).unwrap();
+        let mut expected = format!("\x7fELF\0{prefix_str}/lib:{prefix_str}/lib64").into_bytes();
+        expected.resize(binary.len() - "\0rest\0".len(), 0);
+        expected.extend_from_slice(b"\0rest\0");
+        assert_eq!(binary, expected);
+    }
+
+    #[tokio::test]
+    async fn test_activation_excludes_env_files() {
+        let root = tempfile::tempdir().unwrap();
+        fs_err::write(root.path().join(".env"), "SECRET=hunter2\n").unwrap();
+        let project = Project::from_str(
+            &root.path().join("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+        [activation]
+        env = { GREETING = "hello" }
+        env-file = [".env"]
+        "#,
+        )
+        .unwrap();
+        let environment = project.default_environment();
+
+        let variables = run_activation(&environment, &CurrentEnvVarBehavior::Exclude, false)
+            .await
+            .unwrap();
+        let script = render_activation_script(variables, &environment.dir());
+        assert!(script.contains("GREETING"));
+        assert!(!script.contains("hunter2"));
+    }
+
+    #[test]
+    fn test_render_activation_script() {
+        let prefix = Path::new("/home/user/project/.pixi/envs/default");
+        let variables = HashMap::from([
+            (
+                String::from("PATH"),
+                String::from("/home/user/project/.pixi/envs/default/bin:/usr/bin"),
+            ),
+            (
+                String::from("CONDA_PREFIX"),
+                String::from("/home/user/project/.pixi/envs/default"),
+            ),
+            (String::from("GREETING"), String::from("it's me")),
+            (
+                String::from("PIXI_PROJECT_ROOT"),
+                String::from("/home/user/project"),
+            ),
+            (String::from("BASH_FUNC_x%%"), String::from("() { :; }")),
+        ]);
+
+        assert_eq!(
+            render_activation_script(variables, prefix),
+            "# Generated by `pixi project pack`\n\
+             export CONDA_PREFIX='__PIXI_PACK_PREFIX__'\n\
+             export GREETING='it'\\''s me'\n\
+             export PATH=\"__PIXI_PACK_PREFIX__/bin:$PATH\"\n"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+#!/bin/sh
+# Installs the environment packed by `pixi project pack` without network access.
+#
+# Usage: ./unpack.sh [PREFIX]
+#
+# The environment in the `env` directory of the pack is copied to PREFIX, which
+# defaults to that directory, and the prefix placeholders of its files are
+# replaced by the location of PREFIX. Only standard POSIX tools are used,
+# neither pixi nor conda has to be installed on the machine.
+set -eu
+
+PLATFORM="__PIXI_PACK_PLATFORM__"
+PACK_DIR=$(cd "$(dirname "$0")" && pwd)
+
+case "$(uname -s)-$(uname -m)" in
+    Linux-x86_64) CURRENT_PLATFORM=linux-64 ;;
+    Linux-i686) CURRENT_PLATFORM=linux-32 ;;
+    Linux-aarch64 | Linux-arm64) CURRENT_PLATFORM=linux-aarch64 ;;
+    Linux-armv6l) CURRENT_PLATFORM=linux-armv6l ;;
+    Linux-armv7l) CURRENT_PLATFORM=linux-armv7l ;;
+    Linux-ppc64le) CURRENT_PLATFORM=linux-ppc64le ;;
+    Linux-ppc64) CURRENT_PLATFORM=linux-ppc64 ;;
+    Linux-s390x) CURRENT_PLATFORM=linux-s390x ;;
+    Linux-riscv64) CURRENT_PLATFORM=linux-riscv64 ;;
+    Darwin-x86_64) CURRENT_PLATFORM=osx-64 ;;
+    Darwin-arm64) CURRENT_PLATFORM=osx-arm64 ;;
+    *) CURRENT_PLATFORM="$(uname -s)-$(uname -m)" ;;
+esac
+if [ "$CURRENT_PLATFORM" != "$PLATFORM" ]; then
+    echo "error: the pack contains an environment for $PLATFORM, it cannot be installed on $CURRENT_PLATFORM" >&2
+    exit 1
+fi
+
+PREFIX=${1:-"$PACK_DIR/env"}
+mkdir -p "$PREFIX"
+PREFIX=$(cd "$PREFIX" && pwd)
+if [ "$PREFIX" != "$PACK_DIR/env" ]; then
+    cp -R -P "$PACK_DIR/env/." "$PREFIX/"
+fi
+
+PREFIX_LENGTH=$(printf '%s' "$PREFIX" | wc -c)
+PREFIX_LENGTH=$((PREFIX_LENGTH))
+SED_PREFIX=$(printf '%s\n' "$PREFIX" | sed -e 's/[\/&]/\\&/g')
+TAB=$(printf '\t')
+
+# Every line of `relocate.txt` describes a placeholder to replace, the path of
+# the file relative to the prefix is the last field:
+#   text    <placeholder> <path>
+#   binary  <offset> <count> <placeholder length> <template> <path>
+# A binary line rewrites the null terminated string at the offset, it is
+# rendered by printf from the template with the prefix for each of the <count>
+# placeholders and padded with null bytes to keep the size of the file.
+while IFS="$TAB" read -r kind field1 field2 field3 field4 field5; do
+    case "$kind" in
+        text)
+            file="$PREFIX/$field2"
+            placeholder=$(printf '%s\n' "$field1" | sed -e 's/[]\/$*.^[]/\\&/g')
+            chmod u+w "$file"
+            sed -e "s/$placeholder/$SED_PREFIX/g" "$file" >"$file.pixi-unpack"
+            cat "$file.pixi-unpack" >"$file"
+            rm -f "$file.pixi-unpack"
+            ;;
+        binary)
+            file="$PREFIX/$field5"
+            if [ "$PREFIX_LENGTH" -gt "$field3" ]; then
+                echo "error: the prefix '$PREFIX' is longer than the placeholder of '$field5'" >&2
+                exit 1
+            fi
+            padding=$((field2 * (field3 - PREFIX_LENGTH)))
+            set --
+            while [ "$#" -lt "$field2" ]; do
+                set -- "$@" "$PREFIX"
+            done
+            chmod u+w "$file"
+            {
+                # shellcheck disable=SC2059
+                printf "$field4" "$@"
+                dd if=/dev/zero bs=1 count="$padding" 2>/dev/null
+            } | dd of="$file" bs=1 seek="$field1" conv=notrunc 2>/dev/null
+            ;;
+    esac
+done <"$PACK_DIR/relocate.txt"
+
+echo "Unpacked the environment into '$PREFIX', run 'source $PREFIX/activate.sh' to activate it"
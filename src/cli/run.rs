@@ -1,25 +1,37 @@
 use clap::Parser;
+use deno_task_shell::{execute_with_pipes, pipe, ShellPipeReader, ShellPipeWriter, ShellState};
 use dialoguer::theme::ColorfulTheme;
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
 use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic};
 use std::collections::hash_map::Entry;
 use std::collections::HashSet;
 use std::convert::identity;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::{collections::HashMap, string::String};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use crate::cli::cli_config::{PrefixUpdateConfig, ProjectConfig};
 use crate::environment::verify_prefix_location_unchanged;
-use crate::lock_file::UpdateLockFileOptions;
+use crate::lock_file::{LockFileDerivedData, UpdateLockFileOptions, UpdateMode};
 use crate::project::errors::UnsupportedPlatformError;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::Environment;
 use crate::task::{
-    get_task_env, AmbiguousTask, CanSkip, ExecutableTask, FailedToParseShellScript,
-    InvalidWorkingDirectory, SearchEnvironments, TaskAndEnvironment, TaskGraph,
+    get_task_env, wait_until_ready, AmbiguousTask, CanSkip, ExecutableTask,
+    FailedToParseShellScript, InputWatcher, InvalidWorkingDirectory, LogProbe, SearchEnvironments,
+    TaskAndEnvironment, TaskGraph, TaskHash, TaskId, TaskReport, TaskRunReport, TaskScheduler,
+    TaskStatus,
 };
 use crate::Project;
+use chrono::{DateTime, Utc};
 use fancy_display::FancyDisplay;
-use pixi_manifest::TaskName;
+use pixi_manifest::{task::Service, TaskName};
+use rattler_lock::LockFile;
 use thiserror::Error;
 use tracing::Level;
 
@@ -45,6 +57,26 @@ pub struct Args {
     /// Using this flag will ignore your current shell environment and use bare minimum environment to activate the pixi environment in.
     #[arg(long)]
     pub clean_env: bool,
+
+    /// The maximum number of tasks to run at the same time.
+    ///
+    /// Tasks that do not depend on each other are run concurrently, with the output of each task
+    /// prefixed by its name. Defaults to running one task at a time.
+    #[arg(long, short)]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Rerun the task whenever one of the files matched by the `inputs` of the task, or of the
+    /// tasks it depends on, changes.
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Write a report of the executed tasks to the given file.
+    ///
+    /// The report records the environment, command, timing, exit code, cache status and hashes of
+    /// every task. It is written as JUnit XML if the file has an `.xml` extension and as JSON
+    /// otherwise.
+    #[arg(long, conflicts_with = "watch")]
+    pub report: Option<PathBuf>,
 }
 
 /// CLI entry point for `pixi run`
@@ -101,115 +133,884 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     tracing::info!("Task graph: {}", task_graph);
 
-    // Traverse the task graph in topological order and execute each individual task.
-    let mut task_idx = 0;
+    let options = RunOptions {
+        jobs: args.jobs.map_or(1, NonZeroUsize::get),
+        clean_env: args.clean_env,
+        update_mode: args.prefix_update_config.update_mode(),
+    };
+
+    // The environment variables of the environments in which tasks are executed. These are
+    // lazily computed because we only need them if a task is actually executed.
     let mut task_envs = HashMap::new();
-    for task_id in task_graph.topological_order() {
-        let executable_task = ExecutableTask::from_task_graph(&task_graph, task_id);
 
-        // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
-        // instantiate a prefix for an alias.
-        if !executable_task.task().is_executable() {
-            continue;
+    if args.watch {
+        watch_task_graph(&task_graph, &mut lock_file, &mut task_envs, &options).await?;
+    } else {
+        let mut report = args.report.as_ref().map(|_| TaskRunReport::default());
+        let outcome = execute_task_graph(
+            &task_graph,
+            &mut lock_file,
+            &mut task_envs,
+            &options,
+            report.as_mut(),
+            &CancellationToken::new(),
+        )
+        .await;
+
+        // The report is also written when the execution failed, it then records the tasks that
+        // ran up to the failure.
+        if let (Some(path), Some(report)) = (&args.report, &report) {
+            report.write(path)?;
         }
+        let outcome = outcome?;
 
-        // Showing which command is being run if the level and type allows it.
-        if tracing::enabled!(Level::WARN) && !executable_task.task().is_custom() {
-            if task_idx > 0 {
-                // Add a newline between task outputs
-                eprintln!();
+        // If one of the tasks failed with a non-zero exit code, we exit this parent process with
+        // the same code.
+        if let TaskGraphOutcome::Failed(code) = outcome {
+            if code == 127 {
+                command_not_found(&project, explicit_environment);
             }
-            eprintln!(
-                "{}{}{}{}{}{}{}",
-                console::Emoji("✨ ", ""),
-                console::style("Pixi task (").bold(),
-                console::style(executable_task.name().unwrap_or("unnamed"))
-                    .green()
-                    .bold(),
-                // Only print environment if multiple environments are available
-                if project.environments().len() > 1 {
-                    format!(
-                        " in {}",
-                        executable_task.run_environment.name().fancy_display()
+            std::process::exit(code);
+        }
+    }
+
+    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
+    Ok(())
+}
+
+/// Options that control how the tasks of a [`TaskGraph`] are executed.
+struct RunOptions {
+    /// The maximum number of tasks to run at the same time.
+    jobs: usize,
+
+    /// Whether to run the tasks in a clean environment.
+    clean_env: bool,
+
+    /// How to make sure the prefixes of the environments are up-to-date.
+    update_mode: UpdateMode,
+}
+
+/// The result of executing all the tasks in a [`TaskGraph`].
+enum TaskGraphOutcome {
+    /// All tasks were executed successfully or could be skipped.
+    Succeeded,
+
+    /// A task exited with the contained non-zero exit code.
+    Failed(i32),
+
+    /// The execution was cancelled before all tasks were executed.
+    Cancelled,
+}
+
+/// Executes the tasks in the graph. Tasks are started as soon as all the tasks they depend on
+/// have completed, running at most `jobs` tasks at the same time.
+///
+/// When a task fails, the other running tasks are killed and no new tasks are started. The same
+/// happens when the `cancel` token is cancelled.
+///
+/// Services are started in the background. The tasks that depend on a service are started once
+/// the service is ready and the service is stopped when these tasks have finished.
+///
+/// If a `report` is passed, every task in the graph is recorded in it.
+async fn execute_task_graph<'p>(
+    task_graph: &TaskGraph<'p>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_envs: &mut HashMap<Environment<'p>, HashMap<String, String>>,
+    options: &RunOptions,
+    report: Option<&mut TaskRunReport>,
+    cancel: &CancellationToken,
+) -> miette::Result<TaskGraphOutcome> {
+    let mut recorder = TaskRecorder::new(report);
+    let outcome = execute_tasks(
+        task_graph,
+        lock_file,
+        task_envs,
+        options,
+        &mut recorder,
+        cancel,
+    )
+    .await;
+
+    // Record the tasks that were never started, also when the execution failed.
+    recorder
+        .record_not_run(task_graph, &lock_file.lock_file)
+        .await;
+
+    outcome
+}
+
+/// Executes the tasks of the graph, see [`execute_task_graph`]. The tasks are recorded in the
+/// `recorder` as they finish.
+///
+/// When pixi fails to execute a task, the running tasks are killed and have exited before the
+/// error is returned.
+async fn execute_tasks<'p>(
+    task_graph: &TaskGraph<'p>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_envs: &mut HashMap<Environment<'p>, HashMap<String, String>>,
+    options: &RunOptions,
+    recorder: &mut TaskRecorder<'_>,
+    cancel: &CancellationToken,
+) -> miette::Result<TaskGraphOutcome> {
+    // The running tasks only need the lock-file itself, the derived data is used to install the
+    // environments of the tasks that are executed.
+    let locked = lock_file.lock_file.clone();
+    let environments = TaskEnvironments::new(lock_file, task_envs, options);
+
+    // A token that is cancelled when the running tasks should be killed.
+    let kill = cancel.child_token();
+
+    // The hashes of the tasks in the report are computed by the running tasks, so the files are
+    // not hashed again while scheduling.
+    let is_recording = recorder.is_recording();
+
+    let mut scheduler = TaskScheduler::new(task_graph);
+    let mut running = FuturesUnordered::new();
+    let mut services = BackgroundServices::default();
+    let mut service_exits = FuturesUnordered::new();
+    let mut task_idx = 0;
+    let mut failed_exit_code = None;
+    let mut error: Option<miette::Report> = None;
+    loop {
+        // Start as many tasks as are ready, up to the maximum number of concurrent jobs.
+        while running.len() < options.jobs && !kill.is_cancelled() {
+            let Some(task_id) = scheduler.next_ready() else {
+                break;
+            };
+            let executable_task = ExecutableTask::from_task_graph(task_graph, task_id);
+
+            // If the task is not executable (e.g. an alias), we skip it. This ensures we don't
+            // instantiate a prefix for an alias.
+            if !executable_task.task().is_executable() {
+                services.release(task_graph, task_id);
+                scheduler.complete(task_id);
+                continue;
+            }
+
+            // Add a newline between the outputs of the tasks.
+            let separate = task_idx > 0;
+
+            // A service that other tasks depend on is started in the background. Instead of
+            // waiting for the service to exit, we wait for it to become ready. Services are never
+            // skipped because of the task cache, the tasks that depend on them need them running.
+            let dependents = task_graph.dependents(task_id);
+            if let (Some(service), false) =
+                (task_graph[task_id].task.as_service(), dependents.is_empty())
+            {
+                let stop = services.start(task_id, dependents, &kill);
+                let log_probe = LogProbe::from_probe(&service.ready);
+
+                // The service is started once its environment is known, which is sent by the
+                // task that waits for the service to become ready.
+                let (env_sender, env_receiver) = tokio::sync::oneshot::channel();
+                let service_task = executable_task.clone();
+                let service_log_probe = log_probe.clone();
+                let locked = &locked;
+                service_exits.push(async move {
+                    let Ok(service_env) = env_receiver.await else {
+                        // The service was never started.
+                        return (task_id, service_task, true, Ok(()), None);
+                    };
+
+                    // The output of a service is always prefixed because it runs alongside the
+                    // tasks that depend on it.
+                    let prefix = service_task.name().unwrap_or("unnamed").to_string();
+                    let result = execute_task_once(
+                        &service_task,
+                        &service_env,
+                        Some(&prefix),
+                        &stop,
+                        service_log_probe.as_ref(),
                     )
-                } else {
-                    "".to_string()
-                },
-                console::style("): ").bold(),
-                executable_task.display_command(),
-                if let Some(description) = executable_task.task().description() {
-                    console::style(format!(": ({})", description)).yellow()
-                } else {
-                    console::style("".to_string()).yellow()
+                    .await;
+                    let stopped = stop.is_cancelled();
+                    let hash = if stopped {
+                        None
+                    } else {
+                        report_hash(&service_task, locked, is_recording).await
+                    };
+                    (task_id, service_task, stopped, result, hash)
+                });
+
+                let kill = kill.clone();
+                let environments = &environments;
+                running.push(
+                    async move {
+                        let outcome = start_service(
+                            &executable_task,
+                            service,
+                            log_probe,
+                            environments,
+                            locked,
+                            env_sender,
+                            separate,
+                            is_recording,
+                            &kill,
+                        )
+                        .await;
+                        (task_id, executable_task, outcome)
+                    }
+                    .boxed_local(),
+                );
+                continue;
+            }
+
+            // When multiple tasks run at the same time, prefix their output with the name of the
+            // task so it can be told apart.
+            let output_prefix =
+                (options.jobs > 1).then(|| executable_task.name().unwrap_or("unnamed").to_string());
+
+            // Evaluating the condition, checking the task cache and installing the environment
+            // all happen in the background, so they don't hold up the tasks that are already
+            // running.
+            let kill = kill.clone();
+            let locked = &locked;
+            let environments = &environments;
+            running.push(
+                async move {
+                    let outcome = start_task(
+                        &executable_task,
+                        environments,
+                        locked,
+                        output_prefix.as_deref(),
+                        separate,
+                        is_recording,
+                        &kill,
+                    )
+                    .await;
+                    (task_id, executable_task, outcome)
                 }
+                .boxed_local(),
             );
         }
 
-        // check task cache
-        let task_cache = match executable_task
-            .can_skip(&lock_file)
-            .await
-            .into_diagnostic()?
-        {
-            CanSkip::No(cache) => cache,
-            CanSkip::Yes => {
+        // The services are no longer needed when no other task is running.
+        if running.is_empty() {
+            services.stop_all();
+        }
+
+        // Wait for the next task to finish, if there is nothing running anymore all tasks have
+        // been executed.
+        let finished = tokio::select! {
+            Some(finished) = running.next() => finished,
+            Some((task_id, service_task, stopped, result, hash)) = service_exits.next() => {
+                // A service that exits before it is stopped fails the execution, because the tasks
+                // that depend on it can no longer use it.
+                if !stopped && !kill.is_cancelled() {
+                    let code = match result {
+                        Ok(()) => 1,
+                        Err(err) => match err.exit_code() {
+                            Some(code) => code,
+                            None => {
+                                error.get_or_insert(err.into());
+                                kill.cancel();
+                                continue;
+                            }
+                        },
+                    };
+                    eprintln!(
+                        "Service '{}' exited unexpectedly with exit code {code}",
+                        console::style(service_task.name().unwrap_or("unnamed")).bold(),
+                    );
+                    failed_exit_code = Some(code);
+                    kill.cancel();
+                    recorder.record(
+                        task_id,
+                        &service_task,
+                        hash.as_ref(),
+                        TaskStatus::Failed,
+                        |report| report.with_exit_code(code),
+                    );
+                }
+                services.release(task_graph, task_id);
+                continue;
+            }
+            else => break,
+        };
+        let (task_id, executable_task, outcome) = finished;
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                // Kill the other tasks, the error is returned once they have exited.
+                error.get_or_insert(err);
+                kill.cancel();
+                continue;
+            }
+        };
+        let (result, started_at, finished_at, hash) = match outcome {
+            TaskOutcome::ConditionNotMet(hash) => {
+                eprintln!(
+                    "Task '{}' is skipped because its condition `{}` is false ⏭️",
+                    console::style(executable_task.name().unwrap_or("")).bold(),
+                    executable_task.task().condition().unwrap_or_default()
+                );
+                recorder.record(
+                    task_id,
+                    &executable_task,
+                    hash.as_ref(),
+                    TaskStatus::Skipped,
+                    identity,
+                );
+                task_idx += 1;
+                services.release(task_graph, task_id);
+                scheduler.complete(task_id);
+                continue;
+            }
+            TaskOutcome::CacheHit(hash) => {
                 eprintln!(
                     "Task '{}' can be skipped (cache hit) 🚀",
                     console::style(executable_task.name().unwrap_or("")).bold()
                 );
+                recorder.record(
+                    task_id,
+                    &executable_task,
+                    Some(&hash),
+                    TaskStatus::Skipped,
+                    TaskReport::with_cache_hit,
+                );
                 task_idx += 1;
+                services.release(task_graph, task_id);
+                scheduler.complete(task_id);
                 continue;
             }
-        };
-
-        // If we don't have a command environment yet, we need to compute it. We lazily compute the
-        // task environment because we only need the environment if a task is actually executed.
-        let task_env: &_ = match task_envs.entry(executable_task.run_environment.clone()) {
-            Entry::Occupied(env) => env.into_mut(),
-            Entry::Vacant(entry) => {
-                // Ensure there is a valid prefix
-                lock_file
-                    .prefix(
-                        &executable_task.run_environment,
-                        args.prefix_update_config.update_mode(),
-                    )
-                    .await?;
-
-                let command_env = get_task_env(
-                    &executable_task.run_environment,
-                    args.clean_env || executable_task.task().clean_env(),
-                )
-                .await?;
-                entry.insert(command_env)
+            // Tasks that were not started before the execution was killed are not run.
+            TaskOutcome::NotStarted => continue,
+            TaskOutcome::Executed(result, started_at, finished_at, hash) => {
+                (result, started_at, finished_at, hash)
             }
         };
 
-        // Execute the task itself within the command environment. If one of the tasks failed with
-        // a non-zero exit code, we exit this parent process with the same code.
-        match execute_task(&executable_task, task_env).await {
+        // The results of tasks that were killed are not relevant.
+        if kill.is_cancelled() {
+            recorder.record(
+                task_id,
+                &executable_task,
+                hash.as_ref(),
+                TaskStatus::Cancelled,
+                |report| report.with_timing(started_at, finished_at),
+            );
+            continue;
+        }
+
+        // If one of the tasks failed with a non-zero exit code, we kill all other running
+        // tasks.
+        match result {
             Ok(_) => {
                 task_idx += 1;
             }
-            Err(TaskExecutionError::NonZeroExitCode(code)) => {
-                if code == 127 {
-                    command_not_found(&project, explicit_environment);
+            Err(err) if err.exit_code().is_some() => {
+                let code = err.exit_code().unwrap_or(1);
+                let status = match err {
+                    TaskExecutionError::TimedOut { .. }
+                    | TaskExecutionError::ServiceNotReady { .. } => {
+                        eprintln!("{:?}", miette::Report::new(err));
+                        TaskStatus::TimedOut
+                    }
+                    _ => TaskStatus::Failed,
+                };
+                if !running.is_empty() {
+                    eprintln!(
+                        "Task '{}' failed, cancelling {} running task(s)",
+                        console::style(executable_task.name().unwrap_or("unnamed")).bold(),
+                        running.len()
+                    );
                 }
-                std::process::exit(code);
+                failed_exit_code = Some(code);
+                kill.cancel();
+                recorder.record(task_id, &executable_task, hash.as_ref(), status, |report| {
+                    report
+                        .with_exit_code(code)
+                        .with_timing(started_at, finished_at)
+                });
+                continue;
+            }
+            Err(err) => {
+                recorder.record(
+                    task_id,
+                    &executable_task,
+                    hash.as_ref(),
+                    TaskStatus::Failed,
+                    |report| report.with_timing(started_at, finished_at),
+                );
+                error.get_or_insert(err.into());
+                kill.cancel();
+                continue;
             }
-            Err(err) => return Err(err.into()),
         }
 
-        // Update the task cache with the new hash
+        recorder.record(
+            task_id,
+            &executable_task,
+            hash.as_ref(),
+            TaskStatus::Succeeded,
+            |report| report.with_timing(started_at, finished_at),
+        );
+
+        // A service keeps running until the tasks that depend on it have finished.
+        if services.contains(task_id) {
+            eprintln!(
+                "Service '{}' is ready ✅",
+                console::style(executable_task.name().unwrap_or("unnamed")).bold()
+            );
+        } else {
+            services.release(task_graph, task_id);
+        }
+
+        // Allow the tasks that depend on this task to be scheduled.
+        scheduler.complete(task_id);
+    }
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    Ok(match failed_exit_code {
+        Some(code) => TaskGraphOutcome::Failed(code),
+        None if cancel.is_cancelled() => TaskGraphOutcome::Cancelled,
+        None => TaskGraphOutcome::Succeeded,
+    })
+}
+
+/// The outcome of a task that was started by [`execute_tasks`].
+///
+/// The outcomes carry the hash of the task for the report, which is computed by the task itself
+/// so the files of the task are not hashed while scheduling.
+enum TaskOutcome {
+    /// The task was skipped because its condition is false.
+    ConditionNotMet(Option<TaskHash>),
+
+    /// The task was skipped because it was cached.
+    CacheHit(TaskHash),
+
+    /// The task was not started because the execution was killed in the meantime.
+    NotStarted,
+
+    /// The task was executed between the two points in time with the contained result.
+    Executed(
+        Result<(), TaskExecutionError>,
+        DateTime<Utc>,
+        DateTime<Utc>,
+        Option<TaskHash>,
+    ),
+}
+
+/// Starts a task that was scheduled by [`execute_tasks`]. The task is skipped if its condition is
+/// false or if it is cached, otherwise its environment is installed and it is executed. The task
+/// cache is updated when the task succeeds.
+async fn start_task<'p>(
+    executable_task: &ExecutableTask<'p>,
+    environments: &TaskEnvironments<'_, 'p>,
+    lock_file: &LockFile,
+    output_prefix: Option<&str>,
+    separate: bool,
+    is_recording: bool,
+    kill: &CancellationToken,
+) -> miette::Result<TaskOutcome> {
+    // Skip the task if its condition is not met. The condition is evaluated against the
+    // environment the task would run in, so it is only computed for tasks with a condition.
+    let mut task_env = None;
+    if executable_task.task().condition().is_some() {
+        let env = environments.get(executable_task).await?;
+        if !executable_task.condition_met(&env)? {
+            let hash = report_hash(executable_task, lock_file, is_recording).await;
+            return Ok(TaskOutcome::ConditionNotMet(hash));
+        }
+        task_env = Some(env);
+    }
+
+    print_task_header(executable_task, separate);
+
+    let task_cache = match executable_task
+        .can_skip(lock_file)
+        .await
+        .into_diagnostic()?
+    {
+        CanSkip::Yes(hash) => return Ok(TaskOutcome::CacheHit(hash)),
+        CanSkip::No(task_cache) => task_cache,
+    };
+    if kill.is_cancelled() {
+        return Ok(TaskOutcome::NotStarted);
+    }
+
+    // We lazily compute the task environment because we only need the environment if a task is
+    // actually executed.
+    let task_env = match task_env {
+        Some(task_env) => task_env,
+        None => environments.get(executable_task).await?,
+    };
+    if kill.is_cancelled() {
+        return Ok(TaskOutcome::NotStarted);
+    }
+
+    let started_at = Utc::now();
+    let result = execute_task(executable_task, &task_env, output_prefix, kill).await;
+    let finished_at = Utc::now();
+
+    // Update the task cache with the new hash, the results of tasks that were killed are not
+    // relevant.
+    let hash = if result.is_ok() && !kill.is_cancelled() {
         executable_task
-            .save_cache(&lock_file, task_cache)
+            .save_cache(lock_file, task_cache)
             .await
-            .into_diagnostic()?;
+            .into_diagnostic()?
+    } else {
+        None
+    };
+    let hash = match hash {
+        Some(hash) => Some(hash),
+        None => report_hash(executable_task, lock_file, is_recording).await,
+    };
+
+    Ok(TaskOutcome::Executed(result, started_at, finished_at, hash))
+}
+
+/// Starts a service that other tasks depend on, see [`execute_tasks`]. Once the environment of
+/// the service is known, it is sent through `env_sender` to start the service, after which this
+/// waits for the service to become ready.
+#[allow(clippy::too_many_arguments)]
+async fn start_service<'p>(
+    executable_task: &ExecutableTask<'p>,
+    service: &Service,
+    log_probe: Option<LogProbe>,
+    environments: &TaskEnvironments<'_, 'p>,
+    lock_file: &LockFile,
+    env_sender: tokio::sync::oneshot::Sender<HashMap<String, String>>,
+    separate: bool,
+    is_recording: bool,
+    kill: &CancellationToken,
+) -> miette::Result<TaskOutcome> {
+    let task_env = environments.get(executable_task).await?;
+    if !executable_task.condition_met(&task_env)? {
+        let hash = report_hash(executable_task, lock_file, is_recording).await;
+        return Ok(TaskOutcome::ConditionNotMet(hash));
+    }
+    let cwd = executable_task.working_directory()?;
+    if kill.is_cancelled() {
+        return Ok(TaskOutcome::NotStarted);
     }
 
-    Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
-    Ok(())
+    print_task_header(executable_task, separate);
+
+    let started_at = Utc::now();
+    let _ = env_sender.send(task_env.clone());
+    let ready = wait_until_ready(&service.ready, log_probe.as_ref(), &cwd, &task_env);
+    let result = tokio::select! {
+        ready = ready => match ready {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(TaskExecutionError::ServiceNotReady {
+                task: executable_task.name().unwrap_or("unnamed").to_string(),
+                timeout: service.ready.timeout.into(),
+            }),
+            Err(err) => Err(err.into()),
+        },
+        // The result is ignored when the tasks are killed.
+        _ = kill.cancelled() => Ok(()),
+    };
+    let finished_at = Utc::now();
+    let hash = report_hash(executable_task, lock_file, is_recording).await;
+    Ok(TaskOutcome::Executed(result, started_at, finished_at, hash))
 }
 
+/// Shows which command is being run if the level and type allows it. If `separate` is set, the
+/// output of the previous task is separated by an empty line.
+fn print_task_header(executable_task: &ExecutableTask<'_>, separate: bool) {
+    if !tracing::enabled!(Level::WARN) || executable_task.task().is_custom() {
+        return;
+    }
+    if separate {
+        eprintln!();
+    }
+    eprintln!(
+        "{}{}{}{}{}{}{}",
+        console::Emoji("✨ ", ""),
+        console::style("Pixi task (").bold(),
+        console::style(executable_task.name().unwrap_or("unnamed"))
+            .green()
+            .bold(),
+        // Only print environment if multiple environments are available
+        if executable_task.project().environments().len() > 1 {
+            format!(
+                " in {}",
+                executable_task.run_environment.name().fancy_display()
+            )
+        } else {
+            "".to_string()
+        },
+        console::style("): ").bold(),
+        executable_task.display_command(),
+        if let Some(description) = executable_task.task().description() {
+            console::style(format!(": ({})", description)).yellow()
+        } else {
+            console::style("".to_string()).yellow()
+        }
+    );
+}
+
+/// Computes the environment variables the tasks are executed with. The prefix of an environment
+/// is installed and activated the first time a task needs it. The running tasks share this, so
+/// every prefix is installed once while the other tasks keep running.
+struct TaskEnvironments<'a, 'p> {
+    state: tokio::sync::Mutex<(
+        &'a mut LockFileDerivedData<'p>,
+        &'a mut HashMap<Environment<'p>, HashMap<String, String>>,
+    )>,
+    options: &'a RunOptions,
+}
+
+impl<'a, 'p> TaskEnvironments<'a, 'p> {
+    fn new(
+        lock_file: &'a mut LockFileDerivedData<'p>,
+        task_envs: &'a mut HashMap<Environment<'p>, HashMap<String, String>>,
+        options: &'a RunOptions,
+    ) -> Self {
+        Self {
+            state: tokio::sync::Mutex::new((lock_file, task_envs)),
+            options,
+        }
+    }
+
+    /// Computes the environment variables a task is executed with: the activated environment of
+    /// the task extended with the variables of the `env-file`s of the task.
+    async fn get(
+        &self,
+        executable_task: &ExecutableTask<'p>,
+    ) -> miette::Result<HashMap<String, String>> {
+        let mut task_env = {
+            let mut state = self.state.lock().await;
+            let (lock_file, task_envs) = &mut *state;
+            match task_envs.entry(executable_task.run_environment.clone()) {
+                Entry::Occupied(env) => env.get().clone(),
+                Entry::Vacant(entry) => {
+                    // Ensure there is a valid prefix
+                    lock_file
+                        .prefix(
+                            &executable_task.run_environment,
+                            self.options.update_mode.clone(),
+                        )
+                        .await?;
+
+                    let command_env = get_task_env(
+                        &executable_task.run_environment,
+                        self.options.clean_env || executable_task.task().clean_env(),
+                    )
+                    .await?;
+                    entry.insert(command_env).clone()
+                }
+            }
+        };
+        executable_task.apply_env_files(&mut task_env)?;
+        Ok(task_env)
+    }
+}
+
+/// Records the tasks of a [`TaskGraph`] in a [`TaskRunReport`], if a report was requested. Every
+/// task is recorded at most once.
+struct TaskRecorder<'r> {
+    report: Option<&'r mut TaskRunReport>,
+    recorded: HashSet<TaskId>,
+}
+
+impl<'r> TaskRecorder<'r> {
+    fn new(report: Option<&'r mut TaskRunReport>) -> Self {
+        Self {
+            report,
+            recorded: HashSet::new(),
+        }
+    }
+
+    /// Returns whether the tasks are recorded in a report.
+    fn is_recording(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// Records a task with the given status and `hash`, unless it was recorded before. The
+    /// `finish` function adds the details of the run to the record, e.g. its timing.
+    fn record(
+        &mut self,
+        task_id: TaskId,
+        task: &ExecutableTask<'_>,
+        hash: Option<&TaskHash>,
+        status: TaskStatus,
+        finish: impl FnOnce(TaskReport) -> TaskReport,
+    ) {
+        let Some(report) = self.report.as_deref_mut() else {
+            return;
+        };
+        if self.recorded.insert(task_id) {
+            report
+                .tasks
+                .push(finish(TaskReport::new(task, hash, status)));
+        }
+    }
+
+    /// Records the executable tasks of the graph that were never started. Their hashes are
+    /// computed here because they were never computed by a running task.
+    async fn record_not_run(&mut self, task_graph: &TaskGraph<'_>, lock_file: &LockFile) {
+        if !self.is_recording() {
+            return;
+        }
+        for task_id in task_graph.topological_order() {
+            let executable_task = ExecutableTask::from_task_graph(task_graph, task_id);
+            if executable_task.task().is_executable() && !self.recorded.contains(&task_id) {
+                let hash = report_hash(&executable_task, lock_file, true).await;
+                self.record(
+                    task_id,
+                    &executable_task,
+                    hash.as_ref(),
+                    TaskStatus::NotRun,
+                    identity,
+                );
+            }
+        }
+    }
+}
+
+/// Computes the hash of a task for the report, if the tasks are recorded.
+async fn report_hash(
+    task: &ExecutableTask<'_>,
+    lock_file: &LockFile,
+    is_recording: bool,
+) -> Option<TaskHash> {
+    if !is_recording {
+        return None;
+    }
+    TaskHash::from_task(task, lock_file).await.ok().flatten()
+}
+
+/// Keeps track of the services that run in the background and of the tasks that still need them.
+#[derive(Default)]
+struct BackgroundServices {
+    /// The tokens that stop the services.
+    stop: HashMap<TaskId, CancellationToken>,
+
+    /// The tasks that depend on a service and have not finished yet.
+    dependents: HashMap<TaskId, HashSet<TaskId>>,
+}
+
+impl BackgroundServices {
+    /// Registers a service that is needed by the `dependents`. Returns the token that stops the
+    /// service, which is also cancelled when `kill` is cancelled.
+    fn start(
+        &mut self,
+        task_id: TaskId,
+        dependents: Vec<TaskId>,
+        kill: &CancellationToken,
+    ) -> CancellationToken {
+        let stop = kill.child_token();
+        self.stop.insert(task_id, stop.clone());
+        self.dependents
+            .insert(task_id, dependents.into_iter().collect());
+        stop
+    }
+
+    /// Returns true if the task is a service that runs in the background.
+    fn contains(&self, task_id: TaskId) -> bool {
+        self.stop.contains_key(&task_id)
+    }
+
+    /// Called when a task has finished, stops the services that are no longer needed.
+    fn release(&mut self, task_graph: &TaskGraph<'_>, task_id: TaskId) {
+        for dependency in &task_graph[task_id].dependencies {
+            let Some(dependents) = self.dependents.get_mut(dependency) else {
+                continue;
+            };
+            dependents.remove(&task_id);
+            if dependents.is_empty() {
+                self.stop[dependency].cancel();
+            }
+        }
+    }
+
+    /// Stops all the services.
+    fn stop_all(&self) {
+        for stop in self.stop.values() {
+            stop.cancel();
+        }
+    }
+}
+
+/// Executes the tasks in the graph and executes them again whenever one of the files matched by
+/// the `inputs` of the tasks changes. Tasks that are still running when a change is detected are
+/// killed before the tasks are restarted.
+///
+/// Tasks whose inputs did not change are skipped through the task cache. Pressing CTRL+C kills
+/// the running tasks and stops watching.
+async fn watch_task_graph<'p>(
+    task_graph: &TaskGraph<'p>,
+    lock_file: &mut LockFileDerivedData<'p>,
+    task_envs: &mut HashMap<Environment<'p>, HashMap<String, String>>,
+    options: &RunOptions,
+) -> miette::Result<()> {
+    let Some(mut watcher) = InputWatcher::from_task_graph(task_graph)
+        .await
+        .into_diagnostic()?
+    else {
+        return Err(NoInputsToWatchError.into());
+    };
+
+    loop {
+        let cancel = CancellationToken::new();
+        let changed = {
+            let execution =
+                execute_task_graph(task_graph, lock_file, task_envs, options, None, &cancel);
+            tokio::pin!(execution);
+
+            let changed = tokio::select! {
+                outcome = &mut execution => {
+                    if let TaskGraphOutcome::Failed(code) = outcome? {
+                        eprintln!(
+                            "{}Task failed with exit code {code}",
+                            console::style(console::Emoji("❌ ", "")).red(),
+                        );
+                    }
+                    None
+                }
+                changed = watcher.wait_for_changes() => Some(changed.into_diagnostic()?),
+                _ = tokio::signal::ctrl_c() => {
+                    // Kill the running tasks and stop watching.
+                    cancel.cancel();
+                    execution.await?;
+                    return Ok(());
+                }
+            };
+
+            // Kill the tasks that are still running and wait for them to exit.
+            if changed.is_some() {
+                cancel.cancel();
+                execution.await?;
+            }
+            changed
+        };
+
+        let changed = match changed {
+            Some(changed) => changed,
+            None => {
+                eprintln!(
+                    "{}Waiting for changes...",
+                    console::style(console::Emoji("👀 ", "")).bold(),
+                );
+                tokio::select! {
+                    changed = watcher.wait_for_changes() => changed.into_diagnostic()?,
+                    _ = tokio::signal::ctrl_c() => return Ok(()),
+                }
+            }
+        };
+
+        eprintln!(
+            "\n{}Detected changes in {}, restarting",
+            console::style(console::Emoji("🔄 ", "")).bold(),
+            changed.iter().map(|path| path.display()).format(", ")
+        );
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("none of the tasks define any inputs to watch")]
+#[diagnostic(help(
+    "add `inputs` to the task in the manifest, e.g. `inputs = [\"src/\"]`, to select the files that should be watched"
+))]
+struct NoInputsToWatchError;
+
 /// Called when a command was not found.
 fn command_not_found<'p>(project: &'p Project, explicit_environment: Option<Environment<'p>>) {
     let available_tasks: HashSet<TaskName> =
@@ -237,11 +1038,31 @@ fn command_not_found<'p>(project: &'p Project, explicit_environment: Option<Envi
     }
 }
 
+/// The exit code with which a task exits when it is killed because it exceeded its `timeout`.
+/// This is the same exit code as the one used by the `timeout` command.
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
 #[derive(Debug, Error, Diagnostic)]
 enum TaskExecutionError {
     #[error("the script exited with a non-zero exit code {0}")]
     NonZeroExitCode(i32),
 
+    #[error("the task '{task}' timed out after {timeout}")]
+    #[diagnostic(help("increase the `timeout` of the task if it needs more time"))]
+    TimedOut {
+        task: String,
+        timeout: humantime::Duration,
+    },
+
+    #[error("the service '{task}' did not become ready within {timeout}")]
+    #[diagnostic(help(
+        "check the output of the service or increase the `timeout` of its `ready` probe"
+    ))]
+    ServiceNotReady {
+        task: String,
+        timeout: humantime::Duration,
+    },
+
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
 
@@ -252,12 +1073,76 @@ enum TaskExecutionError {
     UnsupportedPlatformError(#[from] UnsupportedPlatformError),
 }
 
+impl TaskExecutionError {
+    /// Returns the exit code of the task if the task itself failed, as opposed to pixi failing
+    /// to execute the task.
+    fn exit_code(&self) -> Option<i32> {
+        match self {
+            TaskExecutionError::NonZeroExitCode(code) => Some(*code),
+            TaskExecutionError::TimedOut { .. } | TaskExecutionError::ServiceNotReady { .. } => {
+                Some(TIMEOUT_EXIT_CODE)
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Called to execute a single command.
 ///
-/// This function is called from [`execute`].
+/// If `output_prefix` is specified every line the task writes to stdout or stderr is prefixed
+/// with it. When the `kill` token is cancelled the running command is killed.
+///
+/// If the task defines `retries`, the command is executed again when it fails or times out,
+/// waiting for the backoff of the retry policy in between.
+///
+/// This function is called from [`execute_task_graph`].
 async fn execute_task<'p>(
     task: &ExecutableTask<'p>,
     command_env: &HashMap<String, String>,
+    output_prefix: Option<&str>,
+    kill: &CancellationToken,
+) -> Result<(), TaskExecutionError> {
+    let mut retry = 0;
+    loop {
+        let result = execute_task_once(task, command_env, output_prefix, kill, None).await;
+        let Some(retries) = task.task().retries() else {
+            return result;
+        };
+        match result {
+            Err(err) if err.exit_code().is_some() && retry < retries.count => {
+                if kill.is_cancelled() {
+                    return Err(err);
+                }
+                let backoff = retries.backoff_for(retry);
+                retry += 1;
+                eprintln!(
+                    "{}Task '{}' failed ({err}), retrying in {} (attempt {}/{})",
+                    console::style(console::Emoji("🔁 ", "")).yellow(),
+                    console::style(task.name().unwrap_or("unnamed")).bold(),
+                    humantime::format_duration(backoff),
+                    retry,
+                    retries.count
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = kill.cancelled() => return Err(err),
+                }
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Executes the command of a task once. If the task defines a `timeout`, the command is killed
+/// when it exceeds it.
+///
+/// If a `log_probe` is passed, every line of the prefixed output is checked by it.
+async fn execute_task_once<'p>(
+    task: &ExecutableTask<'p>,
+    command_env: &HashMap<String, String>,
+    output_prefix: Option<&str>,
+    kill: &CancellationToken,
+    log_probe: Option<&LogProbe>,
 ) -> Result<(), TaskExecutionError> {
     let Some(script) = task.as_deno_script()? else {
         return Ok(());
@@ -269,16 +1154,77 @@ async fn execute_task<'p>(
     // NOTE: one CTRL+C is registered it will always stay registered for the rest of the runtime of the program
     // which is fine when using run in isolation, however if we start to use run in conjunction with
     // some other command we might want to revaluate this.
-    let ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+    let mut ctrl_c = tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+
+    let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+
+    // Cancelling the token of the shell kills the running child processes, after which the
+    // execution finishes.
+    let shell_token = state.token().clone();
+    let kill_on_cancel = async {
+        kill.cancelled().await;
+        shell_token.cancel();
+        futures::future::pending::<()>().await
+    };
+
+    let execute_future = async {
+        match output_prefix {
+            None => {
+                execute_with_pipes(
+                    script,
+                    state,
+                    ShellPipeReader::stdin(),
+                    ShellPipeWriter::stdout(),
+                    ShellPipeWriter::stderr(),
+                )
+                .await
+            }
+            Some(prefix) => {
+                let (stdout, stdout_handle) =
+                    prefixed_output_writer(prefix, OutputStream::Stdout, log_probe.cloned());
+                let (stderr, stderr_handle) =
+                    prefixed_output_writer(prefix, OutputStream::Stderr, log_probe.cloned());
+                let code =
+                    execute_with_pipes(script, state, ShellPipeReader::stdin(), stdout, stderr)
+                        .await;
+
+                // Make sure all the output has been written before we continue.
+                let _ = stdout_handle.await;
+                let _ = stderr_handle.await;
+                code
+            }
+        }
+    };
+    tokio::pin!(execute_future);
+
+    // Resolves when the task exceeds its timeout.
+    let timeout = async {
+        match task.task().timeout() {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => futures::future::pending().await,
+        }
+    };
 
-    let execute_future =
-        deno_task_shell::execute(script, command_env.clone(), &cwd, Default::default());
     let status_code = tokio::select! {
-        code = execute_future => code,
-        // This should never exit
-        _ = ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
+        code = &mut execute_future => Ok(code),
+        _ = timeout => {
+            // Kill the running child processes and wait for them to exit.
+            shell_token.cancel();
+            execute_future.await;
+            Err(TaskExecutionError::TimedOut {
+                task: task.name().unwrap_or("unnamed").to_string(),
+                timeout: task.task().timeout().unwrap_or_default().into(),
+            })
+        }
+        // These should never exit
+        _ = kill_on_cancel => { unreachable!("killing the task should not complete") }
+        _ = &mut ctrl_c => { unreachable!("Ctrl+C should not be triggered") }
     };
 
+    // Stop ignoring CTRL+C now that the task is no longer running.
+    ctrl_c.abort();
+    let status_code = status_code?;
+
     if status_code != 0 {
         return Err(TaskExecutionError::NonZeroExitCode(status_code));
     }
@@ -286,6 +1232,60 @@ async fn execute_task<'p>(
     Ok(())
 }
 
+/// The stream to which the output of a task is forwarded.
+#[derive(Clone, Copy)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Creates a pipe that forwards everything written to it, line by line, to the given stream with
+/// every line prefixed by `prefix`. Every line is also passed to the `log_probe`, if any.
+fn prefixed_output_writer(
+    prefix: &str,
+    stream: OutputStream,
+    log_probe: Option<LogProbe>,
+) -> (ShellPipeWriter, JoinHandle<()>) {
+    let (mut reader, writer) = pipe();
+    let prefix = format!("{} ", console::style(format!("[{prefix}]")).cyan().bold());
+    let handle = tokio::task::spawn_blocking(move || {
+        let write_line = |line: &[u8]| {
+            if let Some(log_probe) = &log_probe {
+                log_probe.check_line(line);
+            }
+            let mut line = [prefix.as_bytes(), line].concat();
+            if !line.ends_with(b"\n") {
+                line.push(b'\n');
+            }
+            // Write the line at once to make sure lines of concurrent tasks are not interleaved.
+            let _ = match stream {
+                OutputStream::Stdout => std::io::stdout().lock().write_all(&line),
+                OutputStream::Stderr => std::io::stderr().lock().write_all(&line),
+            };
+        };
+
+        let mut pending = Vec::new();
+        let mut buffer = [0; 512];
+        loop {
+            let size = match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => size,
+            };
+            pending.extend_from_slice(&buffer[..size]);
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                write_line(&pending[..=end]);
+                pending.drain(..=end);
+            }
+        }
+
+        // Flush the remainder of the output that was not terminated by a newline.
+        if !pending.is_empty() {
+            write_line(&pending);
+        }
+    });
+    (writer, handle)
+}
+
 /// Called to disambiguate between environments to run a task in.
 fn disambiguate_task_interactive<'p>(
     problem: &AmbiguousTask<'p>,
@@ -7,7 +7,7 @@ use std::str::FromStr;
 use clap::Parser;
 use itertools::Itertools;
 use miette::IntoDiagnostic;
-use pixi_config::default_channel_config;
+use pixi_config::{default_channel_config, ConfigCli};
 use pixi_progress::await_in_progress;
 use pixi_utils::reqwest::build_reqwest_clients;
 use rattler_conda_types::MatchSpec;
@@ -39,6 +39,9 @@ pub struct Args {
     #[clap(flatten)]
     pub project_config: ProjectConfig,
 
+    #[clap(flatten)]
+    pub config: ConfigCli,
+
     /// The platform to search for, defaults to current platform
     #[arg(short, long, default_value_t = Platform::current())]
     pub platform: Platform,
@@ -102,7 +105,9 @@ where
 
 pub async fn execute_impl(args: Args) -> miette::Result<Option<Vec<RepoDataRecord>>> {
     let stdout = io::stdout();
-    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref()).ok();
+    let project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())
+        .ok()
+        .map(|project| project.with_cli_config(args.config.clone()));
 
     // Resolve channels from project / CLI args
     let channels = args.channels.resolve_from_project(project.as_ref())?;
@@ -113,12 +118,14 @@ pub async fn execute_impl(args: Args) -> miette::Result<Option<Vec<RepoDataRecor
 
     let package_name_filter = args.package;
 
+    let config = project
+        .as_ref()
+        .map(|p| p.config().clone())
+        .unwrap_or_else(|| Config::with_cli_config(&args.config));
     let client = project
         .as_ref()
         .map(|p| p.authenticated_client().clone())
-        .unwrap_or_else(|| build_reqwest_clients(None).1);
-
-    let config = Config::load_global();
+        .unwrap_or_else(|| build_reqwest_clients(Some(&config)).1);
 
     // Fetch the all names from the repodata using gateway
     let gateway = config.gateway(client.clone());
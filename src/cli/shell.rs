@@ -248,7 +248,10 @@ pub async fn execute(args: Args) -> miette::Result<()> {
 
     // Get the environment variables we need to set activate the environment in the shell.
     let env = project
-        .get_activated_environment_variables(&environment, CurrentEnvVarBehavior::Exclude)
+        .get_activated_environment_variables_with_env_files(
+            &environment,
+            CurrentEnvVarBehavior::Exclude,
+        )
         .await?;
 
     tracing::debug!("Pixi environment activation:\n{:?}", env);
@@ -13,9 +13,9 @@ use serde_json;
 use crate::activation::CurrentEnvVarBehavior;
 use crate::environment::update_prefix;
 use crate::{
-    activation::get_activator,
+    activation::{get_activator, run_activation},
     cli::cli_config::{PrefixUpdateConfig, ProjectConfig},
-    project::{Environment, HasProjectRef},
+    project::Environment,
     Project,
 };
 
@@ -65,7 +65,9 @@ async fn generate_activation_script(
             .unwrap_or_else(|| ShellEnum::from_env().unwrap_or_default())
     });
 
-    let activator = get_activator(environment, shell).into_diagnostic()?;
+    // The variables of the env-files are left out, the hook is often written to rc files or CI
+    // logs and the env-files usually contain secrets.
+    let activator = get_activator(environment, shell, None)?;
 
     let path = std::env::var("PATH")
         .ok()
@@ -86,15 +88,14 @@ async fn generate_activation_script(
 }
 
 /// Generates a JSON object describing the changes to the shell environment when
-/// activating the provided pixi environment.
+/// activating the provided pixi environment. Like the activation script, it
+/// leaves out the variables of the env-files.
 async fn generate_environment_json(environment: &Environment<'_>) -> miette::Result<String> {
-    let environment_variables = environment
-        .project()
-        .get_activated_environment_variables(environment, CurrentEnvVarBehavior::Exclude)
-        .await?;
+    let environment_variables =
+        run_activation(environment, &CurrentEnvVarBehavior::Exclude, false).await?;
 
     let shell_env = ShellEnv {
-        environment_variables,
+        environment_variables: &environment_variables,
     };
 
     serde_json::to_string(&shell_env).into_diagnostic()
@@ -186,4 +187,32 @@ mod tests {
         assert!(script.contains(&format!("$env.{path_var_name} = ")));
         assert!(script.contains("$env.CONDA_PREFIX = "));
     }
+
+    #[tokio::test]
+    async fn test_shell_hook_excludes_env_files() {
+        let root = tempfile::tempdir().unwrap();
+        fs_err::write(root.path().join(".env"), "SECRET=hunter2\n").unwrap();
+        let project = Project::from_str(
+            &root.path().join("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = []
+        platforms = ["linux-64", "osx-64", "osx-arm64", "win-64"]
+
+        [activation]
+        env = { GREETING = "hello" }
+        env-file = [".env"]
+        "#,
+        )
+        .unwrap();
+
+        let script =
+            generate_activation_script(Some(ShellEnum::Bash(Bash)), &project.default_environment())
+                .await
+                .unwrap();
+        assert!(script.contains("GREETING"));
+        assert!(!script.contains("SECRET"));
+        assert!(!script.contains("hunter2"));
+    }
 }
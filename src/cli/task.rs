@@ -1,17 +1,22 @@
 use crate::cli::cli_config::ProjectConfig;
+use crate::lock_file::load_lock_file;
 use crate::project::virtual_packages::verify_current_platform_has_required_virtual_packages;
 use crate::project::Environment;
+use crate::task::{ExecutableTask, SearchEnvironments, TaskGraph, TaskId};
 use crate::Project;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use fancy_display::FancyDisplay;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use pixi_manifest::task::{quote, Alias, CmdArgs, Execute, Task, TaskName};
+use miette::IntoDiagnostic;
+use pixi_manifest::task::{quote, Alias, CmdArgs, Dependency, Execute, Task, TaskName};
 use pixi_manifest::EnvironmentName;
 use pixi_manifest::FeatureName;
 use rattler_conda_types::Platform;
+use serde::Serialize;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::error::Error;
+use std::fmt::Write;
 use std::io;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -33,6 +38,9 @@ pub enum Operation {
     /// List all tasks in the project
     #[clap(visible_alias = "ls", alias = "l")]
     List(ListArgs),
+
+    /// Show the graph of tasks that `pixi run` would execute for a task
+    Graph(GraphArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -137,9 +145,41 @@ pub struct ListArgs {
     pub environment: Option<String>,
 }
 
+#[derive(Parser, Debug, Clone)]
+#[clap(trailing_var_arg = true, arg_required_else_help = true)]
+pub struct GraphArgs {
+    /// The task, and the arguments passed to it, to show the graph of
+    #[clap(required = true, num_args = 1..)]
+    pub task: Vec<String>,
+
+    /// The environment to resolve the task in
+    #[arg(long, short)]
+    pub environment: Option<String>,
+
+    /// The format in which the graph is printed
+    #[arg(long, value_enum, default_value_t = GraphFormat::Tree)]
+    pub format: GraphFormat,
+}
+
+/// The format in which `pixi task graph` prints the graph.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A tree of the tasks and the tasks they depend on
+    Tree,
+    /// The Graphviz DOT language
+    Dot,
+    /// A JSON list of the tasks
+    Json,
+}
+
 impl From<AddArgs> for Task {
     fn from(value: AddArgs) -> Self {
-        let depends_on = value.depends_on.unwrap_or_default();
+        let depends_on = value
+            .depends_on
+            .unwrap_or_default()
+            .into_iter()
+            .map(Dependency::from)
+            .collect_vec();
         // description or none
         let description = value.description;
 
@@ -184,12 +224,17 @@ impl From<AddArgs> for Task {
             Self::Execute(Execute {
                 cmd: CmdArgs::Single(cmd_args),
                 depends_on,
+                args: Vec::new(),
                 inputs: None,
                 outputs: None,
                 cwd,
                 env,
+                env_file: None,
                 description,
                 clean_env,
+                condition: None,
+                timeout: None,
+                retries: None,
             })
         }
     }
@@ -198,7 +243,7 @@ impl From<AddArgs> for Task {
 impl From<AliasArgs> for Task {
     fn from(value: AliasArgs) -> Self {
         Self::Alias(Alias {
-            depends_on: value.depends_on,
+            depends_on: value.depends_on.into_iter().map(Dependency::from).collect(),
             description: value.description,
         })
     }
@@ -267,7 +312,134 @@ fn list_tasks(
     Ok(())
 }
 
-pub fn execute(args: Args) -> miette::Result<()> {
+/// A task in the graph printed by `pixi task graph`.
+#[derive(Debug, Serialize)]
+struct GraphNode {
+    /// The index of the task in the list of nodes.
+    id: usize,
+    name: Option<String>,
+    environment: String,
+    command: Option<String>,
+    /// Whether the task would be skipped because its inputs and outputs did not change.
+    cached: bool,
+    /// The indices of the tasks this task depends on.
+    depends_on: Vec<usize>,
+}
+
+/// Collects the nodes of the task graph, in the order in which the tasks would be executed.
+async fn graph_nodes(
+    task_graph: &TaskGraph<'_>,
+    lock_file: &rattler_lock::LockFile,
+) -> miette::Result<Vec<GraphNode>> {
+    let order = task_graph.topological_order();
+    let index: HashMap<TaskId, usize> = order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut nodes = Vec::with_capacity(order.len());
+    for (id, &task_id) in order.iter().enumerate() {
+        let executable_task = ExecutableTask::from_task_graph(task_graph, task_id);
+        nodes.push(GraphNode {
+            id,
+            name: executable_task.name().map(ToString::to_string),
+            environment: executable_task.run_environment.name().to_string(),
+            command: executable_task.full_command()?,
+            cached: executable_task
+                .is_cached(lock_file)
+                .await
+                .into_diagnostic()?,
+            depends_on: task_graph[task_id]
+                .dependencies
+                .iter()
+                .map(|dependency| index[dependency])
+                .collect(),
+        });
+    }
+    Ok(nodes)
+}
+
+/// Renders the graph as a tree, starting from the tasks that no other task depends on.
+fn render_tree(nodes: &[GraphNode]) -> String {
+    fn label(node: &GraphNode) -> String {
+        let mut label = format!(
+            "{} ({})",
+            node.name.as_deref().unwrap_or("unnamed"),
+            node.environment
+        );
+        if let Some(command) = &node.command {
+            let _ = write!(label, ": {command}");
+        }
+        if node.cached {
+            label.push_str(" [cached]");
+        }
+        label
+    }
+
+    fn render_children(
+        out: &mut String,
+        nodes: &[GraphNode],
+        node: &GraphNode,
+        prefix: &str,
+        ancestors: &mut Vec<usize>,
+    ) {
+        for (idx, &dependency) in node.depends_on.iter().enumerate() {
+            let last = idx == node.depends_on.len() - 1;
+            let (symbol, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let child = &nodes[dependency];
+            if ancestors.contains(&dependency) {
+                let _ = writeln!(out, "{prefix}{symbol}{} (cycle)", label(child));
+                continue;
+            }
+            let _ = writeln!(out, "{prefix}{symbol}{}", label(child));
+            ancestors.push(dependency);
+            render_children(out, nodes, child, &format!("{prefix}{indent}"), ancestors);
+            ancestors.pop();
+        }
+    }
+
+    let mut out = String::new();
+    let roots = nodes.iter().filter(|node| {
+        !nodes
+            .iter()
+            .any(|other| other.depends_on.contains(&node.id))
+    });
+    for root in roots {
+        let _ = writeln!(out, "{}", label(root));
+        render_children(&mut out, nodes, root, "", &mut vec![root.id]);
+    }
+    out
+}
+
+/// Renders the graph in the Graphviz DOT language. Edges point from a task to the tasks it
+/// depends on, cached tasks are drawn dashed.
+fn render_dot(nodes: &[GraphNode]) -> String {
+    let escape = |value: &str| value.replace('\\', "\\\\").replace('"', "\\\"");
+
+    let mut out = String::from("digraph tasks {\n");
+    for node in nodes {
+        let mut label = format!(
+            "{}\\n({})",
+            escape(node.name.as_deref().unwrap_or("unnamed")),
+            escape(&node.environment)
+        );
+        if let Some(command) = &node.command {
+            let _ = write!(label, "\\n{}", escape(command));
+        }
+        let style = if node.cached { ", style=dashed" } else { "" };
+        let _ = writeln!(out, "    n{} [label=\"{label}\"{style}];", node.id);
+    }
+    for node in nodes {
+        for dependency in &node.depends_on {
+            let _ = writeln!(out, "    n{} -> n{dependency};", node.id);
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+pub async fn execute(args: Args) -> miette::Result<()> {
     let mut project = Project::load_or_else_discover(args.project_config.manifest_path.as_deref())?;
     match args.operation {
         Operation::Add(args) => {
@@ -434,8 +606,89 @@ pub fn execute(args: Args) -> miette::Result<()> {
 
             list_tasks(tasks_per_env, args.summary).expect("io error when printing tasks");
         }
+        Operation::Graph(args) => {
+            let environment = project.environment_from_name_or_env_var(args.environment.clone())?;
+            let explicit_environment = if args.environment.is_none() && environment.is_default() {
+                None
+            } else {
+                Some(environment.clone())
+            };
+
+            // Use the lock-file as it is, showing the graph should not update it.
+            let lock_file = load_lock_file(&project).await?;
+
+            let search_environment = SearchEnvironments::from_opt_env(
+                &project,
+                explicit_environment,
+                Some(environment.best_platform()),
+            );
+            let task_graph = TaskGraph::from_cmd_args(&project, &search_environment, args.task)?;
+            let nodes = graph_nodes(&task_graph, &lock_file).await?;
+
+            match args.format {
+                GraphFormat::Tree => print!("{}", render_tree(&nodes)),
+                GraphFormat::Dot => print!("{}", render_dot(&nodes)),
+                GraphFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&nodes).into_diagnostic()?
+                ),
+            }
+        }
     };
 
     Project::warn_on_discovered_from_env(args.project_config.manifest_path.as_deref());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes() -> Vec<GraphNode> {
+        let node = |id: usize, name: &str, cached: bool, depends_on: Vec<usize>| GraphNode {
+            id,
+            name: Some(name.to_string()),
+            environment: "default".to_string(),
+            command: Some(format!("echo {name}")),
+            cached,
+            depends_on,
+        };
+        vec![
+            node(0, "install", true, vec![]),
+            node(1, "build", false, vec![0]),
+            node(2, "lint", false, vec![0]),
+            node(3, "test", false, vec![1, 2]),
+        ]
+    }
+
+    #[test]
+    fn test_render_tree() {
+        assert_eq!(
+            render_tree(&nodes()),
+            "test (default): echo test
+├── build (default): echo build
+│   └── install (default): echo install [cached]
+└── lint (default): echo lint
+    └── install (default): echo install [cached]
+"
+        );
+    }
+
+    #[test]
+    fn test_render_dot() {
+        assert_eq!(
+            render_dot(&nodes()),
+            r#"digraph tasks {
+    n0 [label="install\n(default)\necho install", style=dashed];
+    n1 [label="build\n(default)\necho build"];
+    n2 [label="lint\n(default)\necho lint"];
+    n3 [label="test\n(default)\necho test"];
+    n1 -> n0;
+    n2 -> n0;
+    n3 -> n1;
+    n3 -> n2;
+}
+"#
+        );
+    }
+}
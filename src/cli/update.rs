@@ -16,7 +16,7 @@ use itertools::Itertools;
 use miette::{Context, IntoDiagnostic, MietteDiagnostic};
 use pixi_config::ConfigCli;
 use pixi_consts::consts;
-use pixi_manifest::EnvironmentName;
+use pixi_manifest::{EnvironmentName, ExcludeNewer};
 use rattler_conda_types::Platform;
 use rattler_lock::{LockFile, Package};
 
@@ -44,6 +44,11 @@ pub struct Args {
     /// Output the changes in JSON format.
     #[clap(long)]
     pub json: bool,
+
+    /// Exclude packages that were published after this date, e.g.
+    /// `2024-06-01`. Overrides the `exclude-newer` of the manifest.
+    #[clap(long)]
+    pub exclude_newer: Option<ExcludeNewer>,
 }
 
 #[derive(Parser, Debug, Default)]
@@ -159,6 +164,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
     let updated_lock_file = UpdateContext::builder(&project)
         .with_lock_file(relaxed_lock_file.clone())
         .with_no_install(args.no_install)
+        .with_exclude_newer(args.exclude_newer)
         .finish()?
         .update()
         .await?;
@@ -132,7 +132,7 @@ pub async fn execute(args: Args) -> miette::Result<()> {
         })
         .collect();
 
-    let pypi_deps = pypi_deps_iter
+    let (index_pinned, pypi_deps): (Vec<_>, Vec<_>) = pypi_deps_iter
         // Don't upgrade excluded packages
         .filter(|(name, _)| match &args.specs.exclude {
             None => true,
@@ -145,14 +145,31 @@ pub async fn execute(args: Args) -> miette::Result<()> {
             Some(packages) if packages.contains(&name.as_normalized().to_string()) => true,
             _ => false,
         })
+        // A pep508 requirement cannot express the index a package is pinned to,
+        // upgrading it would drop the index from the manifest.
+        .partition(|(_, req)| matches!(req, PyPiRequirement::Version { index: Some(_), .. }));
+    if !index_pinned.is_empty() {
+        tracing::warn!(
+            "skipping the upgrade of {}, packages pinned to an index cannot be upgraded yet",
+            index_pinned
+                .iter()
+                .map(|(name, _)| console::style(name.as_source()).bold())
+                .format(", ")
+        );
+    }
+
+    let pypi_deps = pypi_deps
+        .into_iter()
         // Only upgrade version specs
         .filter_map(|(name, req)| match req {
-            PyPiRequirement::Version { extras, .. } => Some((
+            PyPiRequirement::Version {
+                extras, markers, ..
+            } => Some((
                 name.clone(),
                 Requirement {
                     name: name.as_normalized().clone(),
                     extras,
-                    marker: MarkerTree::default(),
+                    marker: markers,
                     origin: None,
                     version_or_url: None,
                 },
@@ -0,0 +1,339 @@
+//! Implements loading of the dotenv files referenced by `env-file` in tasks and in the
+//! `[activation]` table.
+//!
+//! Every line of a dotenv file is either empty, a comment starting with `#` or an assignment
+//! `KEY=VALUE`, optionally prefixed with `export`. Values can be:
+//!
+//! - unquoted, in which case a trailing ` # comment` is removed,
+//! - single quoted, in which case the value is taken literally,
+//! - double quoted, in which case the escapes `\n`, `\t`, `\"`, `\\` and `\$` are supported.
+//!
+//! Quoted values can span multiple lines, the line breaks are part of the value.
+//!
+//! Unquoted and double quoted values expand `$NAME`, `${NAME}` and `${NAME:-default}`. Variables
+//! are looked up in the variables defined earlier in the files and then in a base environment.
+
+use std::{
+    collections::HashMap,
+    iter::Peekable,
+    path::{Path, PathBuf},
+    str::CharIndices,
+};
+
+use indexmap::IndexMap;
+use miette::Diagnostic;
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum DotenvError {
+    #[error("the env-file '{}' does not exist", .0.display())]
+    #[diagnostic(help("create the file or remove it from `env-file` in the manifest"))]
+    NotFound(PathBuf),
+
+    #[error("failed to read the env-file '{}'", .0.display())]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("invalid line {line} in the env-file '{}': {reason}", path.display())]
+    #[diagnostic(help("lines should look like `KEY=VALUE`, `KEY=\"VALUE\"` or `# comment`"))]
+    InvalidLine {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+}
+
+/// Loads the dotenv files at `paths`, relative to `root`, in order. Variables defined in later
+/// files override variables defined in earlier files. Variables that are not defined in the files
+/// are expanded from `base`.
+pub fn load_env_files<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    root: &Path,
+    base: &HashMap<String, String>,
+) -> Result<IndexMap<String, String>, DotenvError> {
+    let mut vars = IndexMap::new();
+    for path in paths {
+        let contents = match fs_err::read_to_string(root.join(path)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(DotenvError::NotFound(path.to_path_buf()))
+            }
+            Err(err) => return Err(DotenvError::Io(path.to_path_buf(), err)),
+        };
+        parse_dotenv(&contents, &mut vars, base).map_err(|(line, reason)| {
+            DotenvError::InvalidLine {
+                path: path.to_path_buf(),
+                line,
+                reason,
+            }
+        })?;
+    }
+    Ok(vars)
+}
+
+/// Parses the contents of a dotenv file and inserts the variables into `vars`. Returns the line
+/// number and the reason if a line is invalid.
+fn parse_dotenv(
+    contents: &str,
+    vars: &mut IndexMap<String, String>,
+    base: &HashMap<String, String>,
+) -> Result<(), (usize, String)> {
+    let mut lines = contents.lines().enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| (idx + 1, "expected `KEY=VALUE`".to_string()))?;
+        let key = key.trim();
+        if !is_valid_name(key) {
+            return Err((idx + 1, format!("invalid variable name `{key}`")));
+        }
+
+        // A quoted value continues on the next lines until its closing quote.
+        let mut value = value.trim_start().to_string();
+        while is_unterminated_quote(&value) {
+            let Some((_, next)) = lines.next() else {
+                break;
+            };
+            value.push('\n');
+            value.push_str(next);
+        }
+
+        let lookup = |name: &str| vars.get(name).or_else(|| base.get(name)).cloned();
+        let value = parse_value(&value, lookup).map_err(|reason| (idx + 1, reason))?;
+        vars.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Returns true if `value` starts with a quote that is not closed.
+fn is_unterminated_quote(value: &str) -> bool {
+    if let Some(value) = value.strip_prefix('\'') {
+        return !value.contains('\'');
+    }
+    let Some(value) = value.strip_prefix('"') else {
+        return false;
+    };
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return false,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Returns true if `name` is a valid name of an environment variable.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses the value of an assignment, expanding the variables it references.
+fn parse_value(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let (parsed, rest) = if let Some(value) = value.strip_prefix('\'') {
+        let end = value
+            .find('\'')
+            .ok_or_else(|| "unterminated single quoted value".to_string())?;
+        (value[..end].to_string(), &value[end + 1..])
+    } else if let Some(value) = value.strip_prefix('"') {
+        parse_double_quoted(value, &lookup)?
+    } else {
+        // Everything after a `#` that is preceded by whitespace is a comment.
+        let end = value
+            .char_indices()
+            .find(|&(idx, c)| c == '#' && value[..idx].ends_with(char::is_whitespace))
+            .map_or(value.len(), |(idx, _)| idx);
+        let value = value[..end].trim_end();
+        let mut parsed = String::new();
+        let mut chars = value.char_indices().peekable();
+        while let Some((_, c)) = chars.next() {
+            match c {
+                '$' => expand_variable(value, &mut chars, &mut parsed, &lookup)?,
+                c => parsed.push(c),
+            }
+        }
+        (parsed, "")
+    };
+
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected `{rest}` after the quoted value"));
+    }
+    Ok(parsed)
+}
+
+/// Parses a double quoted value, starting after the opening quote. Returns the value and the
+/// remainder of the line after the closing quote.
+fn parse_double_quoted<'a>(
+    value: &'a str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(String, &'a str), String> {
+    let mut parsed = String::new();
+    let mut chars = value.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Ok((parsed, &value[idx + 1..])),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => parsed.push('\n'),
+                Some((_, 't')) => parsed.push('\t'),
+                Some((_, c)) => parsed.push(c),
+                None => break,
+            },
+            '$' => expand_variable(value, &mut chars, &mut parsed, lookup)?,
+            c => parsed.push(c),
+        }
+    }
+    Err("unterminated double quoted value".to_string())
+}
+
+/// Expands the variable reference that follows a `$` and appends its value to `out`. A `$` that
+/// is not followed by a variable name is kept as is.
+fn expand_variable(
+    value: &str,
+    chars: &mut Peekable<CharIndices<'_>>,
+    out: &mut String,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    if let Some(&(start, '{')) = chars.peek() {
+        let end = value[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| "unterminated `${`".to_string())?;
+        while chars.next_if(|&(idx, _)| idx <= end).is_some() {}
+
+        let expression = &value[start + 1..end];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if !is_valid_name(name) {
+            return Err(format!("invalid variable name `{name}`"));
+        }
+        match (lookup(name).filter(|value| !value.is_empty()), default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {}
+        }
+    } else {
+        let mut name = String::new();
+        while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_') {
+            name.push(c);
+        }
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(&lookup(&name).unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let contents = r#"
+# A comment
+export DATABASE_HOST=localhost
+DATABASE_PORT = 5432 # the default port
+DATABASE_URL="postgres://${DATABASE_HOST}:$DATABASE_PORT/app"
+PASSWORD='pa$$word # not a comment'
+GREETING="hello\n\"world\" \$HOME"
+FROM_BASE=$HOME/data
+DEFAULT=${UNDEFINED:-fallback}
+EMPTY=
+"#;
+        let base = HashMap::from([("HOME".to_string(), "/home/user".to_string())]);
+        let mut vars = IndexMap::new();
+        parse_dotenv(contents, &mut vars, &base).unwrap();
+
+        assert_eq!(
+            vars,
+            IndexMap::from([
+                ("DATABASE_HOST".to_string(), "localhost".to_string()),
+                ("DATABASE_PORT".to_string(), "5432".to_string()),
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost:5432/app".to_string()
+                ),
+                (
+                    "PASSWORD".to_string(),
+                    "pa$$word # not a comment".to_string()
+                ),
+                ("GREETING".to_string(), "hello\n\"world\" $HOME".to_string()),
+                ("FROM_BASE".to_string(), "/home/user/data".to_string()),
+                ("DEFAULT".to_string(), "fallback".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline_values() {
+        let contents =
+            "CERT=\"-----BEGIN-----\n$HOST\n-----END-----\"\nRAW='first\n  second'\nNEXT=value\n";
+        let base = HashMap::from([("HOST".to_string(), "example.com".to_string())]);
+        let mut vars = IndexMap::new();
+        parse_dotenv(contents, &mut vars, &base).unwrap();
+
+        assert_eq!(
+            vars,
+            IndexMap::from([
+                (
+                    "CERT".to_string(),
+                    "-----BEGIN-----\nexample.com\n-----END-----".to_string()
+                ),
+                ("RAW".to_string(), "first\n  second".to_string()),
+                ("NEXT".to_string(), "value".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let parse = |contents: &str| {
+            parse_dotenv(contents, &mut IndexMap::new(), &HashMap::new()).unwrap_err()
+        };
+        assert_eq!(parse("FOO=bar\nBAR").0, 2);
+        assert_eq!(parse("1FOO=bar").0, 1);
+        assert_eq!(parse("FOO=\"bar").0, 1);
+        assert_eq!(parse("FOO='bar' baz").0, 1);
+        assert_eq!(parse("FOO=bar\nBAR=\"baz\nqux").0, 2);
+    }
+
+    #[test]
+    fn test_load_env_files() {
+        let root = tempfile::tempdir().unwrap();
+        fs_err::write(root.path().join(".env"), "HOST=localhost\nPORT=80\n").unwrap();
+        fs_err::write(
+            root.path().join(".env.local"),
+            "PORT=8080\nURL=$HOST:$PORT\n",
+        )
+        .unwrap();
+
+        let vars = load_env_files(
+            [Path::new(".env"), Path::new(".env.local")],
+            root.path(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["URL"], "localhost:8080");
+
+        assert!(matches!(
+            load_env_files([Path::new(".env.missing")], root.path(), &HashMap::new()),
+            Err(DotenvError::NotFound(_))
+        ));
+    }
+}
@@ -25,7 +25,7 @@ use rattler_lock::{
 use url::Url;
 use uv_auth::store_credentials_from_url;
 use uv_cache::{ArchiveTarget, ArchiveTimestamp, Cache};
-use uv_client::{Connectivity, FlatIndexClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::{DistributionDatabase, RegistryWheelIndex};
@@ -645,7 +645,7 @@ pub async fn update_python_distributions(
             .client(uv_context.client.clone())
             .index_urls(index_locations.index_urls())
             .keyring(uv_context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(uv_context.connectivity)
             .build(),
     );
 
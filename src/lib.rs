@@ -2,11 +2,13 @@ pub mod activation;
 pub mod cli;
 pub(crate) mod conda_pypi_clobber;
 mod diff;
+mod dotenv;
 pub mod environment;
 mod global;
 mod install_pypi;
 mod install_wheel;
 pub mod lock_file;
+mod package_layout;
 mod prefix;
 mod project;
 mod prompt;
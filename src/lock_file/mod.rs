@@ -3,11 +3,12 @@ mod package_identifier;
 mod records_by_name;
 mod resolve;
 mod satisfiability;
+mod stale;
 mod update;
 mod utils;
 
 use miette::{IntoDiagnostic, WrapErr};
-pub(crate) use outdated::OutdatedEnvironments;
+pub(crate) use outdated::{OutdatedEnvironments, OutdatedReason};
 pub(crate) use package_identifier::PypiPackageIdentifier;
 use rattler_conda_types::RepoDataRecord;
 use rattler_lock::{LockFile, PypiPackageData, PypiPackageEnvironmentData};
@@ -19,9 +20,10 @@ pub use satisfiability::{
     verify_environment_satisfiability, verify_platform_satisfiability, EnvironmentUnsat,
     PlatformUnsat,
 };
+pub(crate) use stale::{prune_lock_file, StaleContent};
 pub(crate) use update::{LockFileDerivedData, UpdateContext};
 pub use update::{UpdateLockFileOptions, UpdateMode};
-pub(crate) use utils::filter_lock_file;
+pub(crate) use utils::{filter_lock_file, IoConcurrencyLimit};
 
 use crate::Project;
 
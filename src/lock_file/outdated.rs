@@ -1,5 +1,5 @@
 use super::{verify_environment_satisfiability, verify_platform_satisfiability};
-use crate::lock_file::satisfiability::EnvironmentUnsat;
+use crate::lock_file::satisfiability::{EnvironmentUnsat, PlatformUnsat};
 use crate::{project::Environment, project::SolveGroup, Project};
 use fancy_display::FancyDisplay;
 use itertools::Itertools;
@@ -24,6 +24,22 @@ pub struct OutdatedEnvironments<'p> {
     /// Records the environments for which the lock-file content should also be discarded. This is
     /// the case for instance when the order of the channels changed.
     pub disregard_locked_content: DisregardLockedContent<'p>,
+
+    /// The reasons why environments and platforms are considered out of date.
+    pub reasons: Vec<OutdatedReason<'p>>,
+}
+
+/// Describes why (part of) an environment is considered out of date with the lock-file.
+#[derive(Debug)]
+pub enum OutdatedReason<'p> {
+    /// The environment does not exist in the lock-file.
+    MissingEnvironment(Environment<'p>),
+
+    /// The locked environment does not match the environment in the project.
+    Environment(Environment<'p>, EnvironmentUnsat),
+
+    /// The locked packages of a platform do not satisfy the requirements of the project.
+    Platform(Environment<'p>, Platform, PlatformUnsat),
 }
 
 /// A struct that stores whether the locked content of certain environments
@@ -55,6 +71,7 @@ impl<'p> OutdatedEnvironments<'p> {
         let mut outdated_conda: HashMap<_, HashSet<_>> = HashMap::new();
         let mut outdated_pypi: HashMap<_, HashSet<_>> = HashMap::new();
         let mut disregard_locked_content = DisregardLockedContent::default();
+        let mut reasons = Vec::new();
 
         // Find all targets that are not satisfied by the lock-file
         find_unsatisfiable_targets(
@@ -63,6 +80,7 @@ impl<'p> OutdatedEnvironments<'p> {
             &mut outdated_conda,
             &mut outdated_pypi,
             &mut disregard_locked_content,
+            &mut reasons,
         );
 
         // Extend the outdated targets to include the solve groups
@@ -109,6 +127,7 @@ impl<'p> OutdatedEnvironments<'p> {
             conda: outdated_conda,
             pypi: outdated_pypi,
             disregard_locked_content,
+            reasons,
         }
     }
 
@@ -127,6 +146,7 @@ fn find_unsatisfiable_targets<'p>(
     outdated_conda: &mut HashMap<Environment<'p>, HashSet<Platform>>,
     outdated_pypi: &mut HashMap<Environment<'p>, HashSet<Platform>>,
     disregard_locked_content: &mut DisregardLockedContent<'p>,
+    reasons: &mut Vec<OutdatedReason<'p>>,
 ) {
     for environment in project.environments() {
         let platforms = environment.platforms();
@@ -142,6 +162,7 @@ fn find_unsatisfiable_targets<'p>(
                 .entry(environment.clone())
                 .or_default()
                 .extend(platforms);
+            reasons.push(OutdatedReason::MissingEnvironment(environment.clone()));
 
             continue;
         };
@@ -168,7 +189,22 @@ fn find_unsatisfiable_targets<'p>(
                     // If the indexes mismatched we also cannot trust any of the locked content.
                     disregard_locked_content.pypi.insert(environment.clone());
                 }
+
+                EnvironmentUnsat::ExcludeNewerMismatch { .. } => {
+                    // The locked packages were selected without respecting the exclude-newer
+                    // date, they should not be preferred when solving again.
+                    disregard_locked_content.conda.insert(environment.clone());
+                    disregard_locked_content.pypi.insert(environment.clone());
+                }
+
+                EnvironmentUnsat::SolveStrategyMismatch(_) => {
+                    // The locked packages were selected with a different strategy, they should
+                    // not be preferred when solving again.
+                    disregard_locked_content.conda.insert(environment.clone());
+                    disregard_locked_content.pypi.insert(environment.clone());
+                }
             }
+            reasons.push(OutdatedReason::Environment(environment.clone(), unsat));
 
             continue;
         }
@@ -192,6 +228,11 @@ fn find_unsatisfiable_targets<'p>(
                         .entry(environment.clone())
                         .or_default()
                         .insert(platform);
+                    reasons.push(OutdatedReason::Platform(
+                        environment.clone(),
+                        platform,
+                        unsat,
+                    ));
                 }
                 Err(unsat) => {
                     tracing::info!(
@@ -203,6 +244,11 @@ fn find_unsatisfiable_targets<'p>(
                         .entry(environment.clone())
                         .or_default()
                         .insert(platform);
+                    reasons.push(OutdatedReason::Platform(
+                        environment.clone(),
+                        platform,
+                        unsat,
+                    ));
                 }
             }
         }
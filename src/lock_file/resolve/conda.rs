@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use miette::IntoDiagnostic;
-use rattler_conda_types::{GenericVirtualPackage, MatchSpec, RepoDataRecord};
+use pixi_manifest::{ExcludeNewer, SolveStrategy};
+use rattler_conda_types::{Channel, GenericVirtualPackage, MatchSpec, PackageName, RepoDataRecord};
 use rattler_repodata_gateway::RepoData;
 use rattler_solve::{resolvo, ChannelPriority, SolverImpl};
 
@@ -8,21 +11,47 @@ use crate::lock_file::LockedCondaPackages;
 /// Solves the conda package environment for the given input. This function is
 /// async because it spawns a background task for the solver. Since solving is a
 /// CPU intensive task we do not want to block the main task.
+#[allow(clippy::too_many_arguments)]
 pub async fn resolve_conda(
     specs: Vec<MatchSpec>,
+    constraints: Vec<MatchSpec>,
     virtual_packages: Vec<GenericVirtualPackage>,
     locked_packages: Vec<RepoDataRecord>,
     available_packages: Vec<RepoData>,
     channel_priority: ChannelPriority,
+    exclude_newer: Option<ExcludeNewer>,
+    solve_strategy: SolveStrategy,
 ) -> miette::Result<LockedCondaPackages> {
     tokio::task::spawn_blocking(move || {
+        // Packages that are pinned to a specific channel may only ever be taken
+        // from that channel, regardless of the channel priority. Remove all
+        // records of these packages that originate from other channels.
+        let pinned_channels = pinned_channels(specs.iter().chain(constraints.iter()));
+        let available_packages = available_packages
+            .iter()
+            .map(|repo_data| {
+                resolvo::RepoData::from_iter(repo_data.iter().filter(|record| {
+                    pinned_channels
+                        .get(&record.package_record.name)
+                        .map_or(true, |channels| {
+                            channels
+                                .iter()
+                                .any(|channel| is_record_from_channel(record, channel))
+                        })
+                }))
+            })
+            .collect::<Vec<_>>();
+
         // Construct a solver task that we can start solving.
         let task = rattler_solve::SolverTask {
             specs,
+            constraints,
             locked_packages,
             virtual_packages,
             channel_priority,
-            ..rattler_solve::SolverTask::from_iter(&available_packages)
+            exclude_newer: exclude_newer.as_ref().map(ExcludeNewer::timestamp),
+            strategy: solve_strategy.into(),
+            ..rattler_solve::SolverTask::from_iter(available_packages)
         };
 
         // Solve the task
@@ -34,3 +63,24 @@ pub async fn resolve_conda(
         Err(_err) => Err(miette::miette!("cancelled")),
     })
 }
+
+/// Returns true if the given record was downloaded from the given channel.
+pub(crate) fn is_record_from_channel(record: &RepoDataRecord, channel: &Channel) -> bool {
+    record.url.as_str().starts_with(channel.base_url.as_str())
+}
+
+/// Collects the channels that packages are pinned to by the given specs.
+fn pinned_channels<'a>(
+    specs: impl IntoIterator<Item = &'a MatchSpec>,
+) -> HashMap<PackageName, Vec<Channel>> {
+    let mut pinned: HashMap<PackageName, Vec<Channel>> = HashMap::new();
+    for spec in specs {
+        if let (Some(name), Some(channel)) = (&spec.name, &spec.channel) {
+            pinned
+                .entry(name.clone())
+                .or_default()
+                .push(channel.as_ref().clone());
+        }
+    }
+    pinned
+}
@@ -13,7 +13,10 @@ use indexmap::{IndexMap, IndexSet};
 use indicatif::ProgressBar;
 use itertools::{Either, Itertools};
 use miette::{Context, IntoDiagnostic};
-use pixi_manifest::{pypi::pypi_options::PypiOptions, PyPiRequirement, SystemRequirements};
+use pixi_manifest::{
+    pypi::pypi_options::PypiOptions, ExcludeNewer, PyPiRequirement, SolveStrategy,
+    SystemRequirements,
+};
 use pixi_uv_conversions::{
     as_uv_req, convert_uv_requirements_to_pep508, isolated_names_to_packages,
     names_to_build_isolation, pypi_options_to_index_locations, to_index_strategy, to_normalize,
@@ -29,7 +32,7 @@ use rattler_lock::{
     PackageHashes, PypiPackageData, PypiPackageEnvironmentData, PypiSourceTreeHashable, UrlOrPath,
 };
 use url::Url;
-use uv_client::{Connectivity, FlatIndexClient, RegistryClient, RegistryClientBuilder};
+use uv_client::{FlatIndexClient, RegistryClient, RegistryClientBuilder};
 use uv_configuration::{ConfigSettings, Constraints, IndexStrategy, LowerBound, Overrides};
 use uv_dispatch::BuildDispatch;
 use uv_distribution::DistributionDatabase;
@@ -43,7 +46,7 @@ use uv_pypi_types::{HashAlgorithm, HashDigest, RequirementSource};
 use uv_python::{Interpreter, PythonEnvironment, PythonVersion};
 use uv_resolver::{
     AllowedYanks, DefaultResolverProvider, FlatIndex, InMemoryIndex, Manifest, Options, Preference,
-    Preferences, PythonRequirement, Resolver, ResolverEnvironment,
+    Preferences, PythonRequirement, ResolutionMode, Resolver, ResolverEnvironment,
 };
 use uv_types::EmptyInstalledPackages;
 
@@ -133,11 +136,53 @@ fn print_overridden_requests(package_requests: &HashMap<uv_normalize::PackageNam
     }
 }
 
+/// Converts the exclude-newer date from the manifest into the type used by uv.
+fn to_uv_exclude_newer(exclude_newer: ExcludeNewer) -> miette::Result<uv_resolver::ExcludeNewer> {
+    uv_resolver::ExcludeNewer::from_str(&exclude_newer.to_string())
+        .map_err(|err| miette::miette!("invalid exclude-newer date: {err}"))
+}
+
+/// Converts the solve strategy from the manifest into the resolution mode used
+/// by uv.
+fn to_resolution_mode(solve_strategy: SolveStrategy) -> ResolutionMode {
+    match solve_strategy {
+        SolveStrategy::Highest => ResolutionMode::Highest,
+        SolveStrategy::Lowest => ResolutionMode::Lowest,
+        SolveStrategy::LowestDirect => ResolutionMode::LowestDirect,
+    }
+}
+
+/// Converts a requirement from the manifest into a uv requirement. A
+/// requirement that refers to a named index is only resolved from that index.
+fn to_uv_requirement(
+    requirement: &PyPiRequirement,
+    name: &uv_normalize::PackageName,
+    pypi_options: &PypiOptions,
+    project_root: &Path,
+) -> miette::Result<uv_pypi_types::Requirement> {
+    let mut uv_requirement =
+        as_uv_req(requirement, name.as_ref(), project_root).into_diagnostic()?;
+    if let Some(index_name) = requirement.index() {
+        let url = pypi_options.named_index(index_name).ok_or_else(|| {
+            miette::miette!(
+                "the pypi dependency '{name}' refers to the index '{index_name}' which is not defined in the `pypi-options`"
+            )
+        })?;
+        if let RequirementSource::Registry { index, .. } = &mut uv_requirement.source {
+            *index = Some(url.clone());
+        }
+    }
+    Ok(uv_requirement)
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn resolve_pypi(
     context: UvResolutionContext,
     pypi_options: &PypiOptions,
+    exclude_newer: Option<ExcludeNewer>,
+    solve_strategy: SolveStrategy,
     dependencies: IndexMap<uv_normalize::PackageName, IndexSet<PyPiRequirement>>,
+    pypi_constraints: IndexMap<uv_normalize::PackageName, IndexSet<PyPiRequirement>>,
     system_requirements: SystemRequirements,
     locked_conda_records: &[RepoDataRecord],
     locked_pypi_packages: &[PypiRecord],
@@ -192,10 +237,19 @@ pub async fn resolve_pypi(
         .into_iter()
         .flat_map(|(name, req)| {
             req.into_iter()
-                .map(move |r| as_uv_req(&r, name.as_ref(), project_root))
+                .map(move |r| to_uv_requirement(&r, &name, pypi_options, project_root))
         })
-        .collect::<Result<Vec<_>, _>>()
-        .into_diagnostic()?;
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    // The constraints from the manifest only restrict the versions of packages
+    // that are required by something else.
+    let manifest_constraints = pypi_constraints
+        .into_iter()
+        .flat_map(|(name, req)| {
+            req.into_iter()
+                .map(move |r| to_uv_requirement(&r, &name, pypi_options, project_root))
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
 
     use pixi_consts::consts::PROJECT_MANIFEST;
     // Determine the python interpreter that is installed as part of the conda
@@ -229,7 +283,7 @@ pub async fn resolve_pypi(
             .index_urls(index_locations.index_urls())
             .index_strategy(index_strategy)
             .keyring(context.keyring_provider)
-            .connectivity(Connectivity::Online)
+            .connectivity(context.connectivity)
             .build(),
     );
 
@@ -270,8 +324,11 @@ pub async fn resolve_pypi(
     tracing::debug!("using build-isolation: {:?}", build_isolation);
 
     let dependency_metadata = DependencyMetadata::default();
+    let exclude_newer = exclude_newer.map(to_uv_exclude_newer).transpose()?;
     let options = Options {
         index_strategy,
+        exclude_newer,
+        resolution_mode: to_resolution_mode(solve_strategy),
         ..Options::default()
     };
     let git_resolver = GitResolver::default();
@@ -294,7 +351,7 @@ pub async fn resolve_pypi(
         LinkMode::default(),
         &context.build_options,
         &context.hash_strategy,
-        None,
+        exclude_newer,
         LowerBound::default(),
         context.source_strategy,
         context.concurrency,
@@ -353,7 +410,12 @@ pub async fn resolve_pypi(
     let resolver_env = ResolverEnvironment::specific(marker_environment.into());
     let manifest = Manifest::new(
         requirements,
-        Constraints::from_requirements(constraints.iter().cloned()),
+        Constraints::from_requirements(
+            constraints
+                .iter()
+                .cloned()
+                .chain(manifest_constraints.iter().cloned()),
+        ),
         Overrides::default(),
         Default::default(),
         Preferences::from_iter(preferences, &resolver_env),
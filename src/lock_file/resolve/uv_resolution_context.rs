@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use miette::{Context, IntoDiagnostic};
 use uv_cache::Cache;
+use uv_client::Connectivity;
 use uv_configuration::{BuildOptions, Concurrency, SourceStrategy};
 use uv_distribution_types::IndexCapabilities;
 use uv_types::{HashStrategy, InFlight};
@@ -22,6 +23,7 @@ pub struct UvResolutionContext {
     pub concurrency: Concurrency,
     pub source_strategy: SourceStrategy,
     pub capabilities: IndexCapabilities,
+    pub connectivity: Connectivity,
 }
 
 impl UvResolutionContext {
@@ -46,6 +48,13 @@ impl UvResolutionContext {
             }
         };
 
+        // In offline mode uv only uses the wheels and metadata that are cached.
+        let connectivity = if project.config().offline() {
+            Connectivity::Offline
+        } else {
+            Connectivity::Online
+        };
+
         let in_flight = Arc::new(InFlight::default());
         Ok(Self {
             cache,
@@ -57,6 +66,7 @@ impl UvResolutionContext {
             concurrency: Concurrency::default(),
             source_strategy: SourceStrategy::Disabled,
             capabilities: IndexCapabilities::default(),
+            connectivity,
         })
     }
 }
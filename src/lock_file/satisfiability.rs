@@ -10,7 +10,10 @@ use std::{
 use itertools::Itertools;
 use miette::Diagnostic;
 use pep440_rs::VersionSpecifiers;
-use pixi_manifest::FeaturesExt;
+use pixi_manifest::{
+    pypi::{pypi_options::PypiOptions, PyPiPackageName},
+    ExcludeNewer, FeaturesExt, PyPiRequirement, SolveStrategy,
+};
 use pixi_spec::{PixiSpec, SpecConversionError};
 use pixi_uv_conversions::{
     as_uv_req, to_normalize, to_uv_marker_tree, to_uv_version_specifiers, AsPep508Error,
@@ -33,7 +36,8 @@ use uv_pypi_types::{
 };
 
 use super::{
-    package_identifier::ConversionError, PypiRecord, PypiRecordsByName, RepoDataRecordsByName,
+    package_identifier::ConversionError, resolve::conda::is_record_from_channel, PypiRecord,
+    PypiRecordsByName, RepoDataRecordsByName,
 };
 use crate::project::{grouped_environment::GroupedEnvironment, Environment, HasProjectRef};
 
@@ -47,6 +51,17 @@ pub enum EnvironmentUnsat {
 
     #[error(transparent)]
     InvalidChannel(#[from] ParseChannelError),
+
+    #[error(
+        "the locked package '{package}' was published after the exclude-newer date {exclude_newer}"
+    )]
+    ExcludeNewerMismatch {
+        package: String,
+        exclude_newer: ExcludeNewer,
+    },
+
+    #[error("the lock-file was not solved with the '{0}' solve strategy of the environment")]
+    SolveStrategyMismatch(SolveStrategy),
 }
 
 #[derive(Debug, Error)]
@@ -85,6 +100,18 @@ pub enum PlatformUnsat {
     #[error("the requirement '{0}' could not be satisfied (required by '{1}')")]
     UnsatisfiableMatchSpec(MatchSpec, String),
 
+    #[error("the locked package '{name}' was taken from '{locked}' but it is pinned to the channel '{expected}'")]
+    ChannelMismatch {
+        name: String,
+        expected: String,
+        locked: Url,
+    },
+
+    #[error(
+        "'{0}' is a source dependency, building conda packages from source is not supported yet"
+    )]
+    SourceDependency(String),
+
     #[error("the requirement '{0}' could not be satisfied (required by '{1}')")]
     UnsatisfiableRequirement(Box<uv_pypi_types::Requirement>, String),
 
@@ -182,6 +209,13 @@ pub enum PlatformUnsat {
         lock_url: String,
     },
 
+    #[error("'{name}' is pinned to the index '{index}' but the lock file has: '{lock_url}'")]
+    LockedPyPIIndexMismatch {
+        name: String,
+        index: Url,
+        lock_url: String,
+    },
+
     #[error("'{name}' has mismatching git url: '{spec_url} != {lock_url}'")]
     LockedPyPIGitUrlMismatch {
         name: String,
@@ -228,6 +262,45 @@ impl PlatformUnsat {
                 | PlatformUnsat::SourceTreeHashMismatch(_),
         )
     }
+
+    /// Returns the name of the package that caused the problem, if the problem
+    /// can be attributed to a single package.
+    pub(crate) fn package_name(&self) -> Option<String> {
+        match self {
+            PlatformUnsat::UnsatisfiableMatchSpec(spec, _) => spec
+                .name
+                .as_ref()
+                .map(|name| name.as_normalized().to_string()),
+            PlatformUnsat::UnsatisfiableRequirement(requirement, _)
+            | PlatformUnsat::CondaUnsatisfiableRequirement(requirement, _) => {
+                Some(requirement.name.to_string())
+            }
+            PlatformUnsat::ChannelMismatch { name, .. }
+            | PlatformUnsat::SourceDependency(name)
+            | PlatformUnsat::DuplicateEntry(name)
+            | PlatformUnsat::CorruptedEntry(name, _)
+            | PlatformUnsat::LockedPyPINamesMismatch { expected: name, .. }
+            | PlatformUnsat::LockedPyPIVersionsMismatch { name, .. }
+            | PlatformUnsat::LockedPyPIRequiresDirectUrl(name)
+            | PlatformUnsat::LockedPyPIDirectUrlMismatch { name, .. }
+            | PlatformUnsat::LockedPyPIIndexMismatch { name, .. }
+            | PlatformUnsat::LockedPyPIGitUrlMismatch { name, .. }
+            | PlatformUnsat::LockedPyPIGitRefMismatch { name, .. }
+            | PlatformUnsat::LockedPyPIRequiresGitUrl(name, _)
+            | PlatformUnsat::LockedPyPIRequiresPath(name)
+            | PlatformUnsat::LockedPyPIPathMismatch { name, .. } => Some(name.clone()),
+            PlatformUnsat::PythonVersionMismatch(name, _, _)
+            | PlatformUnsat::AsPep508Error(name, _)
+            | PlatformUnsat::FailedToDetermineSourceTreeHash(name, _)
+            | PlatformUnsat::SourceTreeHashMismatch(name) => Some(name.to_string()),
+            PlatformUnsat::EditableDependencyOnCondaInstalledPackage(name, _)
+            | PlatformUnsat::DirectUrlDependencyOnCondaInstalledPackage(name)
+            | PlatformUnsat::GitDependencyOnCondaInstalledPackage(name)
+            | PlatformUnsat::EditablePackageIsUrl(name, _)
+            | PlatformUnsat::EditablePackagePathMismatch(name, _, _) => Some(name.to_string()),
+            _ => None,
+        }
+    }
 }
 
 /// Convert something into a uv requirement.
@@ -346,6 +419,37 @@ pub fn verify_environment_satisfiability(
         return Err(EnvironmentUnsat::ChannelsMismatch);
     }
 
+    // Check if the lock file was solved with the same strategy. Lock-files that
+    // predate solve strategies were solved with the default strategy.
+    let solve_strategy = grouped_env.solve_strategy();
+    if locked_environment.solve_options().strategy
+        != rattler_lock::SolveStrategy::from(solve_strategy)
+    {
+        return Err(EnvironmentUnsat::SolveStrategyMismatch(solve_strategy));
+    }
+
+    // Check that none of the locked packages were published after the
+    // `exclude-newer` date. Only conda packages record when they were published,
+    // packages without a timestamp are never excluded.
+    if let Some(exclude_newer) = environment.project().manifest.parsed.project.exclude_newer {
+        let newer_package = locked_environment
+            .packages_by_platform()
+            .flat_map(|(_, packages)| packages)
+            .filter_map(Package::into_conda)
+            .find(|package| {
+                package
+                    .package_record()
+                    .timestamp
+                    .is_some_and(|timestamp| timestamp > exclude_newer.timestamp())
+            });
+        if let Some(package) = newer_package {
+            return Err(EnvironmentUnsat::ExcludeNewerMismatch {
+                package: package.package_record().name.as_source().to_string(),
+                exclude_newer,
+            });
+        }
+    }
+
     // Check if the indexes in the lock file match our current configuration.
     if !environment.pypi_dependencies(None).is_empty() {
         let indexes = rattler_lock::PypiIndexes::from(grouped_env.pypi_options());
@@ -520,6 +624,31 @@ pub(crate) fn pypi_satifisfies_editable(
     }
 }
 
+/// Returns true if the url of a locked distribution is served by the given
+/// index. PyPI serves its files from a separate host, every other index is
+/// expected to serve its files from its own host, below the path of the index
+/// without its `simple` (or devpi's `+simple`) segment. This distinguishes
+/// indexes that share a host, like the repositories of an artifact server.
+///
+/// The lock-file does not record the index a distribution was taken from, so
+/// indexes that serve their files from a different host or path, like a CDN,
+/// are never matched.
+fn is_url_from_index(url: &Url, index: &Url) -> bool {
+    if index.host_str() == Some("pypi.org") {
+        return url.host_str() == Some("files.pythonhosted.org");
+    }
+
+    let index_path = index.path().trim_end_matches('/');
+    let base_path = index_path
+        .strip_suffix("/simple")
+        .or_else(|| index_path.strip_suffix("/+simple"))
+        .unwrap_or(index_path);
+
+    url.host_str() == index.host_str()
+        && url.port_or_known_default() == index.port_or_known_default()
+        && url.path().starts_with(&format!("{base_path}/"))
+}
+
 /// Checks if the string seems like a git commit sha
 fn seems_like_commit_sha(s: &str) -> bool {
     s.len() >= 4 && s.chars().all(|c| c.is_ascii_hexdigit())
@@ -541,7 +670,22 @@ pub(crate) fn pypi_satifisfies_requirement(
     }
 
     match &spec.source {
-        RequirementSource::Registry { specifier, .. } => {
+        RequirementSource::Registry { specifier, index } => {
+            // A package that is pinned to an index must have been taken from that index
+            if let Some(index) = index {
+                let is_from_index = match &locked_data.url_or_path {
+                    UrlOrPath::Url(url) => is_url_from_index(url, index),
+                    UrlOrPath::Path(_) => false,
+                };
+                if !is_from_index {
+                    return Err(PlatformUnsat::LockedPyPIIndexMismatch {
+                        name: spec.name.to_string(),
+                        index: index.clone(),
+                        lock_url: locked_data.url_or_path.to_string(),
+                    });
+                }
+            }
+
             // In the old way we always satisfy based on version so let's keep it similar
             // here
             let version_string = locked_data.version.to_string();
@@ -672,6 +816,28 @@ pub(crate) fn pypi_satifisfies_requirement(
     }
 }
 
+/// Converts a pypi requirement from the manifest into a uv requirement. A
+/// requirement that is pinned to a named index refers to the url of that
+/// index.
+fn as_pinned_uv_req(
+    requirement: &PyPiRequirement,
+    name: &PyPiPackageName,
+    pypi_options: &PypiOptions,
+    project_root: &Path,
+) -> Result<uv_pypi_types::Requirement, PlatformUnsat> {
+    let mut uv_requirement = as_uv_req(requirement, name.as_source(), project_root)
+        .map_err(|e| PlatformUnsat::AsPep508Error(name.as_normalized().clone(), e))?;
+    let index_url = requirement
+        .index()
+        .and_then(|index| pypi_options.named_index(index));
+    if let (Some(url), RequirementSource::Registry { index, .. }) =
+        (index_url, &mut uv_requirement.source)
+    {
+        *index = Some(url.clone());
+    }
+    Ok(uv_requirement)
+}
+
 #[allow(clippy::result_large_err)]
 pub(crate) fn verify_package_platform_satisfiability(
     environment: &Environment<'_>,
@@ -694,15 +860,15 @@ pub(crate) fn verify_package_platform_satisfiability(
     }
 
     // Transform from PyPiPackage name into UV Requirement type
+    let pypi_options = environment.pypi_options();
     let pypi_requirements = environment
         .pypi_dependencies(Some(platform))
         .iter()
         .flat_map(|(name, reqs)| {
+            let pypi_options = &pypi_options;
             reqs.iter().map(move |req| {
                 Ok::<Dependency, PlatformUnsat>(Dependency::PyPi(
-                    as_uv_req(req, name.as_source(), project_root).map_err(|e| {
-                        PlatformUnsat::AsPep508Error(name.as_normalized().clone(), e)
-                    })?,
+                    as_pinned_uv_req(req, name, pypi_options, project_root)?,
                     "<environment>".into(),
                 ))
             })
@@ -749,6 +915,21 @@ pub(crate) fn verify_package_platform_satisfiability(
         Ok(marker_environment) => marker_environment,
     };
 
+    // Requirements from the manifest whose environment markers do not apply to
+    // this platform are not expected to be part of the lock-file.
+    let pypi_requirements = match marker_environment.as_ref() {
+        Some(marker_environment) => pypi_requirements
+            .into_iter()
+            .filter(|dependency| match dependency {
+                Dependency::PyPi(requirement, _) => {
+                    requirement.evaluate_markers(Some(marker_environment), &[])
+                }
+                _ => true,
+            })
+            .collect_vec(),
+        None => pypi_requirements,
+    };
+
     // Determine the pypi packages provided by the locked conda packages.
     let locked_conda_pypi_packages = locked_conda_packages.by_pypi_name()?;
 
@@ -775,26 +956,32 @@ pub(crate) fn verify_package_platform_satisfiability(
             Dependency::Input(name, spec, source) => {
                 let spec = match spec.try_into_nameless_match_spec(&channel_config) {
                     Ok(Some(spec)) => MatchSpec::from_nameless(spec, Some(name)),
-                    Ok(None) => unimplemented!("source dependencies are not yet implemented"),
+                    Ok(None) => {
+                        return Err(PlatformUnsat::SourceDependency(
+                            name.as_source().to_string(),
+                        ))
+                    }
                     Err(e) => {
-                        let parse_channel_err: ParseMatchSpecError = match e {
-                            SpecConversionError::NonAbsoluteRootDir(p) => {
-                                ParseChannelError::NonAbsoluteRootDir(p).into()
-                            }
-                            SpecConversionError::NotUtf8RootDir(p) => {
-                                ParseChannelError::NotUtf8RootDir(p).into()
-                            }
-                            SpecConversionError::InvalidPath(p) => {
-                                ParseChannelError::InvalidPath(p).into()
-                            }
-                            SpecConversionError::InvalidChannel(c) => c.into(),
-                        };
                         return Err(PlatformUnsat::FailedToParseMatchSpec(
                             name.as_source().to_string(),
-                            parse_channel_err,
+                            spec_conversion_error(e),
                         ));
                     }
                 };
+                if let (Some(channel), Some(record)) = (
+                    &spec.channel,
+                    spec.name
+                        .as_ref()
+                        .and_then(|name| locked_conda_packages.by_name(name)),
+                ) {
+                    if !is_record_from_channel(record, channel) {
+                        return Err(PlatformUnsat::ChannelMismatch {
+                            name: record.package_record.name.as_source().to_string(),
+                            expected: channel.base_url.as_str().to_string(),
+                            locked: record.url.clone(),
+                        });
+                    }
+                }
                 match find_matching_package(locked_conda_packages, &virtual_packages, spec, source)?
                 {
                     Some(pkg) => pkg,
@@ -978,6 +1165,52 @@ pub(crate) fn verify_package_platform_satisfiability(
         ));
     }
 
+    // Check if the locked packages satisfy the constraints of the environment.
+    // Constraints only restrict packages that are part of the environment.
+    for (name, spec) in environment.constraints(Some(platform)).into_specs() {
+        let Some(record) = locked_conda_packages.by_name(&name) else {
+            continue;
+        };
+        let spec = match spec.try_into_nameless_match_spec(&channel_config) {
+            Ok(Some(spec)) => MatchSpec::from_nameless(spec, Some(name)),
+            Ok(None) => continue,
+            Err(e) => {
+                return Err(PlatformUnsat::FailedToParseMatchSpec(
+                    name.as_source().to_string(),
+                    spec_conversion_error(e),
+                ));
+            }
+        };
+        if !record.matches(&spec) {
+            return Err(PlatformUnsat::UnsatisfiableMatchSpec(
+                spec,
+                "<constraints>".into(),
+            ));
+        }
+    }
+
+    for (name, reqs) in environment.pypi_constraints(Some(platform)).iter() {
+        for req in reqs {
+            let requirement = as_pinned_uv_req(req, name, &pypi_options, project_root)?;
+            if let Some((identifier, _, _)) = locked_conda_pypi_packages.get(&requirement.name) {
+                if !identifier.satisfies(&requirement)? {
+                    return Err(PlatformUnsat::CondaUnsatisfiableRequirement(
+                        Box::new(requirement),
+                        "<constraints>".into(),
+                    ));
+                }
+            } else if let Some(idx) = locked_pypi_environment.index_by_name(
+                &to_normalize(&requirement.name).map_err(ConversionError::NameConversion)?,
+            ) {
+                pypi_satifisfies_requirement(
+                    &requirement,
+                    &locked_pypi_environment.records[idx].0,
+                    project_root,
+                )?;
+            }
+        }
+    }
+
     // Check if all packages that should be editable are actually editable and vice
     // versa.
     let locked_editable_packages = locked_pypi_environment
@@ -1002,6 +1235,19 @@ pub(crate) fn verify_package_platform_satisfiability(
     Ok(())
 }
 
+/// Converts an error that occurred while converting a [`PixiSpec`] into a
+/// match spec into a [`ParseMatchSpecError`].
+fn spec_conversion_error(e: SpecConversionError) -> ParseMatchSpecError {
+    match e {
+        SpecConversionError::NonAbsoluteRootDir(p) => {
+            ParseChannelError::NonAbsoluteRootDir(p).into()
+        }
+        SpecConversionError::NotUtf8RootDir(p) => ParseChannelError::NotUtf8RootDir(p).into(),
+        SpecConversionError::InvalidPath(p) => ParseChannelError::InvalidPath(p).into(),
+        SpecConversionError::InvalidChannel(c) => c.into(),
+    }
+}
+
 enum FoundPackage {
     Conda(usize),
     PyPi(usize, Vec<uv_pep508::ExtraName>),
@@ -1090,7 +1336,7 @@ impl MatchesMatchspec for RepoDataRecord {
         // TODO: We should really move this into rattler
         // Check the channel
         if let Some(channel) = &spec.channel {
-            if !self.url.as_str().starts_with(channel.base_url.as_str()) {
+            if !is_record_from_channel(self, channel) {
                 return false;
             }
         }
@@ -1205,6 +1451,7 @@ mod tests {
         str::FromStr,
     };
 
+    use assert_matches::assert_matches;
     use miette::{IntoDiagnostic, NarratableReportHandler};
     use pep440_rs::Version;
     use rattler_lock::LockFile;
@@ -1338,6 +1585,102 @@ mod tests {
         pypi_satifisfies_requirement(&spec, &locked_data, &project_root).unwrap();
     }
 
+    #[test]
+    fn test_pypi_index_pinning() {
+        let locked_data = PypiPackageData {
+            name: "mypkg".parse().unwrap(),
+            version: Version::from_str("0.1.0").unwrap(),
+            url_or_path: "https://pypi.internal.example.com/packages/mypkg-0.1.0-py3-none-any.whl"
+                .parse()
+                .expect("failed to parse url"),
+            hash: None,
+            requires_dist: vec![],
+            requires_python: None,
+            editable: false,
+        };
+        let mut spec = pep508_rs::Requirement::from_str("mypkg>=0.1")
+            .unwrap()
+            .into_uv_requirement()
+            .unwrap();
+        let project_root = PathBuf::from_str("/").unwrap();
+
+        // The package was taken from the index it is pinned to.
+        let RequirementSource::Registry { index, .. } = &mut spec.source else {
+            panic!("expected a registry requirement");
+        };
+        *index = Some(Url::parse("https://pypi.internal.example.com/simple").unwrap());
+        pypi_satifisfies_requirement(&spec, &locked_data, &project_root).unwrap();
+
+        // The package was not taken from the index it is pinned to.
+        let RequirementSource::Registry { index, .. } = &mut spec.source else {
+            panic!("expected a registry requirement");
+        };
+        *index = Some(Url::parse("https://pypi.other.example.com/simple").unwrap());
+        assert_matches!(
+            pypi_satifisfies_requirement(&spec, &locked_data, &project_root),
+            Err(PlatformUnsat::LockedPyPIIndexMismatch { .. })
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "https://download.pytorch.org/whl/cu121/torch-2.4.0-cp312-none-linux_x86_64.whl",
+        "https://download.pytorch.org/whl/cu121",
+        true
+    )]
+    #[case(
+        "https://devpi.example.com/root/pypi/+f/abc/rich-13.8.0-py3-none-any.whl",
+        "https://devpi.example.com/root/pypi/+simple/",
+        true
+    )]
+    #[case(
+        "https://files.pythonhosted.org/packages/ab/cd/rich-13.8.0-py3-none-any.whl",
+        "https://pypi.org/simple",
+        true
+    )]
+    #[case(
+        "https://files.pythonhosted.org/packages/ab/cd/rich-13.8.0-py3-none-any.whl",
+        "https://pypi.internal.example.com/simple",
+        false
+    )]
+    #[case(
+        "https://example.com:8443/packages/rich-13.8.0-py3-none-any.whl",
+        "https://example.com/simple",
+        false
+    )]
+    #[case(
+        "https://artifacts.example.com/api/pypi/team-a/packages/rich-13.8.0-py3-none-any.whl",
+        "https://artifacts.example.com/api/pypi/team-a/simple",
+        true
+    )]
+    #[case(
+        "https://artifacts.example.com/api/pypi/team-b/packages/rich-13.8.0-py3-none-any.whl",
+        "https://artifacts.example.com/api/pypi/team-a/simple/",
+        false
+    )]
+    #[case(
+        "https://download.pytorch.org/whl/cu118/torch-2.4.0-cp312-none-linux_x86_64.whl",
+        "https://download.pytorch.org/whl/cu121",
+        false
+    )]
+    fn test_is_url_from_index(#[case] url: &str, #[case] index: &str, #[case] expected: bool) {
+        assert_eq!(
+            is_url_from_index(&Url::parse(url).unwrap(), &Url::parse(index).unwrap()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(
+            PlatformUnsat::DuplicateEntry("foo".to_string()).package_name(),
+            Some("foo".to_string())
+        );
+        assert_eq!(PlatformUnsat::TooManyCondaPackages.package_name(), None);
+        assert_eq!(PlatformUnsat::MissingPurls.package_name(), None);
+        assert_eq!(PlatformUnsat::MissingPythonInterpreter.package_name(), None);
+    }
+
     // Currently this test is missing from `good_satisfiability`, so we test the
     // specific windows case here this should work an all supported platforms
     #[test]
@@ -0,0 +1,330 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use pixi_manifest::FeaturesExt;
+use rattler_conda_types::{MatchSpec, ParseStrictness, Platform, RepoDataRecord};
+use rattler_lock::{LockFile, Package};
+
+use super::{filter_lock_file, OutdatedEnvironments, PypiPackageIdentifier};
+use crate::{project::Environment, Project};
+
+/// Describes the content of a lock-file and the installed prefixes of a
+/// project that no longer correspond to the manifest.
+///
+/// Use [`StaleContent::from_project_and_lock_file`] to create an instance of
+/// this struct by comparing the project with the lock-file and the
+/// environments directory.
+pub struct StaleContent<'p> {
+    /// Environments in the lock-file that are no longer defined in the
+    /// manifest.
+    pub environments: Vec<String>,
+
+    /// Platforms of locked environments that are no longer part of the
+    /// environment in the manifest.
+    pub platforms: IndexMap<String, Vec<Platform>>,
+
+    /// Packages that are locked for an environment and platform of the
+    /// manifest but that are no longer required by the dependencies of the
+    /// environment, e.g. because a dependency was removed.
+    pub packages: Vec<StalePackage>,
+
+    /// Environments and platforms whose locked packages no longer satisfy the
+    /// manifest, even after the stale packages are removed. These require a
+    /// new solve and cannot be pruned.
+    pub outdated: OutdatedEnvironments<'p>,
+
+    /// Prefixes on disk that do not belong to an environment or solve group
+    /// of the project.
+    pub orphaned_prefixes: Vec<PathBuf>,
+}
+
+/// A package in the lock-file that is no longer required by the environment
+/// and platform it is locked for.
+pub struct StalePackage {
+    pub environment: String,
+    pub platform: Platform,
+    pub package: Package,
+}
+
+impl StalePackage {
+    /// Returns the name of the package.
+    pub(crate) fn name(&self) -> String {
+        match &self.package {
+            Package::Conda(package) => package.package_record().name.as_source().to_string(),
+            Package::Pypi(package) => package.data().package.name.to_string(),
+        }
+    }
+}
+
+impl<'p> StaleContent<'p> {
+    /// Constructs a new instance by examining the project, the lock-file and the
+    /// prefixes of the project.
+    pub(crate) fn from_project_and_lock_file(
+        project: &'p Project,
+        lock_file: &LockFile,
+    ) -> miette::Result<Self> {
+        let mut environments = Vec::new();
+        let mut platforms = IndexMap::new();
+        let mut packages = Vec::new();
+        for (name, locked_environment) in lock_file.environments() {
+            let Some(environment) = project.environment(name) else {
+                environments.push(name.to_string());
+                continue;
+            };
+
+            let manifest_platforms = environment.platforms();
+            let stale_platforms = locked_environment
+                .platforms()
+                .filter(|platform| !manifest_platforms.contains(platform))
+                .sorted_by_key(|platform| platform.as_str())
+                .collect_vec();
+            if !stale_platforms.is_empty() {
+                platforms.insert(name.to_string(), stale_platforms);
+            }
+
+            for (platform, locked_packages) in locked_environment.packages_by_platform() {
+                if !manifest_platforms.contains(&platform) {
+                    continue;
+                }
+                packages.extend(
+                    unused_packages(&environment, platform, locked_packages.collect())
+                        .into_iter()
+                        .map(|package| StalePackage {
+                            environment: name.to_string(),
+                            platform,
+                            package,
+                        }),
+                );
+            }
+        }
+
+        // Find the prefixes that are not used by any environment or solve group.
+        let environment_names = project
+            .environments()
+            .iter()
+            .map(|env| env.name().as_str().to_string())
+            .collect::<HashSet<_>>();
+        let solve_group_names = project
+            .solve_groups()
+            .iter()
+            .map(|group| group.name().to_string())
+            .collect::<HashSet<_>>();
+        let orphaned_prefixes =
+            orphaned_directories(&project.environments_dir(), &environment_names)?
+                .into_iter()
+                .chain(orphaned_directories(
+                    &project.solve_group_environments_dir(),
+                    &solve_group_names,
+                )?)
+                .collect();
+
+        // Only report what pruning cannot fix as outdated.
+        let pruned_lock_file = prune_lock_file(project, lock_file, &packages);
+        let outdated = OutdatedEnvironments::from_project_and_lock_file(project, &pruned_lock_file);
+
+        Ok(Self {
+            environments,
+            platforms,
+            packages,
+            outdated,
+            orphaned_prefixes,
+        })
+    }
+
+    /// Returns true if nothing in the lock-file or on disk can be pruned.
+    pub(crate) fn is_prunable_empty(&self) -> bool {
+        self.environments.is_empty()
+            && self.platforms.is_empty()
+            && self.packages.is_empty()
+            && self.orphaned_prefixes.is_empty()
+    }
+
+    /// Returns true if the lock-file and the prefixes fully correspond to the
+    /// manifest.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.is_prunable_empty() && self.outdated.is_empty()
+    }
+}
+
+/// Constructs a new lock-file from which all environments and platforms that
+/// are no longer part of the project have been removed, together with the
+/// stale `packages`.
+pub(crate) fn prune_lock_file(
+    project: &Project,
+    lock_file: &LockFile,
+    packages: &[StalePackage],
+) -> LockFile {
+    let mut stale_packages: HashMap<&str, HashSet<(Platform, PackageKey)>> = HashMap::new();
+    for stale in packages {
+        stale_packages
+            .entry(stale.environment.as_str())
+            .or_default()
+            .insert((stale.platform, PackageKey::from(&stale.package)));
+    }
+    filter_lock_file(project, lock_file, |environment, platform, package| {
+        environment.platforms().contains(&platform)
+            && !stale_packages
+                .get(environment.name().as_str())
+                .is_some_and(|stale| stale.contains(&(platform, PackageKey::from(package))))
+    })
+}
+
+/// Identifies a package within the packages of an environment and platform.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PackageKey {
+    Conda(String),
+    Pypi(String),
+}
+
+impl From<&Package> for PackageKey {
+    fn from(package: &Package) -> Self {
+        match package {
+            Package::Conda(package) => {
+                PackageKey::Conda(package.package_record().name.as_normalized().to_string())
+            }
+            Package::Pypi(package) => PackageKey::Pypi(package.data().package.name.to_string()),
+        }
+    }
+}
+
+/// Returns the locked `packages` of an environment and platform that cannot be
+/// reached from the dependencies of the environment in the manifest.
+///
+/// The requirements of the locked packages are followed regardless of their
+/// markers and extras, so a package is only unused if nothing could require it.
+fn unused_packages(
+    environment: &Environment<'_>,
+    platform: Platform,
+    packages: Vec<Package>,
+) -> Vec<Package> {
+    let keys = packages.iter().map(PackageKey::from).collect_vec();
+    let indices = keys
+        .iter()
+        .enumerate()
+        .map(|(idx, key)| (key, idx))
+        .collect::<HashMap<_, _>>();
+
+    // PyPI requirements can also be provided by conda packages.
+    let mut pypi_providers = HashMap::new();
+    for (idx, package) in packages.iter().enumerate() {
+        let Package::Conda(package) = package else {
+            continue;
+        };
+        let Ok(record) = RepoDataRecord::try_from(package.clone()) else {
+            continue;
+        };
+        for identifier in PypiPackageIdentifier::from_record(&record).unwrap_or_default() {
+            pypi_providers.insert(identifier.name.as_normalized().to_string(), idx);
+        }
+    }
+
+    let mut queue = environment
+        .dependencies(None, Some(platform))
+        .names()
+        .map(|name| PackageKey::Conda(name.as_normalized().to_string()))
+        .chain(
+            environment
+                .pypi_dependencies(Some(platform))
+                .names()
+                .map(|name| PackageKey::Pypi(name.as_normalized().to_string())),
+        )
+        .collect_vec();
+    let mut required = HashSet::new();
+    while let Some(key) = queue.pop() {
+        let idx = match (indices.get(&key), &key) {
+            (Some(idx), _) => *idx,
+            (None, PackageKey::Pypi(name)) => match pypi_providers.get(name) {
+                Some(idx) => *idx,
+                None => continue,
+            },
+            (None, PackageKey::Conda(_)) => continue,
+        };
+        if !required.insert(idx) {
+            continue;
+        }
+
+        match &packages[idx] {
+            Package::Conda(package) => {
+                queue.extend(
+                    package
+                        .package_record()
+                        .depends
+                        .iter()
+                        .filter_map(|depends| {
+                            MatchSpec::from_str(depends, ParseStrictness::Lenient)
+                                .ok()?
+                                .name
+                        })
+                        .map(|name| PackageKey::Conda(name.as_normalized().to_string())),
+                );
+            }
+            Package::Pypi(package) => {
+                // PyPI packages are installed with the python interpreter of the
+                // environment.
+                queue.push(PackageKey::Conda(String::from("python")));
+                queue.extend(
+                    package
+                        .data()
+                        .package
+                        .requires_dist
+                        .iter()
+                        .map(|requirement| PackageKey::Pypi(requirement.name.to_string())),
+                );
+            }
+        }
+    }
+
+    packages
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| !required.contains(idx))
+        .map(|(_, package)| package)
+        .collect()
+}
+
+/// Returns the directories in `dir` whose name is not in `names`.
+fn orphaned_directories(dir: &Path, names: &HashSet<String>) -> miette::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut orphaned = Vec::new();
+    for entry in fs_err::read_dir(dir).into_diagnostic()? {
+        let entry = entry.into_diagnostic()?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if !names.contains(entry.file_name().to_string_lossy().as_ref()) {
+            orphaned.push(path);
+        }
+    }
+    orphaned.sort();
+    Ok(orphaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphaned_directories() {
+        let tmp = tempfile::tempdir().unwrap();
+        fs_err::create_dir(tmp.path().join("default")).unwrap();
+        fs_err::create_dir(tmp.path().join("removed")).unwrap();
+        fs_err::write(tmp.path().join("file"), "").unwrap();
+
+        let names = HashSet::from([String::from("default"), String::from("test")]);
+        let orphaned = orphaned_directories(tmp.path(), &names).unwrap();
+        assert_eq!(orphaned, vec![tmp.path().join("removed")]);
+
+        let missing = orphaned_directories(&tmp.path().join("missing"), &names).unwrap();
+        assert!(missing.is_empty());
+    }
+}
@@ -7,7 +7,7 @@ use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic, LabeledSpan, MietteDiagnostic, WrapErr};
 use parking_lot::Mutex;
 use pixi_consts::consts;
-use pixi_manifest::{EnvironmentName, FeaturesExt, HasFeaturesIter};
+use pixi_manifest::{EnvironmentName, ExcludeNewer, FeaturesExt, HasFeaturesIter};
 use pixi_progress::global_multi_progress;
 use pixi_uv_conversions::{
     to_extra_name, to_marker_environment, to_normalize, to_uv_extra_name, to_uv_normalize,
@@ -17,7 +17,9 @@ use pypi_mapping::{self, Reporter};
 use pypi_modifiers::{pypi_marker_env::determine_marker_environment, pypi_tags::is_python_record};
 use rattler::package_cache::PackageCache;
 use rattler_conda_types::{Arch, Channel, MatchSpec, Platform, RepoDataRecord};
-use rattler_lock::{LockFile, PypiIndexes, PypiPackageData, PypiPackageEnvironmentData};
+use rattler_lock::{
+    LockFile, PypiIndexes, PypiPackageData, PypiPackageEnvironmentData, SolveOptions,
+};
 use rattler_repodata_gateway::{Gateway, RepoData};
 use rattler_solve::ChannelPriority;
 use std::cmp::PartialEq;
@@ -441,6 +443,13 @@ pub struct UpdateContext<'p> {
 
     /// Whether it is allowed to instantiate any prefix.
     no_install: bool,
+
+    /// The directory in which the prefixes that are required to solve pypi
+    /// dependencies are instantiated instead of the environment prefixes.
+    solve_prefix_dir: Option<PathBuf>,
+
+    /// Packages published after this date are excluded from the solves.
+    exclude_newer: Option<ExcludeNewer>,
 }
 
 impl<'p> UpdateContext<'p> {
@@ -707,6 +716,15 @@ pub struct UpdateContextBuilder<'p> {
 
     /// The io concurrency semaphore to use when updating environments
     io_concurrency_limit: Option<IoConcurrencyLimit>,
+
+    /// Overrides the exclude-newer date of the project. If this value is
+    /// `None` the date from the manifest is used.
+    exclude_newer: Option<ExcludeNewer>,
+
+    /// The directory in which the prefixes that are required to solve pypi
+    /// dependencies are instantiated. If this value is `None` the prefixes of
+    /// the environments themselves are used.
+    solve_prefix_dir: Option<PathBuf>,
 }
 
 impl<'p> UpdateContextBuilder<'p> {
@@ -726,6 +744,17 @@ impl<'p> UpdateContextBuilder<'p> {
         Self { no_install, ..self }
     }
 
+    /// Instantiate the prefixes that are required to solve pypi dependencies
+    /// in the given directory instead of in the environment prefixes. This
+    /// allows solving pypi dependencies without touching any environment,
+    /// even if `no_install` is set.
+    pub(crate) fn with_solve_prefix_dir(self, solve_prefix_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            solve_prefix_dir: Some(solve_prefix_dir.into()),
+            ..self
+        }
+    }
+
     /// Sets the current lock-file that should be used to determine the
     /// previously locked packages.
     pub(crate) fn with_lock_file(self, lock_file: LockFile) -> Self {
@@ -752,6 +781,15 @@ impl<'p> UpdateContextBuilder<'p> {
         }
     }
 
+    /// Excludes packages that were published after the given date, instead of
+    /// the date specified in the manifest.
+    pub(crate) fn with_exclude_newer(self, exclude_newer: Option<ExcludeNewer>) -> Self {
+        Self {
+            exclude_newer: exclude_newer.or(self.exclude_newer),
+            ..self
+        }
+    }
+
     /// Sets the io concurrency semaphore to use when updating environments.
     #[allow(unused)]
     pub fn with_io_concurrency_semaphore(self, io_concurrency_limit: IoConcurrencyLimit) -> Self {
@@ -951,6 +989,10 @@ impl<'p> UpdateContextBuilder<'p> {
             io_concurrency_limit: self.io_concurrency_limit.unwrap_or_default(),
 
             no_install: self.no_install,
+            solve_prefix_dir: self.solve_prefix_dir,
+            exclude_newer: self
+                .exclude_newer
+                .or(project.manifest.parsed.project.exclude_newer),
         })
     }
 }
@@ -966,6 +1008,8 @@ impl<'p> UpdateContext<'p> {
             package_cache: None,
             max_concurrent_solves: None,
             io_concurrency_limit: None,
+            exclude_newer: None,
+            solve_prefix_dir: None,
         }
     }
 
@@ -1044,6 +1088,7 @@ impl<'p> UpdateContext<'p> {
                     self.conda_solve_semaphore.clone(),
                     project.client().clone(),
                     channel_priority,
+                    self.exclude_newer,
                 )
                 .boxed_local();
 
@@ -1085,7 +1130,7 @@ impl<'p> UpdateContext<'p> {
             }
 
             // If we are not allowed to install, we can't instantiate a prefix.
-            if self.no_install {
+            if self.no_install && self.solve_prefix_dir.is_none() {
                 miette::bail!("Cannot update pypi dependencies without first installing a conda prefix that includes python.");
             }
 
@@ -1105,8 +1150,13 @@ impl<'p> UpdateContext<'p> {
 
             // Spawn a task to instantiate the environment
             let environment_name = environment.name().clone();
+            let prefix = match &self.solve_prefix_dir {
+                Some(dir) => Prefix::new(dir.join(group.name().as_str())),
+                None => group.prefix(),
+            };
             let pypi_env_task = spawn_create_prefix_task(
                 group.clone(),
+                prefix,
                 self.package_cache.clone(),
                 records_future,
                 self.io_concurrency_limit.clone(),
@@ -1196,6 +1246,7 @@ impl<'p> UpdateContext<'p> {
                 self.pypi_solve_semaphore.clone(),
                 project.root().to_path_buf(),
                 locked_group_records,
+                self.exclude_newer,
             );
 
             pending_futures.push(pypi_solve_future.boxed_local());
@@ -1426,6 +1477,15 @@ impl<'p> UpdateContext<'p> {
 
             builder.set_channels(&environment_name, channels);
 
+            // Store the strategy that was used to solve the environment.
+            builder.set_options(
+                &environment_name,
+                SolveOptions {
+                    strategy: grouped_env.solve_strategy().into(),
+                    ..SolveOptions::default()
+                },
+            );
+
             let mut has_pypi_records = false;
             for platform in environment.platforms() {
                 if let Some(records) = self.take_latest_repodata_records(&environment, platform) {
@@ -1460,7 +1520,13 @@ impl<'p> UpdateContext<'p> {
         Ok(LockFileDerivedData {
             project,
             lock_file,
-            updated_conda_prefixes: self.take_instantiated_conda_prefixes(),
+            // Prefixes that were only instantiated to solve pypi dependencies
+            // are not the prefixes of the environments.
+            updated_conda_prefixes: if self.solve_prefix_dir.is_some() {
+                HashMap::default()
+            } else {
+                self.take_instantiated_conda_prefixes()
+            },
             package_cache: self.package_cache,
             updated_pypi_prefixes: HashMap::default(),
             uv_context,
@@ -1560,6 +1626,7 @@ enum TaskResult {
 }
 
 /// A task that solves the conda dependencies for a given environment.
+#[allow(clippy::too_many_arguments)]
 async fn spawn_solve_conda_environment_task(
     group: GroupedEnvironment<'_>,
     existing_repodata_records: Arc<RepoDataRecordsByName>,
@@ -1568,9 +1635,11 @@ async fn spawn_solve_conda_environment_task(
     concurrency_semaphore: Arc<Semaphore>,
     client: reqwest::Client,
     channel_priority: ChannelPriority,
+    exclude_newer: Option<ExcludeNewer>,
 ) -> miette::Result<TaskResult> {
-    // Get the dependencies for this platform
+    // Get the dependencies and constraints for this platform
     let dependencies = group.dependencies(None, Some(platform));
+    let constraints = group.constraints(Some(platform));
 
     // Get the virtual packages for this platform
     let virtual_packages = group.virtual_packages(platform);
@@ -1587,9 +1656,15 @@ async fn spawn_solve_conda_environment_task(
     // Whether we should use custom mapping location
     let pypi_name_mapping_location = group.project().pypi_name_mapping_source()?.clone();
 
+    // Whether the pypi mapping may only be read from the cache
+    let offline = group.project().config().offline();
+
     // Get the channel configuration
     let channel_config = group.project().channel_config();
 
+    // Get the strategy used to select package versions
+    let solve_strategy = group.solve_strategy();
+
     tokio::spawn(
         async move {
             let _permit = concurrency_semaphore
@@ -1613,11 +1688,34 @@ async fn spawn_solve_conda_environment_task(
                     let nameless = constraint
                         .clone()
                         .try_into_nameless_match_spec(&channel_config)
-                        .unwrap()
-                        .expect("only binaries are supported at the moment");
-                    MatchSpec::from_nameless(nameless, Some(name.clone()))
+                        .into_diagnostic()?
+                        .ok_or_else(|| {
+                            miette::miette!(
+                                "'{}' is a source dependency, building conda packages from source is not supported yet",
+                                name.as_source()
+                            )
+                        })?;
+                    Ok(MatchSpec::from_nameless(nameless, Some(name.clone())))
                 })
-                .collect_vec();
+                .collect::<miette::Result<Vec<_>>>()?;
+
+            // Convert the constraints into match specs
+            let constraint_specs = constraints
+                .iter_specs()
+                .map(|(name, constraint)| {
+                    let nameless = constraint
+                        .clone()
+                        .try_into_nameless_match_spec(&channel_config)
+                        .into_diagnostic()?
+                        .ok_or_else(|| {
+                            miette::miette!(
+                                "the constraint on '{}' must refer to a binary package",
+                                name.as_source()
+                            )
+                        })?;
+                    Ok(MatchSpec::from_nameless(nameless, Some(name.clone())))
+                })
+                .collect::<miette::Result<Vec<_>>>()?;
 
             // Extract the repo data records needed to solve the environment.
             pb.set_message("loading repodata");
@@ -1645,10 +1743,13 @@ async fn spawn_solve_conda_environment_task(
             pb.set_message("resolving conda");
             let mut records = lock_file::resolve_conda(
                 match_specs,
+                constraint_specs,
                 virtual_packages,
                 existing_repodata_records.records.clone(),
                 available_packages,
                 channel_priority,
+                exclude_newer,
+                solve_strategy,
             )
             .await
             .with_context(|| {
@@ -1663,8 +1764,13 @@ async fn spawn_solve_conda_environment_task(
             // we need them.
             if has_pypi_dependencies {
                 pb.set_message("extracting pypi packages");
+                let client = if offline {
+                    pypi_mapping::cache_only_client(client.into())
+                } else {
+                    client.into()
+                };
                 pypi_mapping::amend_pypi_purls(
-                    client.into(),
+                    client,
                     &pypi_name_mapping_location,
                     &mut records,
                     Some(pb.purl_amend_reporter()),
@@ -1890,9 +1996,11 @@ async fn spawn_solve_pypi_task(
     semaphore: Arc<Semaphore>,
     project_root: PathBuf,
     locked_pypi_packages: Arc<PypiRecordsByName>,
+    exclude_newer: Option<ExcludeNewer>,
 ) -> miette::Result<TaskResult> {
-    // Get the Pypi dependencies for this environment
+    // Get the Pypi dependencies and constraints for this environment
     let dependencies = environment.pypi_dependencies(Some(platform));
+    let constraints = environment.pypi_constraints(Some(platform));
     if dependencies.is_empty() {
         return Ok(TaskResult::PypiGroupSolved(
             environment.name().clone(),
@@ -1916,15 +2024,18 @@ async fn spawn_solve_pypi_task(
     let mut conda_records = repodata_records.records.clone();
     let locked_pypi_records = locked_pypi_packages.records.clone();
 
-    pypi_mapping::amend_pypi_purls(
-        environment.project().client().clone().into(),
-        pypi_name_mapping_location,
-        &mut conda_records,
-        None,
-    )
-    .await?;
+    let client =
+        reqwest_middleware::ClientWithMiddleware::from(environment.project().client().clone());
+    let client = if environment.project().config().offline() {
+        pypi_mapping::cache_only_client(client)
+    } else {
+        client
+    };
+    pypi_mapping::amend_pypi_purls(client, pypi_name_mapping_location, &mut conda_records, None)
+        .await?;
 
     let pypi_options = environment.pypi_options();
+    let solve_strategy = environment.solve_strategy();
     // let (pypi_packages, duration) = tokio::spawn(
     let (pypi_packages, duration) = async move {
         let pb = SolveProgressBar::new(
@@ -1954,10 +2065,19 @@ async fn spawn_solve_pypi_task(
 
         let index_map = IndexMap::from_iter(dependencies);
 
+        let constraints: IndexMap<uv_normalize::PackageName, IndexSet<_>> = constraints
+            .into_iter()
+            .map(|(name, requirement)| Ok((to_uv_normalize(name.as_normalized())?, requirement)))
+            .collect::<Result<_, ConversionError>>()
+            .into_diagnostic()?;
+
         let records = lock_file::resolve_pypi(
             resolution_context,
             &pypi_options,
+            exclude_newer,
+            solve_strategy,
             index_map,
+            constraints,
             system_requirements,
             &conda_records,
             &locked_pypi_records,
@@ -2002,12 +2122,12 @@ async fn spawn_solve_pypi_task(
 /// available.
 async fn spawn_create_prefix_task(
     group: GroupedEnvironment<'_>,
+    prefix: Prefix,
     package_cache: PackageCache,
     conda_records: impl Future<Output = Arc<RepoDataRecordsByName>>,
     io_concurrency_limit: IoConcurrencyLimit,
 ) -> miette::Result<TaskResult> {
     let group_name = group.name().clone();
-    let prefix = group.prefix();
     let client = group.project().authenticated_client().clone();
 
     // Spawn a task to determine the currently installed packages.
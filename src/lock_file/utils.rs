@@ -44,6 +44,9 @@ pub fn filter_lock_file<'p, F: FnMut(&Environment<'p>, Platform, &Package) -> bo
         // Copy the channels
         builder.set_channels(environment_name, environment.channels().to_vec());
 
+        // Copy the solve options
+        builder.set_options(environment_name, environment.solve_options().clone());
+
         // Copy the indexes
         let indexes = environment.pypi_indexes().cloned().unwrap_or_else(|| {
             GroupedEnvironment::from(project_env.clone())
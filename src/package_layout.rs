@@ -0,0 +1,610 @@
+//! Lays out the files of extracted conda packages and unpacked wheels in a
+//! prefix without installing them, to export an environment to another
+//! machine. The prefix placeholders of the files are replaced by the consumer
+//! of the layout, once the location of the prefix is known.
+
+use std::path::{Path, PathBuf};
+
+use miette::{Context, IntoDiagnostic};
+use pixi_consts::consts;
+use pypi_modifiers::pypi_tags::is_python_record;
+use rattler_conda_types::{
+    package::{
+        FileMode, LinkJson, NoArchLinks, PackageFile, PathType, PathsJson, PrefixPlaceholder,
+    },
+    RepoDataRecord,
+};
+
+/// The placeholder of the files that are generated for a prefix, e.g. entry
+/// points. It is the placeholder that conda uses for text files.
+pub(crate) const SCRIPT_PLACEHOLDER: &str = "/opt/anaconda1anaconda2anaconda3";
+
+/// A path that a package adds to a prefix.
+#[derive(Debug)]
+pub(crate) struct PrefixPath {
+    /// The location of the path relative to the prefix, with `/` separators.
+    pub relative_path: String,
+
+    /// What is stored at the path.
+    pub kind: PrefixPathKind,
+}
+
+/// The kind of a path that a package adds to a prefix.
+#[derive(Debug)]
+pub(crate) enum PrefixPathKind {
+    Directory,
+
+    /// A symbolic link to the given target.
+    Symlink(PathBuf),
+
+    /// A file of the package. The placeholder in its content has to be
+    /// replaced by the location of the prefix.
+    File {
+        source: PathBuf,
+        placeholder: Option<PrefixPlaceholder>,
+    },
+
+    /// A file that is generated for the prefix, e.g. an entry point.
+    /// [`SCRIPT_PLACEHOLDER`] in its content has to be replaced by the location
+    /// of the prefix.
+    Generated {
+        content: Vec<u8>,
+        executable: bool,
+    },
+}
+
+impl PrefixPath {
+    fn generated(relative_path: String, content: Vec<u8>, executable: bool) -> Self {
+        Self {
+            relative_path,
+            kind: PrefixPathKind::Generated {
+                content,
+                executable,
+            },
+        }
+    }
+}
+
+/// Returns the major and minor version of the python interpreter in the
+/// given records.
+pub(crate) fn python_version(records: &[RepoDataRecord]) -> Option<(u64, u64)> {
+    records
+        .iter()
+        .find(|record| is_python_record(record))
+        .and_then(|record| record.package_record.version.as_major_minor())
+}
+
+/// Returns the paths that an extracted conda package adds to a prefix, in the
+/// order of its `paths.json`, followed by the entry points of noarch python
+/// packages.
+pub(crate) fn conda_package_paths(
+    package_dir: &Path,
+    record: &RepoDataRecord,
+    python_version: Option<(u64, u64)>,
+) -> miette::Result<Vec<PrefixPath>> {
+    let paths = PathsJson::from_package_directory_with_deprecated_fallback(package_dir)
+        .into_diagnostic()
+        .with_context(|| format!("failed to read the paths of '{}'", record.file_name))?;
+
+    // The files of noarch python packages are installed into the site-packages
+    // of the python interpreter of the environment.
+    let python_version = if record.package_record.noarch.is_python() {
+        Some(python_version.ok_or_else(|| {
+            miette::miette!(
+                "the noarch python package '{}' requires python in the environment",
+                record.file_name
+            )
+        })?)
+    } else {
+        None
+    };
+
+    let mut result = Vec::with_capacity(paths.paths.len());
+    for entry in paths.paths {
+        let source = package_dir.join(&entry.relative_path);
+        let relative_path = entry.relative_path.to_string_lossy().replace('\\', "/");
+        let relative_path = match python_version {
+            Some(version) => noarch_python_path(&relative_path, version),
+            None => relative_path,
+        };
+        let kind = match entry.path_type {
+            PathType::Directory => PrefixPathKind::Directory,
+            PathType::SoftLink => {
+                PrefixPathKind::Symlink(fs_err::read_link(&source).into_diagnostic()?)
+            }
+            PathType::HardLink => PrefixPathKind::File {
+                source,
+                placeholder: entry.prefix_placeholder,
+            },
+        };
+        result.push(PrefixPath {
+            relative_path,
+            kind,
+        });
+    }
+
+    if let Some(version) = python_version {
+        if package_dir.join("info/link.json").is_file() {
+            let link = LinkJson::from_package_directory(package_dir)
+                .into_diagnostic()
+                .with_context(|| format!("failed to read the links of '{}'", record.file_name))?;
+            if let NoArchLinks::Python(entry_points) = link.noarch {
+                for entry_point in entry_points.entry_points {
+                    result.push(PrefixPath::generated(
+                        format!("bin/{}", entry_point.command),
+                        entry_point_script(&entry_point.module, &entry_point.function, version)
+                            .into_bytes(),
+                        true,
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns the record of a conda package in the `conda-meta` directory of a
+/// prefix, which lists the files that the package adds to the prefix.
+pub(crate) fn conda_meta_path(
+    record: &RepoDataRecord,
+    paths: &[PrefixPath],
+) -> miette::Result<PrefixPath> {
+    let files = paths
+        .iter()
+        .filter(|path| !matches!(path.kind, PrefixPathKind::Directory))
+        .map(|path| path.relative_path.as_str())
+        .collect::<Vec<_>>();
+    let mut prefix_record = serde_json::to_value(record).into_diagnostic()?;
+    prefix_record["files"] = serde_json::json!(files);
+    Ok(PrefixPath::generated(
+        format!(
+            "conda-meta/{}-{}-{}.json",
+            record.package_record.name.as_normalized(),
+            record.package_record.version,
+            record.package_record.build
+        ),
+        serde_json::to_vec_pretty(&prefix_record).into_diagnostic()?,
+        false,
+    ))
+}
+
+/// Returns the paths that an unpacked wheel adds to a prefix, following the
+/// scheme of a conda environment on unix.
+pub(crate) fn wheel_paths(
+    wheel_dir: &Path,
+    (major, minor): (u64, u64),
+) -> miette::Result<Vec<PrefixPath>> {
+    let site_packages = format!("lib/python{major}.{minor}/site-packages");
+    let mut result = Vec::new();
+    let mut dist_info = None;
+    for relative_path in files_in(wheel_dir)? {
+        let source = wheel_dir.join(&relative_path);
+        let (top_level, rest) = relative_path
+            .split_once('/')
+            .unwrap_or((relative_path.as_str(), ""));
+        if top_level.ends_with(".dist-info") {
+            dist_info = Some(top_level.to_string());
+        }
+
+        let Some(name) = top_level.strip_suffix(".data").filter(|_| !rest.is_empty()) else {
+            result.push(PrefixPath {
+                relative_path: format!("{site_packages}/{relative_path}"),
+                kind: PrefixPathKind::File {
+                    source,
+                    placeholder: None,
+                },
+            });
+            continue;
+        };
+
+        let (scheme, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let relative_path = match scheme {
+            "purelib" | "platlib" => format!("{site_packages}/{path}"),
+            "data" => path.to_string(),
+            "headers" => {
+                let name = name.split_once('-').map_or(name, |(name, _)| name);
+                format!("include/python{major}.{minor}/{name}/{path}")
+            }
+            "scripts" => {
+                // Scripts that start with `#!python` are run by the python
+                // interpreter of the environment.
+                let content = fs_err::read(&source).into_diagnostic()?;
+                let content = match content.strip_prefix(b"#!python") {
+                    Some(rest) => {
+                        let rest = rest.strip_prefix(b"w").unwrap_or(rest);
+                        let mut script =
+                            format!("#!{SCRIPT_PLACEHOLDER}/bin/python{major}.{minor}")
+                                .into_bytes();
+                        script.extend_from_slice(rest);
+                        script
+                    }
+                    None => content,
+                };
+                result.push(PrefixPath::generated(format!("bin/{path}"), content, true));
+                continue;
+            }
+            _ => miette::bail!(
+                "the wheel in '{}' contains the unknown directory '{top_level}/{scheme}'",
+                wheel_dir.display()
+            ),
+        };
+        result.push(PrefixPath {
+            relative_path,
+            kind: PrefixPathKind::File {
+                source,
+                placeholder: None,
+            },
+        });
+    }
+
+    let dist_info = dist_info.ok_or_else(|| {
+        miette::miette!(
+            "the wheel in '{}' does not contain a .dist-info directory",
+            wheel_dir.display()
+        )
+    })?;
+    let entry_points = wheel_dir.join(&dist_info).join("entry_points.txt");
+    if entry_points.is_file() {
+        let entry_points = fs_err::read_to_string(&entry_points).into_diagnostic()?;
+        for (command, module, function) in console_scripts(&entry_points) {
+            result.push(PrefixPath::generated(
+                format!("bin/{command}"),
+                entry_point_script(module, function, (major, minor)).into_bytes(),
+                true,
+            ));
+        }
+    }
+    result.push(PrefixPath::generated(
+        format!("{site_packages}/{dist_info}/INSTALLER"),
+        format!("{}\n", consts::PIXI_UV_INSTALLER).into_bytes(),
+        false,
+    ));
+
+    Ok(result)
+}
+
+/// Returns the paths of the files in a directory relative to it, recursively
+/// and sorted.
+fn files_in(dir: &Path) -> miette::Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs_err::read_dir(&current).into_diagnostic()? {
+            let path = entry.into_diagnostic()?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative_path) = path.strip_prefix(dir) {
+                files.push(relative_path.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parses the console and gui scripts of the `entry_points.txt` of a wheel
+/// into their command, module and function.
+fn console_scripts(entry_points: &str) -> Vec<(&str, &str, &str)> {
+    let mut scripts = Vec::new();
+    let mut in_scripts = false;
+    for line in entry_points.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_scripts = matches!(line, "[console_scripts]" | "[gui_scripts]");
+            continue;
+        }
+        if !in_scripts {
+            continue;
+        }
+        let Some((command, target)) = line.split_once('=') else {
+            continue;
+        };
+        // Extras of an entry point, e.g. `cli = foo:main [extra]`, are ignored.
+        let target = target.split('[').next().unwrap_or_default().trim();
+        if let Some((module, function)) = target.split_once(':') {
+            scripts.push((command.trim(), module.trim(), function.trim()));
+        }
+    }
+    scripts
+}
+
+/// Returns the location of a file of a noarch python package in the prefix.
+fn noarch_python_path(relative_path: &str, (major, minor): (u64, u64)) -> String {
+    if let Some(path) = relative_path.strip_prefix("site-packages/") {
+        format!("lib/python{major}.{minor}/site-packages/{path}")
+    } else if let Some(path) = relative_path.strip_prefix("python-scripts/") {
+        format!("bin/{path}")
+    } else {
+        relative_path.to_string()
+    }
+}
+
+/// Renders the script of a python entry point, the same way conda creates
+/// them. The interpreter is located in [`SCRIPT_PLACEHOLDER`].
+fn entry_point_script(module: &str, function: &str, (major, minor): (u64, u64)) -> String {
+    let import_name = function.split_once('.').map_or(function, |(name, _)| name);
+    format!(
+        "#!{SCRIPT_PLACEHOLDER}/bin/python{major}.{minor}\n\
+         # -*- coding: utf-8 -*-\n\
+         import re\n\
+         import sys\n\
+         \n\
+         from {module} import {import_name}\n\
+         \n\
+         if __name__ == '__main__':\n    \
+         sys.argv[0] = re.sub(r'(-script\\.pyw?|\\.exe)?$', '', sys.argv[0])\n    \
+         sys.exit({function}())\n"
+    )
+}
+
+/// A null terminated string in a binary file that contains a prefix
+/// placeholder.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct BinarySegment<'a> {
+    /// The offset of the string in the file.
+    pub offset: usize,
+
+    /// The string split at the occurrences of the placeholder.
+    pub parts: Vec<&'a [u8]>,
+}
+
+/// Returns the null terminated strings of a binary file that contain the
+/// placeholder.
+pub(crate) fn binary_segments<'a>(content: &'a [u8], placeholder: &[u8]) -> Vec<BinarySegment<'a>> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = find(&content[offset..], placeholder).map(|start| offset + start) {
+        let end = content[start..]
+            .iter()
+            .position(|&byte| byte == 0)
+            .map_or(content.len(), |len| start + len);
+        let mut parts = vec![&content[start..start]];
+        let mut rest = &content[start + placeholder.len()..end];
+        while let Some(next) = find(rest, placeholder) {
+            parts.push(&rest[..next]);
+            rest = &rest[next + placeholder.len()..];
+        }
+        parts.push(rest);
+        segments.push(BinarySegment {
+            offset: start,
+            parts,
+        });
+        offset = end;
+    }
+    segments
+}
+
+/// Replaces the prefix placeholder in the content of a file by the location of
+/// the prefix, the same way conda installs packages.
+///
+/// In binary files the rest of the null terminated string that contains the
+/// placeholder is moved and padded with null bytes, which requires the prefix
+/// to not be longer than the placeholder.
+pub(crate) fn replace_prefix(
+    content: &[u8],
+    placeholder: &PrefixPlaceholder,
+    prefix: &str,
+) -> miette::Result<Vec<u8>> {
+    let from = placeholder.placeholder.as_bytes();
+    let to = prefix.as_bytes();
+    if matches!(placeholder.file_mode, FileMode::Text) {
+        return Ok(replace_all(content, from, to));
+    }
+    if to.len() > from.len() {
+        miette::bail!(
+            "the prefix '{prefix}' is longer than the placeholder '{}' of a binary file",
+            placeholder.placeholder
+        );
+    }
+
+    let mut result = content.to_vec();
+    for segment in binary_segments(content, from) {
+        let length = segment.parts.iter().map(|part| part.len()).sum::<usize>()
+            + (segment.parts.len() - 1) * from.len();
+        let mut replaced = segment.parts.join(to);
+        replaced.resize(length, 0);
+        result[segment.offset..segment.offset + length].copy_from_slice(&replaced);
+    }
+    Ok(result)
+}
+
+/// Returns the offset of the first occurrence of `needle` in `haystack`.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Replaces all occurrences of `from` in `content` by `to`.
+pub(crate) fn replace_all(content: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = find(rest, from) {
+        result.extend_from_slice(&rest[..start]);
+        result.extend_from_slice(to);
+        rest = &rest[start + from.len()..];
+    }
+    result.extend_from_slice(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rattler_conda_types::{PackageRecord, VersionWithSource};
+    use serde_json::json;
+    use url::Url;
+
+    use super::*;
+
+    fn record(file_name: &str) -> RepoDataRecord {
+        RepoDataRecord {
+            package_record: PackageRecord::new(
+                "foo".parse().unwrap(),
+                VersionWithSource::from_str("1.0").unwrap(),
+                "h123_0".to_string(),
+            ),
+            file_name: file_name.to_string(),
+            url: Url::from_str("https://example.com/foo").unwrap(),
+            channel: String::from("https://example.com"),
+        }
+    }
+
+    #[test]
+    fn test_replace_prefix() {
+        let text = PrefixPlaceholder {
+            file_mode: FileMode::Text,
+            placeholder: String::from(SCRIPT_PLACEHOLDER),
+        };
+        assert_eq!(
+            replace_prefix(
+                b"#!/opt/anaconda1anaconda2anaconda3/bin/python\nprefix=/opt/anaconda1anaconda2anaconda3\n",
+                &text,
+                "/opt/pixi/envs/default",
+            )
+            .unwrap(),
+            b"#!/opt/pixi/envs/default/bin/python\nprefix=/opt/pixi/envs/default\n"
+        );
+
+        let binary = PrefixPlaceholder {
+            file_mode: FileMode::Binary,
+            placeholder: String::from("/home/placeholder"),
+        };
+        let content = b"\x7fELF\0/home/placeholder/lib:/home/placeholder/lib64\0rest\0";
+        let replaced = replace_prefix(content, &binary, "/opt/env").unwrap();
+        assert_eq!(replaced.len(), content.len());
+        assert_eq!(
+            replaced,
+            b"\x7fELF\0/opt/env/lib:/opt/env/lib64\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0rest\0"
+        );
+
+        // The prefix of a binary file can't grow.
+        assert!(replace_prefix(content, &binary, "/opt/pixi/envs/default").is_err());
+    }
+
+    #[test]
+    fn test_binary_segments() {
+        let content = b"a\0/p/lib:/p/lib64\0b/p\0";
+        assert_eq!(
+            binary_segments(content, b"/p"),
+            vec![
+                BinarySegment {
+                    offset: 2,
+                    parts: vec![b"".as_slice(), b"/lib:".as_slice(), b"/lib64".as_slice()],
+                },
+                BinarySegment {
+                    offset: 19,
+                    parts: vec![b"".as_slice(), b"".as_slice()],
+                },
+            ]
+        );
+        assert!(binary_segments(b"no placeholder", b"/p").is_empty());
+    }
+
+    #[test]
+    fn test_conda_package_paths() {
+        let package_dir = tempfile::tempdir().unwrap();
+        fs_err::create_dir_all(package_dir.path().join("info")).unwrap();
+        fs_err::create_dir_all(package_dir.path().join("site-packages/foo")).unwrap();
+        fs_err::write(package_dir.path().join("site-packages/foo/__init__.py"), "").unwrap();
+        fs_err::write(
+            package_dir.path().join("info/paths.json"),
+            serde_json::to_vec(&json!({
+                "paths": [{ "_path": "site-packages/foo/__init__.py", "path_type": "hardlink" }],
+                "paths_version": 1,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        fs_err::write(
+            package_dir.path().join("info/link.json"),
+            serde_json::to_vec(&json!({
+                "noarch": { "type": "python", "entry_points": ["foo = foo.cli:main"] },
+                "package_metadata_version": 1,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        let mut record = record("foo-1.0-h123_0.conda");
+        record.package_record.noarch = rattler_conda_types::NoArchType::python();
+
+        let paths = conda_package_paths(package_dir.path(), &record, Some((3, 12))).unwrap();
+        assert_eq!(
+            paths
+                .iter()
+                .map(|path| path.relative_path.as_str())
+                .collect::<Vec<_>>(),
+            ["lib/python3.12/site-packages/foo/__init__.py", "bin/foo"]
+        );
+        assert!(matches!(
+            &paths[1].kind,
+            PrefixPathKind::Generated {
+                executable: true,
+                ..
+            }
+        ));
+
+        // Noarch python packages can only be added to an environment with python.
+        assert!(conda_package_paths(package_dir.path(), &record, None).is_err());
+    }
+
+    #[test]
+    fn test_wheel_paths() {
+        let wheel_dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("foo/__init__.py", ""),
+            (
+                "foo-1.0.dist-info/entry_points.txt",
+                "[console_scripts]\nfoo = foo.cli:main [extra]\n\n[other]\nbar = bar:main\n",
+            ),
+            ("foo-1.0.data/scripts/run", "#!python\nprint('hi')\n"),
+            ("foo-1.0.data/data/share/foo.txt", ""),
+        ];
+        for (path, content) in files {
+            let path = wheel_dir.path().join(path);
+            fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+            fs_err::write(path, content).unwrap();
+        }
+
+        let paths = wheel_paths(wheel_dir.path(), (3, 12)).unwrap();
+        assert_eq!(
+            paths
+                .iter()
+                .map(|path| path.relative_path.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "share/foo.txt",
+                "bin/run",
+                "lib/python3.12/site-packages/foo-1.0.dist-info/entry_points.txt",
+                "lib/python3.12/site-packages/foo/__init__.py",
+                "bin/foo",
+                "lib/python3.12/site-packages/foo-1.0.dist-info/INSTALLER",
+            ]
+        );
+        let PrefixPathKind::Generated { content, .. } = &paths[1].kind else {
+            panic!("expected a generated script");
+        };
+        assert_eq!(
+            String::from_utf8_lossy(content),
+            "#!/opt/anaconda1anaconda2anaconda3/bin/python3.12\nprint('hi')\n"
+        );
+    }
+
+    #[test]
+    fn test_entry_point_script() {
+        assert_eq!(
+            entry_point_script("foo.cli", "main.run", (3, 12)),
+            "#!/opt/anaconda1anaconda2anaconda3/bin/python3.12\n\
+             # -*- coding: utf-8 -*-\n\
+             import re\n\
+             import sys\n\
+             \n\
+             from foo.cli import main\n\
+             \n\
+             if __name__ == '__main__':\n    \
+             sys.argv[0] = re.sub(r'(-script\\.pyw?|\\.exe)?$', '', sys.argv[0])\n    \
+             sys.exit(main.run())\n"
+        );
+    }
+}
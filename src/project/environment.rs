@@ -4,6 +4,7 @@ use std::{
     fmt::Debug,
     fs,
     hash::{Hash, Hasher},
+    path::PathBuf,
     sync::Once,
 };
 
@@ -268,6 +269,19 @@ impl<'p> Environment<'p> {
             })
     }
 
+    /// Returns the dotenv files that should be loaded when activating this
+    /// environment.
+    ///
+    /// The files of all features are combined in the order they are defined
+    /// for the environment.
+    pub(crate) fn activation_env_files(&self, platform: Option<Platform>) -> Vec<PathBuf> {
+        self.features()
+            .filter_map(|f| f.activation_env_files(platform))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
     /// Validates that the given platform is supported by this environment.
     fn validate_platform_support(
         &self,
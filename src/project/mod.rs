@@ -132,6 +132,9 @@ pub struct Project {
     /// The environment variables that are activated when the environment is
     /// activated. Cached per environment, for both clean and normal
     env_vars: HashMap<EnvironmentName, EnvironmentVars>,
+    /// The same as `env_vars` but including the variables of the `env-file`s
+    /// of the activation.
+    env_file_vars: HashMap<EnvironmentName, EnvironmentVars>,
     /// The cache that contains mapping
     mapping_source: OnceCell<MappingSource>,
     /// The global configuration as loaded from the config file(s)
@@ -157,6 +160,7 @@ impl Project {
     /// Constructs a new instance from an internal manifest representation
     pub(crate) fn from_manifest(manifest: Manifest) -> Self {
         let env_vars = Project::init_env_vars(&manifest.parsed.environments);
+        let env_file_vars = Project::init_env_vars(&manifest.parsed.environments);
 
         let root = manifest
             .path
@@ -171,6 +175,7 @@ impl Project {
             client: Default::default(),
             manifest,
             env_vars,
+            env_file_vars,
             mapping_source: Default::default(),
             config,
             repodata_gateway: Default::default(),
@@ -436,7 +441,35 @@ impl Project {
         environment: &Environment<'_>,
         current_env_var_behavior: CurrentEnvVarBehavior,
     ) -> miette::Result<&HashMap<String, String>> {
-        let vars = self.env_vars.get(environment.name()).ok_or_else(|| {
+        self.activated_environment_variables(environment, current_env_var_behavior, false)
+            .await
+    }
+
+    /// Get or initialize the activated environment variables including the
+    /// variables of the `env-file`s of the activation. Only the commands that
+    /// run in the environment load the env-files, so that e.g. solving and
+    /// installing work on a checkout without them.
+    pub async fn get_activated_environment_variables_with_env_files(
+        &self,
+        environment: &Environment<'_>,
+        current_env_var_behavior: CurrentEnvVarBehavior,
+    ) -> miette::Result<&HashMap<String, String>> {
+        self.activated_environment_variables(environment, current_env_var_behavior, true)
+            .await
+    }
+
+    async fn activated_environment_variables(
+        &self,
+        environment: &Environment<'_>,
+        current_env_var_behavior: CurrentEnvVarBehavior,
+        include_env_files: bool,
+    ) -> miette::Result<&HashMap<String, String>> {
+        let env_vars = if include_env_files {
+            &self.env_file_vars
+        } else {
+            &self.env_vars
+        };
+        let vars = env_vars.get(environment.name()).ok_or_else(|| {
             miette::miette!(
                 "{} environment should be already created during project creation",
                 environment.name()
@@ -446,21 +479,36 @@ impl Project {
             CurrentEnvVarBehavior::Clean => {
                 vars.clean()
                     .get_or_try_init(async {
-                        initialize_env_variables(environment, current_env_var_behavior).await
+                        initialize_env_variables(
+                            environment,
+                            current_env_var_behavior,
+                            include_env_files,
+                        )
+                        .await
                     })
                     .await
             }
             CurrentEnvVarBehavior::Exclude => {
                 vars.pixi_only()
                     .get_or_try_init(async {
-                        initialize_env_variables(environment, current_env_var_behavior).await
+                        initialize_env_variables(
+                            environment,
+                            current_env_var_behavior,
+                            include_env_files,
+                        )
+                        .await
                     })
                     .await
             }
             CurrentEnvVarBehavior::Include => {
                 vars.full()
                     .get_or_try_init(async {
-                        initialize_env_variables(environment, current_env_var_behavior).await
+                        initialize_env_variables(
+                            environment,
+                            current_env_var_behavior,
+                            include_env_files,
+                        )
+                        .await
                     })
                     .await
             }
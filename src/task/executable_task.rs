@@ -2,6 +2,7 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
     path::PathBuf,
 };
 
@@ -12,10 +13,16 @@ use itertools::Itertools;
 use miette::{Context, Diagnostic, IntoDiagnostic};
 use thiserror::Error;
 use tokio::task::JoinHandle;
+use xxhash_rust::xxh3::Xxh3;
 
-use super::task_hash::{InputHashesError, TaskCache, TaskHash};
+use super::{
+    output_cache::OutputCache,
+    task_args::{quote_shell_word, render_task_command, TaskArgValues},
+    task_condition::{evaluate_condition, TaskConditionError},
+    task_hash::{InputHashesError, TaskCache, TaskHash},
+};
 use crate::{
-    lock_file::LockFileDerivedData,
+    dotenv::{load_env_files, DotenvError},
     project::Environment,
     task::task_graph::{TaskGraph, TaskId},
     Project,
@@ -27,6 +34,7 @@ use crate::project::virtual_packages::verify_current_platform_has_required_virtu
 use crate::project::HasProjectRef;
 use pixi_manifest::{Task, TaskName};
 use pixi_progress::await_in_progress;
+use rattler_lock::LockFile;
 
 /// Runs task in project.
 #[derive(Default, Debug)]
@@ -56,6 +64,9 @@ pub enum TaskExecutionError {
 
     #[error(transparent)]
     FailedToParseShellScript(#[from] FailedToParseShellScript),
+
+    #[error(transparent)]
+    Dotenv(#[from] DotenvError),
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -71,7 +82,7 @@ pub enum CacheUpdateError {
 }
 
 pub enum CanSkip {
-    Yes,
+    Yes(TaskHash),
     No(Option<TaskHash>),
 }
 
@@ -85,6 +96,7 @@ pub struct ExecutableTask<'p> {
     pub task: Cow<'p, Task>,
     pub run_environment: Environment<'p>,
     pub additional_args: Vec<String>,
+    pub args: Option<TaskArgValues>,
 }
 
 impl<'p> ExecutableTask<'p> {
@@ -97,6 +109,7 @@ impl<'p> ExecutableTask<'p> {
             task: node.task.clone(),
             run_environment: node.run_environment.clone(),
             additional_args: node.additional_args.clone(),
+            args: node.args.clone(),
         }
     }
 
@@ -115,10 +128,37 @@ impl<'p> ExecutableTask<'p> {
         self.project
     }
 
+    /// Returns the command of the task with the values of the arguments of the
+    /// task substituted.
+    fn command(&self) -> Result<Option<Cow<'_, str>>, minijinja::Error> {
+        let Some(command) = self.task.as_single_command() else {
+            return Ok(None);
+        };
+        match &self.args {
+            Some(args) => Ok(Some(Cow::Owned(render_task_command(&command, args)?))),
+            None => Ok(Some(command)),
+        }
+    }
+
+    /// Returns the command of the task with the values of the arguments of the
+    /// task substituted, failing if they can not be substituted.
+    fn rendered_command(&self) -> Result<Option<Cow<'_, str>>, FailedToParseShellScript> {
+        self.command().map_err(|e| FailedToParseShellScript {
+            script: self
+                .task
+                .as_single_command()
+                .unwrap_or_default()
+                .into_owned(),
+            error: e.to_string(),
+        })
+    }
+
     /// Returns the task as script
-    fn as_script(&self) -> Option<String> {
+    fn as_script(&self) -> Result<Option<String>, FailedToParseShellScript> {
         // Convert the task into an executable string
-        let task = self.task.as_single_command()?;
+        let Some(task) = self.rendered_command()? else {
+            return Ok(None);
+        };
 
         // Get the export specific environment variables
         let export = get_export_specific_task_env(self.task.as_ref());
@@ -127,7 +167,7 @@ impl<'p> ExecutableTask<'p> {
         let cli_args = self
             .additional_args
             .iter()
-            .format_with(" ", |arg, f| f(&format_args!("'{}'", arg)));
+            .format_with(" ", |arg, f| f(&quote_shell_word(arg)));
 
         // Skip the export if it's empty, to avoid newlines
         let full_script = if export.is_empty() {
@@ -136,7 +176,7 @@ impl<'p> ExecutableTask<'p> {
             format!("{export}\n{task} {cli_args}")
         };
 
-        Some(full_script)
+        Ok(Some(full_script))
     }
 
     /// Returns a [`SequentialList`] which can be executed by deno task shell.
@@ -145,7 +185,7 @@ impl<'p> ExecutableTask<'p> {
     pub(crate) fn as_deno_script(
         &self,
     ) -> Result<Option<SequentialList>, FailedToParseShellScript> {
-        if let Some(full_script) = self.as_script() {
+        if let Some(full_script) = self.as_script()? {
             tracing::debug!("Parsing shell script: {}", full_script);
 
             // Parse the shell command
@@ -177,21 +217,53 @@ impl<'p> ExecutableTask<'p> {
         })
     }
 
+    /// Evaluates the `condition` of the task against the environment the task
+    /// is executed in: the `command_env` of the task, which contains the
+    /// activated environment and the variables of the `env-file`s, overlaid
+    /// with the `env` of the task. Returns `true` if the task has no
+    /// condition.
+    pub(crate) fn condition_met(
+        &self,
+        command_env: &HashMap<String, String>,
+    ) -> Result<bool, TaskConditionError> {
+        let Some(condition) = self.task.condition() else {
+            return Ok(true);
+        };
+        let mut env_vars = command_env.clone();
+        if let Some(env) = self.task.env() {
+            env_vars.extend(env.iter().map(|(key, value)| (key.clone(), value.clone())));
+        }
+        evaluate_condition(
+            condition,
+            self.project.root(),
+            self.run_environment.best_platform(),
+            &env_vars,
+        )
+        .map_err(|source| TaskConditionError {
+            task: self.name.clone().unwrap_or_else(|| "unnamed".into()),
+            condition: condition.to_string(),
+            source,
+        })
+    }
+
     /// Returns the full command that should be executed for this task. This
     /// includes any additional arguments that should be passed to the
     /// command.
     ///
     /// This function returns `None` if the task does not define a command to
     /// execute. This is the case for alias only commands.
-    pub(crate) fn full_command(&self) -> Option<String> {
-        let mut cmd = self.task.as_single_command()?.to_string();
+    pub(crate) fn full_command(&self) -> Result<Option<String>, FailedToParseShellScript> {
+        let Some(command) = self.rendered_command()? else {
+            return Ok(None);
+        };
+        let mut cmd = command.into_owned();
 
         if !self.additional_args.is_empty() {
             cmd.push(' ');
             cmd.push_str(&self.additional_args.join(" "));
         }
 
-        Some(cmd)
+        Ok(Some(cmd))
     }
 
     /// Returns an object that implements [`Display`] which outputs the command
@@ -221,7 +293,9 @@ impl<'p> ExecutableTask<'p> {
         drop(stdin_writer); // prevent a deadlock by dropping the writer
         let (stdout, stdout_handle) = get_output_writer_and_handle();
         let (stderr, stderr_handle) = get_output_writer_and_handle();
-        let state = ShellState::new(command_env.clone(), &cwd, Default::default());
+        let mut command_env = command_env.clone();
+        self.apply_env_files(&mut command_env)?;
+        let state = ShellState::new(command_env, &cwd, Default::default());
         let code = execute_with_pipes(script, state, stdin, stdout, stderr).await;
         Ok(RunOutput {
             exit_code: code,
@@ -230,71 +304,220 @@ impl<'p> ExecutableTask<'p> {
         })
     }
 
+    /// Adds the variables from the dotenv files of the task to `command_env`.
+    /// Variables that are referenced in the files but not defined in them are
+    /// expanded from `command_env`.
+    pub(crate) fn apply_env_files(
+        &self,
+        command_env: &mut HashMap<String, String>,
+    ) -> Result<(), DotenvError> {
+        let env_files = self.task.env_files();
+        if env_files.is_empty() {
+            return Ok(());
+        }
+        let vars = load_env_files(
+            env_files.iter().map(PathBuf::as_path),
+            self.project().root(),
+            command_env,
+        )?;
+        command_env.extend(vars);
+        Ok(())
+    }
+
     /// We store the hashes of the inputs and the outputs of the task in a file
     /// in the cache. The current name is something like
-    /// `run_environment-task_name.json`.
+    /// `run_environment-task_name.json`. Tasks that are invoked with arguments
+    /// also include a hash of the argument values, e.g.
+    /// `run_environment-task_name-1a2b3c.json`, so that every invocation is
+    /// cached separately.
     pub(crate) fn cache_name(&self) -> String {
-        format!(
-            "{}-{}.json",
+        let name = format!(
+            "{}-{}",
             self.run_environment.name(),
             self.name().unwrap_or("default")
-        )
+        );
+        match &self.args {
+            Some(args) if !args.is_empty() => {
+                let mut hasher = Xxh3::new();
+                for (arg, value) in args {
+                    arg.hash(&mut hasher);
+                    value.hash(&mut hasher);
+                }
+                format!("{name}-{:x}.json", hasher.finish())
+            }
+            _ => format!("{name}.json"),
+        }
+    }
+
+    /// Returns true if the task would be skipped because the hash of its
+    /// inputs and outputs matches the hash of its last run. Unlike
+    /// [`Self::can_skip`] this only consults the local task cache and never
+    /// restores outputs.
+    pub(crate) async fn is_cached(&self, lock_file: &LockFile) -> Result<bool, std::io::Error> {
+        let cache_file = self.project().task_cache_folder().join(self.cache_name());
+        if !cache_file.exists() {
+            return Ok(false);
+        }
+        let cache = tokio::fs::read_to_string(&cache_file).await?;
+        let cache: TaskCache = serde_json::from_str(&cache)?;
+        let hash = TaskHash::from_task(self, lock_file).await.ok().flatten();
+        Ok(hash.is_some_and(|hash| hash.computation_hash() == cache.hash))
     }
 
     /// Checks if the task can be skipped. If the task can be skipped, it
-    /// returns `CanSkip::Yes`. If the task cannot be skipped, it returns
+    /// returns `CanSkip::Yes` with the hash of the task. If the task cannot be skipped, it returns
     /// `CanSkip::No` and includes the hash of the task that caused the task
     /// to not be skipped - we can use this later to update the cache file
     /// quickly.
-    pub(crate) async fn can_skip(
-        &self,
-        lock_file: &LockFileDerivedData<'p>,
-    ) -> Result<CanSkip, std::io::Error> {
+    ///
+    /// If a shared task cache is configured and the outputs of the task are
+    /// stored in it, the outputs are restored and the task is skipped.
+    pub(crate) async fn can_skip(&self, lock_file: &LockFile) -> Result<CanSkip, std::io::Error> {
         tracing::info!("Checking if task can be skipped");
         let cache_name = self.cache_name();
         let cache_file = self.project().task_cache_folder().join(cache_name);
+        let mut hash = None;
         if cache_file.exists() {
             let cache = tokio::fs::read_to_string(&cache_file).await?;
             let cache: TaskCache = serde_json::from_str(&cache)?;
-            let hash = TaskHash::from_task(self, &lock_file.lock_file).await;
-            if let Ok(Some(hash)) = hash {
-                if hash.computation_hash() != cache.hash {
-                    return Ok(CanSkip::No(Some(hash)));
-                } else {
-                    return Ok(CanSkip::Yes);
+            hash = TaskHash::from_task(self, lock_file).await.ok().flatten();
+            if let Some(task_hash) = hash {
+                if task_hash.computation_hash() == cache.hash {
+                    return Ok(CanSkip::Yes(task_hash));
+                }
+                hash = Some(task_hash);
+            }
+        }
+
+        if let Some(output_cache) = self.output_cache() {
+            if hash.is_none() {
+                hash = TaskHash::from_task(self, lock_file).await.ok().flatten();
+            }
+            if let Some(mut hash) = hash {
+                if self.restore_outputs(&output_cache, &mut hash).await {
+                    return Ok(CanSkip::Yes(hash));
                 }
+                return Ok(CanSkip::No(Some(hash)));
             }
         }
-        Ok(CanSkip::No(None))
+
+        Ok(CanSkip::No(hash))
     }
 
     /// Saves the cache of the task. This function will update the cache file
     /// with the new hash of the task (inputs and outputs). If the task has
-    /// no hash, it will not save the cache.
+    /// no hash, it will not save the cache. Returns the new hash of the task.
+    ///
+    /// If a shared task cache is configured, the outputs of the task are also
+    /// uploaded to it.
     pub(crate) async fn save_cache(
         &self,
-        lock_file: &LockFileDerivedData<'_>,
+        lock_file: &LockFile,
         previous_hash: Option<TaskHash>,
-    ) -> Result<(), CacheUpdateError> {
-        let task_cache_folder = self.project().task_cache_folder();
-        let cache_file = task_cache_folder.join(self.cache_name());
+    ) -> Result<Option<TaskHash>, CacheUpdateError> {
         let new_hash = if let Some(mut previous_hash) = previous_hash {
             previous_hash.update_output(self).await?;
             previous_hash
-        } else if let Some(hash) = TaskHash::from_task(self, &lock_file.lock_file).await? {
+        } else if let Some(hash) = TaskHash::from_task(self, lock_file).await? {
             hash
         } else {
-            return Ok(());
+            return Ok(None);
         };
 
+        self.write_cache_file(&new_hash).await?;
+
+        let read_only = self
+            .project()
+            .config()
+            .task_cache()
+            .is_some_and(|config| config.read_only());
+        if let Some(output_cache) = self.output_cache().filter(|_| !read_only) {
+            let files = new_hash
+                .outputs
+                .iter()
+                .flat_map(|outputs| outputs.files.files.keys().cloned())
+                .collect_vec();
+            if !files.is_empty() {
+                if let Err(err) = output_cache
+                    .store(&new_hash.output_cache_key(), self.project().root(), files)
+                    .await
+                {
+                    tracing::warn!(
+                        "failed to upload the outputs of task '{}' to the task cache: {err}",
+                        self.name().unwrap_or_default()
+                    );
+                }
+            }
+        }
+
+        Ok(Some(new_hash))
+    }
+
+    /// Writes the computation hash of the task to the cache file of the task.
+    async fn write_cache_file(&self, hash: &TaskHash) -> Result<(), CacheUpdateError> {
+        let task_cache_folder = self.project().task_cache_folder();
+        let cache_file = task_cache_folder.join(self.cache_name());
         tokio::fs::create_dir_all(&task_cache_folder).await?;
 
         let cache = TaskCache {
-            hash: new_hash.computation_hash(),
+            hash: hash.computation_hash(),
         };
         let cache = serde_json::to_string(&cache)?;
         Ok(tokio::fs::write(&cache_file, cache).await?)
     }
+
+    /// Returns the shared cache for the outputs of the task. Returns `None` if
+    /// no shared cache is configured or if the task has no outputs.
+    fn output_cache(&self) -> Option<OutputCache> {
+        let has_outputs = self
+            .task()
+            .as_execute()
+            .and_then(|execute| execute.outputs.as_ref())
+            .is_some_and(|outputs| !outputs.is_empty());
+        if !has_outputs {
+            return None;
+        }
+
+        let config = self.project().config().task_cache()?;
+        match OutputCache::from_config(config, self.project().authenticated_client()) {
+            Ok(output_cache) => output_cache,
+            Err(err) => {
+                tracing::warn!("ignoring the task cache: {err}");
+                None
+            }
+        }
+    }
+
+    /// Tries to restore the outputs of the task from the shared cache. If the
+    /// outputs are restored, the hash is updated with the restored outputs and
+    /// stored in the cache file so subsequent runs don't need the shared cache.
+    async fn restore_outputs(&self, output_cache: &OutputCache, hash: &mut TaskHash) -> bool {
+        let key = hash.output_cache_key();
+        match output_cache.restore(&key, self.project().root()).await {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(err) => {
+                tracing::warn!(
+                    "failed to restore the outputs of task '{}' from the task cache: {err}",
+                    self.name().unwrap_or_default()
+                );
+                return false;
+            }
+        }
+
+        tracing::info!(
+            "Restored the outputs of task '{}' from the task cache ({key})",
+            self.name().unwrap_or_default()
+        );
+        if let Err(err) = hash.update_output(self).await {
+            tracing::warn!("failed to hash the restored outputs: {err}");
+            return false;
+        }
+        if let Err(err) = self.write_cache_file(hash).await {
+            tracing::warn!("failed to update the task cache: {err}");
+        }
+        true
+    }
 }
 
 /// A helper object that implements [`Display`] to display (with ascii color)
@@ -305,7 +528,7 @@ struct ExecutableTaskConsoleDisplay<'p, 't> {
 
 impl<'p, 't> Display for ExecutableTaskConsoleDisplay<'p, 't> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let command = self.task.task.as_single_command();
+        let command = self.task.command().ok().flatten();
         write!(
             f,
             "{}",
@@ -366,7 +589,7 @@ pub async fn get_task_env<'p>(
     let mut activation_env = await_in_progress("activating environment", |_| {
         environment
             .project()
-            .get_activated_environment_variables(environment, env_var_behavior)
+            .get_activated_environment_variables_with_env_files(environment, env_var_behavior)
     })
     .await
     .wrap_err("failed to activate environment")?
@@ -450,12 +673,91 @@ mod tests {
             task: Cow::Borrowed(task),
             run_environment: project.default_environment(),
             additional_args: vec![],
+            args: None,
         };
 
-        let script = executable_task.as_script().unwrap();
+        let script = executable_task.as_script().unwrap().unwrap();
         assert_eq!(script, "export \"FOO=bar\";\n\ntest ");
     }
 
+    #[test]
+    fn test_cache_name_includes_args() {
+        let file_contents = r#"
+            [tasks]
+            test = {cmd = "test {{ target }}", args = [{ name = "target" }]}
+            "#;
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            format!("{PROJECT_BOILERPLATE}\n{file_contents}").as_str(),
+        )
+        .unwrap();
+
+        let project = Project::from_manifest(manifest);
+
+        let task = project
+            .default_environment()
+            .task(&TaskName::from("test"), None)
+            .unwrap();
+
+        let executable_task = |args: Option<TaskArgValues>| ExecutableTask {
+            project: &project,
+            name: Some("test".into()),
+            task: Cow::Borrowed(task),
+            run_environment: project.default_environment(),
+            additional_args: vec![],
+            args,
+        };
+
+        let without_args = executable_task(None).cache_name();
+        let debug = executable_task(Some(TaskArgValues::from_iter([(
+            "target".to_string(),
+            "debug".to_string(),
+        )])))
+        .cache_name();
+        let release = executable_task(Some(TaskArgValues::from_iter([(
+            "target".to_string(),
+            "release".to_string(),
+        )])))
+        .cache_name();
+
+        assert_eq!(without_args, "default-test.json");
+        assert!(debug.starts_with("default-test-"));
+        assert_ne!(debug, release);
+    }
+
+    #[test]
+    fn test_condition_met_uses_task_env() {
+        let file_contents = r#"
+            [tasks]
+            test = {cmd = "test", env = {MODE = "release"}, condition = "env.MODE == \"release\" and env.FROM_ENV_FILE == \"1\""}
+            "#;
+        let manifest = Manifest::from_str(
+            Path::new("pixi.toml"),
+            format!("{PROJECT_BOILERPLATE}\n{file_contents}").as_str(),
+        )
+        .unwrap();
+
+        let project = Project::from_manifest(manifest);
+
+        let task = project
+            .default_environment()
+            .task(&TaskName::from("test"), None)
+            .unwrap();
+
+        let executable_task = ExecutableTask {
+            project: &project,
+            name: Some("test".into()),
+            task: Cow::Borrowed(task),
+            run_environment: project.default_environment(),
+            additional_args: vec![],
+            args: None,
+        };
+
+        let command_env = HashMap::from([("FROM_ENV_FILE".to_string(), "1".to_string())]);
+        assert!(executable_task.condition_met(&command_env).unwrap());
+        assert!(!executable_task.condition_met(&HashMap::new()).unwrap());
+    }
+
     #[tokio::test]
     async fn test_get_task_env() {
         let file_contents = r#"
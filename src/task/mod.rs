@@ -1,20 +1,35 @@
 mod error;
 mod executable_task;
 mod file_hashes;
+mod output_cache;
+mod s3;
+mod task_args;
+mod task_condition;
 mod task_environment;
 mod task_graph;
 mod task_hash;
+mod task_report;
+mod task_scheduler;
+mod task_service;
+mod watcher;
 
 pub use file_hashes::{FileHashes, FileHashesError};
+pub use output_cache::{OutputCache, OutputCacheError};
 pub use pixi_manifest::{Task, TaskName};
 pub use task_hash::{ComputationHash, InputHashes, TaskHash};
+pub use task_report::{TaskReport, TaskRunReport, TaskStatus};
 
 pub use executable_task::{
     get_task_env, CanSkip, ExecutableTask, FailedToParseShellScript, InvalidWorkingDirectory,
     RunOutput, TaskExecutionError,
 };
+pub use task_args::{TaskArgError, TaskArgValues};
+pub use task_condition::TaskConditionError;
 pub use task_environment::{
     AmbiguousTask, FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment,
     TaskDisambiguation,
 };
 pub use task_graph::{TaskGraph, TaskGraphError, TaskId, TaskNode};
+pub use task_scheduler::TaskScheduler;
+pub use task_service::{wait_until_ready, LogProbe};
+pub use watcher::InputWatcher;
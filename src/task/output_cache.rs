@@ -0,0 +1,341 @@
+//! Implements a cache for the outputs of tasks that can be shared between machines.
+//!
+//! The files matched by the `outputs` of a task are stored as a gzipped tarball that is addressed
+//! by the [`ComputationHash`] of the task, next to a file with the sha256 digest of the tarball.
+//! An entry whose tarball doesn't match the digest is not restored. The digest is stored in the
+//! same place as the tarball though, so anyone who can write to the cache can replace both.
+//!
+//! The cache is either a local (or network mounted) directory, an HTTP(S) server that accepts
+//! plain `GET` and `PUT` requests authenticated with the credentials pixi stores for the host, or
+//! an S3 (compatible) bucket whose requests are signed with AWS signature version 4. When a task
+//! is run in a fresh checkout, the outputs are restored from the cache instead of running the task
+//! again.
+
+use std::{
+    io::{Cursor, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use miette::Diagnostic;
+use pixi_config::TaskCacheConfig;
+use rattler_digest::{compute_bytes_digest, Sha256};
+use reqwest::StatusCode;
+use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
+use thiserror::Error;
+use url::Url;
+
+use crate::task::{
+    s3::{S3Bucket, S3BucketError},
+    ComputationHash,
+};
+
+/// An error that might occur when reading from or writing to an [`OutputCache`].
+#[derive(Debug, Error, Diagnostic)]
+pub enum OutputCacheError {
+    #[error("invalid task cache location '{0}'")]
+    InvalidUrl(Url),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("failed to reach the task cache at '{0}'")]
+    Http(Url, #[source] reqwest_middleware::Error),
+
+    #[error(transparent)]
+    S3(#[from] S3BucketError),
+
+    #[error("failed to archive the outputs of the task")]
+    Archive(#[source] std::io::Error),
+
+    #[error("the task cache entry '{0}' does not match its digest")]
+    DigestMismatch(String),
+}
+
+/// A cache for the outputs of tasks that is shared between machines.
+#[derive(Clone)]
+pub enum OutputCache {
+    /// The outputs are stored in a directory.
+    Directory(PathBuf),
+
+    /// The outputs are stored on a server that accepts plain `GET` and `PUT` requests.
+    Http {
+        client: ClientWithMiddleware,
+        url: Url,
+    },
+
+    /// The outputs are stored in an S3 (compatible) bucket.
+    S3 {
+        client: ClientWithMiddleware,
+        bucket: S3Bucket,
+    },
+}
+
+impl OutputCache {
+    /// Constructs the cache from the configuration. Returns `None` if no cache is configured.
+    pub fn from_config(
+        config: &TaskCacheConfig,
+        client: &ClientWithMiddleware,
+    ) -> Result<Option<Self>, OutputCacheError> {
+        let Some(url) = &config.url else {
+            return Ok(None);
+        };
+
+        match url.scheme() {
+            "file" => url
+                .to_file_path()
+                .map(|path| Some(Self::Directory(path)))
+                .map_err(|_| OutputCacheError::InvalidUrl(url.clone())),
+            "http" | "https" => {
+                // Make sure the url is interpreted as a directory when joining the name of an
+                // entry.
+                let mut url = url.clone();
+                if !url.path().ends_with('/') {
+                    url.set_path(&format!("{}/", url.path()));
+                }
+                Ok(Some(Self::Http {
+                    client: client.clone(),
+                    url,
+                }))
+            }
+            "s3" => Ok(Some(Self::S3 {
+                client: client.clone(),
+                bucket: S3Bucket::from_url(url, &config.s3.clone().unwrap_or_default())?,
+            })),
+            _ => Err(OutputCacheError::InvalidUrl(url.clone())),
+        }
+    }
+
+    /// Restores the outputs that were stored under `hash` into `root`. Returns `false` if the
+    /// cache does not contain the outputs. The outputs are only restored if they match the digest
+    /// that was stored with them, which catches entries that were corrupted in the cache.
+    pub async fn restore(
+        &self,
+        hash: &ComputationHash,
+        root: &Path,
+    ) -> Result<bool, OutputCacheError> {
+        let name = entry_name(hash);
+        let Some(archive) = self.read(&name).await? else {
+            return Ok(false);
+        };
+        let Some(digest) = self.read(&digest_name(&name)).await? else {
+            tracing::debug!("the task cache entry '{name}' has no digest, ignoring it");
+            return Ok(false);
+        };
+        if String::from_utf8_lossy(&digest).trim() != archive_digest(&archive) {
+            return Err(OutputCacheError::DigestMismatch(name));
+        }
+
+        let root = root.to_path_buf();
+        tokio::task::spawn_blocking(move || unpack_outputs(&archive, &root))
+            .await
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+            .map_err(OutputCacheError::Archive)?;
+        Ok(true)
+    }
+
+    /// Stores the `files`, relative to `root`, under `hash`.
+    pub async fn store(
+        &self,
+        hash: &ComputationHash,
+        root: &Path,
+        files: Vec<PathBuf>,
+    ) -> Result<(), OutputCacheError> {
+        let root = root.to_path_buf();
+        let archive = tokio::task::spawn_blocking(move || pack_outputs(&root, &files))
+            .await
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+            .map_err(OutputCacheError::Archive)?;
+
+        // The digest is written last, an entry without a digest is never restored.
+        let name = entry_name(hash);
+        let digest = archive_digest(&archive);
+        self.write(&name, archive).await?;
+        self.write(&digest_name(&name), digest.into_bytes()).await
+    }
+
+    /// Reads an entry from the cache, returns `None` if the entry does not exist.
+    async fn read(&self, name: &str) -> Result<Option<Vec<u8>>, OutputCacheError> {
+        match self {
+            OutputCache::Directory(dir) => match tokio::fs::read(dir.join(name)).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+            OutputCache::Http { client, url } => {
+                let url = entry_url(url, name)?;
+                get(client.get(url.clone()), url).await
+            }
+            OutputCache::S3 { client, bucket } => {
+                let url = bucket.object_url(name);
+                let request = bucket
+                    .sign("GET", &url, &[], Utc::now())
+                    .into_iter()
+                    .fold(client.get(url.clone()), |request, (key, value)| {
+                        request.header(key, value)
+                    });
+                get(request, url).await
+            }
+        }
+    }
+
+    /// Writes an entry to the cache, overwriting an existing entry with the same name.
+    async fn write(&self, name: &str, bytes: Vec<u8>) -> Result<(), OutputCacheError> {
+        match self {
+            OutputCache::Directory(dir) => {
+                let dir = dir.clone();
+                let name = name.to_string();
+                // Write to a temporary file first so that other processes never observe a
+                // partially written entry.
+                tokio::task::spawn_blocking(move || {
+                    fs_err::create_dir_all(&dir)?;
+                    let mut file = tempfile::NamedTempFile::new_in(&dir)?;
+                    file.write_all(&bytes)?;
+                    file.persist(dir.join(name)).map_err(|err| err.error)?;
+                    Ok::<_, std::io::Error>(())
+                })
+                .await
+                .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?;
+                Ok(())
+            }
+            OutputCache::Http { client, url } => {
+                let url = entry_url(url, name)?;
+                put(client.put(url.clone()), url, bytes).await
+            }
+            OutputCache::S3 { client, bucket } => {
+                let url = bucket.object_url(name);
+                let request = bucket
+                    .sign("PUT", &url, &bytes, Utc::now())
+                    .into_iter()
+                    .fold(client.put(url.clone()), |request, (key, value)| {
+                        request.header(key, value)
+                    });
+                put(request, url, bytes).await
+            }
+        }
+    }
+}
+
+/// Sends a `GET` request for an entry, returns `None` if the entry does not exist.
+async fn get(request: RequestBuilder, url: Url) -> Result<Option<Vec<u8>>, OutputCacheError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|err| OutputCacheError::Http(url.clone(), err))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let bytes = response
+        .error_for_status()
+        .map_err(|err| OutputCacheError::Http(url.clone(), err.into()))?
+        .bytes()
+        .await
+        .map_err(|err| OutputCacheError::Http(url, err.into()))?;
+    Ok(Some(bytes.to_vec()))
+}
+
+/// Sends a `PUT` request that uploads an entry.
+async fn put(request: RequestBuilder, url: Url, bytes: Vec<u8>) -> Result<(), OutputCacheError> {
+    request
+        .body(bytes)
+        .send()
+        .await
+        .and_then(|response| Ok(response.error_for_status()?))
+        .map_err(|err| OutputCacheError::Http(url, err))?;
+    Ok(())
+}
+
+/// Returns the name of the entry in which the outputs of a task with the given hash are stored.
+fn entry_name(hash: &ComputationHash) -> String {
+    format!("{hash}.tar.gz")
+}
+
+/// Returns the name of the file that stores the digest of the entry with the given name.
+fn digest_name(name: &str) -> String {
+    format!("{name}.sha256")
+}
+
+/// Returns the hex encoded sha256 digest of an archive.
+fn archive_digest(archive: &[u8]) -> String {
+    format!("{:x}", compute_bytes_digest::<Sha256>(archive))
+}
+
+fn entry_url(base: &Url, name: &str) -> Result<Url, OutputCacheError> {
+    base.join(name)
+        .map_err(|_| OutputCacheError::InvalidUrl(base.clone()))
+}
+
+/// Creates a gzipped tarball of the `files` relative to `root`.
+fn pack_outputs(root: &Path, files: &[PathBuf]) -> std::io::Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for file in files {
+        builder.append_path_with_name(root.join(file), file)?;
+    }
+    builder.into_inner()?.finish()
+}
+
+/// Unpacks a tarball created by [`pack_outputs`] into `root`. Entries that would be unpacked
+/// outside of `root` are skipped.
+fn unpack_outputs(archive: &[u8], root: &Path) -> std::io::Result<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(Cursor::new(archive)));
+    for entry in archive.entries()? {
+        entry?.unpack_in(root)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{create_dir, read_to_string, write};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_store_and_restore() {
+        let cache_dir = tempdir().unwrap();
+        let cache = OutputCache::Directory(cache_dir.path().to_path_buf());
+        let hash = ComputationHash::from("abcdef".to_string());
+
+        let source = tempdir().unwrap();
+        create_dir(source.path().join("build")).unwrap();
+        write(source.path().join("build/app"), "binary").unwrap();
+        write(source.path().join("report.txt"), "report").unwrap();
+
+        let target = tempdir().unwrap();
+        assert!(!cache.restore(&hash, target.path()).await.unwrap());
+
+        cache
+            .store(
+                &hash,
+                source.path(),
+                vec![PathBuf::from("build/app"), PathBuf::from("report.txt")],
+            )
+            .await
+            .unwrap();
+
+        assert!(cache.restore(&hash, target.path()).await.unwrap());
+        assert_eq!(
+            read_to_string(target.path().join("build/app")).unwrap(),
+            "binary"
+        );
+        assert_eq!(
+            read_to_string(target.path().join("report.txt")).unwrap(),
+            "report"
+        );
+
+        // An entry that was corrupted is not restored.
+        let entry = cache_dir.path().join(entry_name(&hash));
+        let mut archive = std::fs::read(&entry).unwrap();
+        archive.push(0);
+        write(&entry, archive).unwrap();
+        assert!(matches!(
+            cache.restore(&hash, target.path()).await,
+            Err(OutputCacheError::DigestMismatch(_))
+        ));
+
+        // An entry without a digest is ignored.
+        std::fs::remove_file(cache_dir.path().join(digest_name(&entry_name(&hash)))).unwrap();
+        assert!(!cache.restore(&hash, target.path()).await.unwrap());
+    }
+}
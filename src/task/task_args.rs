@@ -0,0 +1,176 @@
+use fancy_display::FancyDisplay;
+use indexmap::IndexMap;
+use miette::Diagnostic;
+use minijinja::{Environment, UndefinedBehavior};
+use pixi_manifest::{task::TaskArg, TaskName};
+use thiserror::Error;
+
+/// The values of the named arguments of a task, indexed by the name of the
+/// argument.
+pub type TaskArgValues = IndexMap<String, String>;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum TaskArgError {
+    #[error("no value was passed for the argument '{arg}' of the task '{}'", task.fancy_display())]
+    #[diagnostic(help("pass a value for the argument or add a `default` to the argument"))]
+    MissingArgument { task: TaskName, arg: String },
+
+    #[error("the task '{}' accepts {expected} argument(s) but {actual} were passed", task.fancy_display())]
+    TooManyArguments {
+        task: TaskName,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("failed to substitute the arguments in the command of the task '{}'", task.fancy_display())]
+    InvalidTemplate {
+        task: TaskName,
+        #[source]
+        source: minijinja::Error,
+    },
+}
+
+/// Determines the values of the arguments declared by a task from the values
+/// that are passed to it. Values are assigned to the arguments in the order in
+/// which the arguments are declared, arguments without a value use their
+/// default.
+pub fn resolve_task_args(
+    task_name: &TaskName,
+    declared: &[TaskArg],
+    values: Vec<String>,
+) -> Result<TaskArgValues, TaskArgError> {
+    if values.len() > declared.len() {
+        return Err(TaskArgError::TooManyArguments {
+            task: task_name.clone(),
+            expected: declared.len(),
+            actual: values.len(),
+        });
+    }
+
+    let mut values = values.into_iter();
+    declared
+        .iter()
+        .map(|arg| {
+            let value = values
+                .next()
+                .or_else(|| arg.default.clone())
+                .ok_or_else(|| TaskArgError::MissingArgument {
+                    task: task_name.clone(),
+                    arg: arg.name.clone(),
+                })?;
+            Ok((arg.name.clone(), value))
+        })
+        .collect()
+}
+
+/// Substitutes the values of the arguments in a command, e.g. `cargo build
+/// --profile {{ profile }}`. Referencing an argument that does not exist is an
+/// error.
+///
+/// Every substituted value is quoted with [`quote_shell_word`], so a value
+/// that contains spaces or shell operators is passed as a single word.
+pub fn render_task_command(
+    command: &str,
+    args: &TaskArgValues,
+) -> Result<String, minijinja::Error> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(UndefinedBehavior::Strict);
+    env.set_formatter(|out, _state, value| {
+        out.write_str(&quote_shell_word(&value.to_string()))?;
+        Ok(())
+    });
+    env.render_str(command, args)
+}
+
+/// Quotes a value so that the shell of a task reads it as a single word. The
+/// value is wrapped in single quotes, single quotes in the value are closed,
+/// escaped and reopened.
+pub fn quote_shell_word(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r#"'"'"'"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declared_args() -> Vec<TaskArg> {
+        vec![
+            TaskArg {
+                name: "target".to_string(),
+                default: None,
+            },
+            TaskArg {
+                name: "profile".to_string(),
+                default: Some("debug".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_resolve_task_args() {
+        let task_name = TaskName::from("build");
+
+        let args =
+            resolve_task_args(&task_name, &declared_args(), vec!["linux".to_string()]).unwrap();
+        assert_eq!(
+            render_task_command(
+                "cargo build --target {{ target }} --profile {{ profile }}",
+                &args
+            )
+            .unwrap(),
+            "cargo build --target 'linux' --profile 'debug'"
+        );
+
+        let args = resolve_task_args(
+            &task_name,
+            &declared_args(),
+            vec!["linux".to_string(), "release".to_string()],
+        )
+        .unwrap();
+        assert_eq!(args.get("profile").unwrap(), "release");
+
+        assert!(matches!(
+            resolve_task_args(&task_name, &declared_args(), vec![]),
+            Err(TaskArgError::MissingArgument { arg, .. }) if arg == "target"
+        ));
+        assert!(matches!(
+            resolve_task_args(
+                &task_name,
+                &declared_args(),
+                vec!["a".to_string(), "b".to_string(), "c".to_string()]
+            ),
+            Err(TaskArgError::TooManyArguments {
+                expected: 2,
+                actual: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_render_quotes_values() {
+        let args = TaskArgValues::from([
+            ("filter".to_string(), "not slow; rm -rf /".to_string()),
+            ("name".to_string(), "it's".to_string()),
+        ]);
+        assert_eq!(
+            render_task_command("pytest -k {{ filter }} --name {{ name }}", &args).unwrap(),
+            r#"pytest -k 'not slow; rm -rf /' --name 'it'"'"'s'"#
+        );
+
+        // Expressions are evaluated on the values, only the output is quoted.
+        assert_eq!(
+            render_task_command(
+                "{% if name == \"it's\" %}echo {{ name | upper }}{% endif %}",
+                &args
+            )
+            .unwrap(),
+            r#"echo 'IT'"'"'S'"#
+        );
+    }
+
+    #[test]
+    fn test_render_undefined_argument() {
+        assert!(render_task_command("echo {{ unknown }}", &TaskArgValues::new()).is_err());
+    }
+}
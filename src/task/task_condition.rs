@@ -0,0 +1,89 @@
+use std::{collections::HashMap, path::Path};
+
+use fancy_display::FancyDisplay;
+use miette::Diagnostic;
+use minijinja::{context, Environment};
+use pixi_manifest::TaskName;
+use rattler_conda_types::Platform;
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("invalid condition `{condition}` of the task '{}'", task.fancy_display())]
+#[diagnostic(help(
+    "conditions are expressions like `env.CI == \"true\"`, `platform == \"linux-64\"` or `exists(\"build/\")`"
+))]
+pub struct TaskConditionError {
+    pub task: TaskName,
+    pub condition: String,
+    #[source]
+    pub source: minijinja::Error,
+}
+
+/// Constructs the environment in which conditions are evaluated. Paths passed
+/// to `exists` are relative to `root`.
+fn condition_environment(root: &Path) -> Environment<'static> {
+    let mut env = Environment::new();
+    let root = root.to_path_buf();
+    env.add_function("exists", move |path: String| root.join(path).exists());
+    env
+}
+
+/// Verifies that a condition is a valid expression without evaluating it.
+pub fn validate_condition(condition: &str) -> Result<(), minijinja::Error> {
+    Environment::new().compile_expression(condition)?;
+    Ok(())
+}
+
+/// Evaluates the condition of a task. The expression has access to:
+///
+/// - `env`: the environment variables, e.g. `env.CI == "true"`.
+/// - `platform`: the platform the task runs on, e.g. `platform == "osx-arm64"`.
+/// - `exists(path)`: whether a path relative to `root` exists.
+pub fn evaluate_condition(
+    condition: &str,
+    root: &Path,
+    platform: Platform,
+    env_vars: &HashMap<String, String>,
+) -> Result<bool, minijinja::Error> {
+    let env = condition_environment(root);
+    let expression = env.compile_expression(condition)?;
+    let value = expression.eval(context! {
+        env => env_vars,
+        platform => platform.as_str(),
+    })?;
+    Ok(value.is_true())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case("env.CI == \"true\"", true)]
+    #[case("env.CI == \"false\"", false)]
+    #[case("env.UNDEFINED == \"true\"", false)]
+    #[case("not env.UNDEFINED", true)]
+    #[case("platform == \"linux-64\"", true)]
+    #[case("platform is startingwith(\"osx\")", false)]
+    #[case("exists(\"build/\")", true)]
+    #[case("exists(\"dist/\") or env.CI == \"true\"", true)]
+    #[case("exists(\"dist/\")", false)]
+    fn test_evaluate_condition(#[case] condition: &str, #[case] expected: bool) {
+        let root = tempdir().unwrap();
+        std::fs::create_dir(root.path().join("build")).unwrap();
+        let env_vars = HashMap::from([("CI".to_string(), "true".to_string())]);
+
+        assert_eq!(
+            evaluate_condition(condition, root.path(), Platform::Linux64, &env_vars).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_invalid_condition() {
+        assert!(validate_condition("env.CI ==").is_err());
+        assert!(validate_condition("env.CI == \"true\" and exists(\"build/\")").is_ok());
+    }
+}
@@ -23,7 +23,9 @@ use crate::{
     },
     task::{
         error::{AmbiguousTaskError, MissingTaskError},
-        task_environment::{FindTaskError, FindTaskSource, SearchEnvironments},
+        task_args::{render_task_command, resolve_task_args, TaskArgError, TaskArgValues},
+        task_condition::{validate_condition, TaskConditionError},
+        task_environment::{FindTaskError, FindTaskSource, SearchEnvironments, TaskAndEnvironment},
         TaskDisambiguation,
     },
     Project,
@@ -51,6 +53,10 @@ pub struct TaskNode<'p> {
     /// verbatim, e.g. they will not be interpreted by deno.
     pub additional_args: Vec<String>,
 
+    /// The values of the named arguments of the task, or `None` if the task
+    /// does not declare any arguments.
+    pub args: Option<TaskArgValues>,
+
     /// The id's of the task that this task depends on.
     pub dependencies: Vec<TaskId>,
 }
@@ -58,11 +64,15 @@ impl fmt::Display for TaskNode<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "task: {}, environment: {}, command: `{}`, additional arguments: `{}`, depends-on: `{}`",
+            "task: {}, environment: {}, command: `{}`, additional arguments: `{}`, arguments: `{}`, depends-on: `{}`",
             self.name.clone().unwrap_or("CUSTOM COMMAND".into()),
             self.run_environment.name(),
             self.task.as_single_command().unwrap_or(Cow::Owned("".to_string())),
             self.format_additional_args(),
+            self.args
+                .iter()
+                .flatten()
+                .format_with(", ", |(name, value), f| f(&format_args!("{name}={value}"))),
             self.dependencies
                 .iter()
                 .map(|id| id.0.to_string())
@@ -83,6 +93,10 @@ impl<'p> TaskNode<'p> {
     pub(crate) fn full_command(&self) -> Option<String> {
         let mut cmd = self.task.as_single_command()?.to_string();
 
+        if let Some(args) = &self.args {
+            cmd = render_task_command(&cmd, args).ok()?;
+        }
+
         if !self.additional_args.is_empty() {
             // Pass each additional argument varbatim by wrapping it in single quotes
             cmd.push_str(&format!(" {}", self.format_additional_args()));
@@ -165,14 +179,26 @@ impl<'p> TaskGraph<'p> {
                         Some(explicit_env) if task_env.is_default() => explicit_env,
                         _ => task_env,
                     };
+                    // If the task declares named arguments, the arguments are assigned to those
+                    // instead of being appended to the command.
+                    let name = TaskName::from(args.remove(0));
+                    let (additional_args, task_args) = if task.args().is_empty() {
+                        (args, None)
+                    } else {
+                        (
+                            Vec::new(),
+                            Some(resolve_task_args(&name, task.args(), args)?),
+                        )
+                    };
                     return Self::from_root(
                         project,
                         search_envs,
                         TaskNode {
-                            name: Some(args.remove(0).into()),
+                            name: Some(name),
                             task: Cow::Borrowed(task),
                             run_environment: run_env,
-                            additional_args: args,
+                            additional_args,
+                            args: task_args,
                             dependencies: vec![],
                         },
                     );
@@ -213,6 +239,7 @@ impl<'p> TaskGraph<'p> {
                 ),
                 run_environment,
                 additional_args,
+                args: None,
                 dependencies: vec![],
             },
         )
@@ -224,62 +251,104 @@ impl<'p> TaskGraph<'p> {
         search_environments: &SearchEnvironments<'p, D>,
         root: TaskNode<'p>,
     ) -> Result<Self, TaskGraphError> {
-        let mut task_name_to_node: HashMap<TaskName, TaskId> =
-            HashMap::from_iter(root.name.clone().into_iter().map(|name| (name, TaskId(0))));
+        validate_task_node(&root)?;
+
+        let mut task_name_to_node: HashMap<TaskNodeKey, TaskId> = HashMap::from_iter(
+            root.name
+                .clone()
+                .map(|name| (task_node_key(name, root.args.as_ref()), TaskId(0))),
+        );
+        let mut resolved_tasks: HashMap<TaskName, TaskAndEnvironment<'p>> = HashMap::new();
+        if let (Some(name), Cow::Borrowed(task)) = (&root.name, &root.task) {
+            resolved_tasks.insert(name.clone(), (root.run_environment.clone(), *task));
+        }
         let mut nodes = vec![root];
 
         // Iterate over all the nodes in the graph and add them to the graph.
         let mut next_node_to_visit = 0;
         while next_node_to_visit < nodes.len() {
-            let dependency_names =
+            let dependencies =
                 Vec::from_iter(nodes[next_node_to_visit].task.depends_on().iter().cloned());
 
             // Iterate over all the dependencies of the node and add them to the graph.
-            let mut node_dependencies = Vec::with_capacity(dependency_names.len());
-            for dependency in dependency_names {
-                // Check if we visited this node before already.
-                if let Some(&task_id) = task_name_to_node.get(&dependency) {
-                    node_dependencies.push(task_id);
-                    continue;
-                }
-
-                // Find the task in the project
-                let node = &nodes[next_node_to_visit];
-                let (task_env, task_dependency) = match search_environments.find_task(
-                    dependency.clone(),
-                    FindTaskSource::DependsOn(
-                        node.name
-                            .clone()
-                            .expect("only named tasks can have dependencies"),
-                        match &node.task {
-                            Cow::Borrowed(task) => task,
-                            Cow::Owned(_) => {
-                                unreachable!("only named tasks can have dependencies")
+            let mut node_dependencies = Vec::with_capacity(dependencies.len());
+            for dependency in dependencies {
+                // Find the task in the project. Tasks are only looked up once per name, so that
+                // a task that is depended on with different arguments is disambiguated once.
+                let (task_env, task_dependency) = match resolved_tasks.get(&dependency.task_name) {
+                    Some((task_env, task)) => (task_env.clone(), *task),
+                    None => {
+                        let node = &nodes[next_node_to_visit];
+                        let resolved = match search_environments.find_task(
+                            dependency.task_name.clone(),
+                            FindTaskSource::DependsOn(
+                                node.name
+                                    .clone()
+                                    .expect("only named tasks can have dependencies"),
+                                match &node.task {
+                                    Cow::Borrowed(task) => task,
+                                    Cow::Owned(_) => {
+                                        unreachable!("only named tasks can have dependencies")
+                                    }
+                                },
+                            ),
+                        ) {
+                            Err(FindTaskError::MissingTask(err)) => {
+                                return Err(TaskGraphError::MissingTask(err))
                             }
-                        },
-                    ),
-                ) {
-                    Err(FindTaskError::MissingTask(err)) => {
-                        return Err(TaskGraphError::MissingTask(err))
+                            Err(FindTaskError::AmbiguousTask(err)) => {
+                                return Err(TaskGraphError::AmbiguousTask(err))
+                            }
+                            Ok(result) => result,
+                        };
+                        resolved_tasks.insert(dependency.task_name.clone(), resolved.clone());
+                        resolved
                     }
-                    Err(FindTaskError::AmbiguousTask(err)) => {
-                        return Err(TaskGraphError::AmbiguousTask(err))
+                };
+
+                // Determine the values of the arguments of the task.
+                let args = if task_dependency.args().is_empty() {
+                    if let Some(values) = &dependency.args {
+                        return Err(TaskArgError::TooManyArguments {
+                            task: dependency.task_name.clone(),
+                            expected: 0,
+                            actual: values.len(),
+                        }
+                        .into());
                     }
-                    Ok(result) => result,
+                    None
+                } else {
+                    Some(resolve_task_args(
+                        &dependency.task_name,
+                        task_dependency.args(),
+                        dependency.args.clone().unwrap_or_default(),
+                    )?)
                 };
 
+                // Check if we visited this node before already. Dependencies are identified by
+                // the values of their arguments, so that dependencies that spell out a default
+                // value refer to the same node.
+                let key = task_node_key(dependency.task_name.clone(), args.as_ref());
+                if let Some(&task_id) = task_name_to_node.get(&key) {
+                    node_dependencies.push(task_id);
+                    continue;
+                }
+
                 // Add the node to the graph
                 let task_id = TaskId(nodes.len());
-                nodes.push(TaskNode {
-                    name: Some(dependency.clone()),
+                let node = TaskNode {
+                    name: Some(dependency.task_name.clone()),
                     task: Cow::Borrowed(task_dependency),
                     run_environment: task_env,
                     additional_args: Vec::new(),
+                    args,
                     dependencies: Vec::new(),
-                });
+                };
+                validate_task_node(&node)?;
+                nodes.push(node);
 
-                // Store the task id in the map to be able to look up the name later
-                task_name_to_node.insert(dependency.clone(), task_id);
+                // Store the task id in the map to be able to look up the dependency later
+                task_name_to_node.insert(key, task_id);
 
                 // Add the dependency to the node
                 node_dependencies.push(task_id);
@@ -292,6 +361,14 @@ impl<'p> TaskGraph<'p> {
         Ok(Self { project, nodes })
     }
 
+    /// Returns the tasks that directly depend on the given task.
+    pub fn dependents(&self, id: TaskId) -> Vec<TaskId> {
+        (0..self.nodes.len())
+            .map(TaskId)
+            .filter(|&other| self.nodes[other.0].dependencies.contains(&id))
+            .collect()
+    }
+
     /// Returns the topological order of the tasks in the graph.
     ///
     /// The topological order is the order in which the tasks should be executed
@@ -326,6 +403,44 @@ impl<'p> TaskGraph<'p> {
     }
 }
 
+/// Identifies a node in the [`TaskGraph`] by the name of its task and the
+/// resolved values of its arguments.
+type TaskNodeKey = (TaskName, Option<Vec<(String, String)>>);
+
+fn task_node_key(name: TaskName, args: Option<&TaskArgValues>) -> TaskNodeKey {
+    (
+        name,
+        args.map(|args| {
+            args.iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()
+        }),
+    )
+}
+
+/// Verifies that the values of the arguments of a task can be substituted in
+/// its command and that its condition is a valid expression.
+fn validate_task_node(node: &TaskNode<'_>) -> Result<(), TaskGraphError> {
+    let task_name = || node.name.clone().unwrap_or_else(|| "unnamed".into());
+
+    if let Some(condition) = node.task.condition() {
+        validate_condition(condition).map_err(|source| TaskConditionError {
+            task: task_name(),
+            condition: condition.to_string(),
+            source,
+        })?;
+    }
+
+    let (Some(args), Some(command)) = (&node.args, node.task.as_single_command()) else {
+        return Ok(());
+    };
+    render_task_command(&command, args).map_err(|source| TaskArgError::InvalidTemplate {
+        task: task_name(),
+        source,
+    })?;
+    Ok(())
+}
+
 #[derive(Debug, Error, Diagnostic)]
 pub enum TaskGraphError {
     #[error(transparent)]
@@ -341,11 +456,19 @@ pub enum TaskGraphError {
 
     #[error("could not split task, assuming non valid task")]
     InvalidTask,
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    TaskArg(#[from] TaskArgError),
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    TaskCondition(#[from] TaskConditionError),
 }
 
 #[cfg(test)]
 mod test {
-    use std::path::Path;
+    use std::{cell::Cell, path::Path};
 
     use pixi_manifest::EnvironmentName;
     use rattler_conda_types::Platform;
@@ -582,6 +705,82 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_task_args() {
+        let project = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        build = { cmd = "echo build {{ target }} {{ profile }}", args = [{ name = "target" }, { name = "profile", default = "debug" }] }
+        release = { cmd = "echo release", depends-on = [{ task = "build", args = ["linux", "release"] }] }
+    "#;
+
+        assert_eq!(
+            commands_in_order(project, &["build", "osx"], None, None),
+            vec![r#"echo build 'osx' 'debug'"#]
+        );
+        assert_eq!(
+            commands_in_order(project, &["release"], None, None),
+            vec![r#"echo build 'linux' 'release'"#, r#"echo release"#]
+        );
+    }
+
+    #[test]
+    fn test_task_args_deduplicated() {
+        // Depending on a task with its default arguments spelled out refers to the
+        // same task as depending on it without arguments.
+        assert_eq!(
+            commands_in_order(
+                r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        build = { cmd = "echo build {{ profile }}", args = [{ name = "profile", default = "debug" }] }
+        test = { cmd = "echo test", depends-on = [{ task = "build" }] }
+        lint = { cmd = "echo lint", depends-on = [{ task = "build", args = ["debug"] }] }
+        bench = { cmd = "echo bench", depends-on = [{ task = "build", args = ["release"] }] }
+        all = { depends-on = ["test", "lint", "bench"] }
+    "#,
+                &["all"],
+                None,
+                None
+            ),
+            vec![
+                "echo build 'debug'",
+                "echo test",
+                "echo lint",
+                "echo build 'release'",
+                "echo bench"
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_task_args_missing() {
+        // The `target` argument has no default so it must be passed
+        commands_in_order(
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+
+        [tasks]
+        build = { cmd = "echo build {{ target }}", args = [{ name = "target" }] }
+    "#,
+            &["build"],
+            None,
+            None,
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_multi_env_defaults_ambigu() {
@@ -609,4 +808,56 @@ mod test {
             None,
         );
     }
+
+    #[test]
+    fn test_disambiguate_once_per_task() {
+        // `foo` is ambiguous and depended on with different arguments, the user should
+        // only be asked once which environment to use.
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64", "linux-riscv64"]
+
+        [tasks]
+        foo = { cmd = "echo foo {{ mode }}", args = [{ name = "mode", default = "debug" }] }
+        bar = { cmd = "echo bar", depends-on = [{ task = "foo", args = ["release"] }] }
+        all = { depends-on = ["foo", "bar"] }
+
+        [feature.build.tasks]
+        foo = "echo foo ambiguity"
+
+        [environments]
+        build = ["build"]
+    "#,
+        )
+        .unwrap();
+
+        let prompts = Cell::new(0);
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None)
+            .with_ignore_system_requirements(true)
+            .with_disambiguate_fn(|task| {
+                prompts.set(prompts.get() + 1);
+                task.environments
+                    .iter()
+                    .find(|(env, _)| env.name().is_default())
+                    .cloned()
+            });
+
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["all".to_string()]).unwrap();
+        let commands: Vec<_> = graph
+            .topological_order()
+            .into_iter()
+            .filter_map(|task| graph[task].full_command())
+            .collect();
+
+        assert_eq!(prompts.get(), 1);
+        assert_eq!(
+            commands,
+            vec!["echo foo 'debug'", "echo foo 'release'", "echo bar"]
+        );
+    }
 }
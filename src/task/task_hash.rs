@@ -1,10 +1,15 @@
 use crate::project;
-use crate::task::{ExecutableTask, FileHashes, FileHashesError, InvalidWorkingDirectory};
+use crate::task::{
+    ExecutableTask, FailedToParseShellScript, FileHashes, FileHashesError, InvalidWorkingDirectory,
+};
+use itertools::Itertools;
 use miette::Diagnostic;
+use rattler_digest::{compute_bytes_digest, Sha256};
 use rattler_lock::LockFile;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 use thiserror::Error;
 use xxhash_rust::xxh3::Xxh3;
 
@@ -71,6 +76,28 @@ pub struct TaskHash {
     pub command: Option<String>,
     pub inputs: Option<InputHashes>,
     pub outputs: Option<OutputHashes>,
+    pub output_key: OutputKey,
+}
+
+/// Identifies what a task produces, independent of the contents of its
+/// outputs. Two tasks with the same command and inputs but a different name,
+/// working directory or output globs produce different outputs.
+#[derive(Debug, Hash)]
+pub struct OutputKey {
+    pub task_name: Option<String>,
+    pub cwd: Option<PathBuf>,
+    pub output_globs: Vec<String>,
+}
+
+impl OutputKey {
+    fn from_task(task: &ExecutableTask<'_>) -> Self {
+        let execute = task.task().as_execute();
+        Self {
+            task_name: task.name().map(ToString::to_string),
+            cwd: execute.and_then(|e| e.cwd.clone()),
+            output_globs: execute.and_then(|e| e.outputs.clone()).unwrap_or_default(),
+        }
+    }
 }
 
 impl TaskHash {
@@ -87,10 +114,11 @@ impl TaskHash {
         }
 
         Ok(Some(Self {
-            command: task.full_command(),
+            command: task.full_command()?,
             outputs: output_hashes,
             inputs: input_hashes,
             environment: EnvironmentHash::from_environment(&task.run_environment, lock_file),
+            output_key: OutputKey::from_task(task),
         }))
     }
 
@@ -111,6 +139,37 @@ impl TaskHash {
         self.environment.hash(&mut hasher);
         ComputationHash(format!("{:x}", hasher.finish()))
     }
+
+    /// Computes the hash under which the outputs of the task are stored in the shared task
+    /// cache. Unlike [`TaskHash::computation_hash`] this does not include the outputs because
+    /// those are what is looked up, instead it includes the name, working directory and output
+    /// globs of the task.
+    ///
+    /// The cache is shared by many projects and machines, so this is a sha256 digest of an
+    /// explicit serialization of the components instead of a 64-bit [`Hash`] of them.
+    pub fn output_cache_key(&self) -> ComputationHash {
+        let input_files = self.inputs.as_ref().map(|inputs| {
+            inputs
+                .files
+                .files
+                .iter()
+                .sorted_by_key(|(path, _)| *path)
+                .map(|(path, hash)| (path.to_string_lossy(), hash))
+                .collect_vec()
+        });
+        let key = serde_json::json!({
+            "command": self.command,
+            "inputs": input_files,
+            "environment": self.environment.0,
+            "task_name": self.output_key.task_name,
+            "cwd": self.output_key.cwd.as_ref().map(|cwd| cwd.to_string_lossy()),
+            "output_globs": self.output_key.output_globs,
+        });
+        ComputationHash(format!(
+            "{:x}",
+            compute_bytes_digest::<Sha256>(key.to_string().as_bytes())
+        ))
+    }
 }
 
 /// The combination of all the hashes of the inputs of a task.
@@ -187,4 +246,7 @@ pub enum InputHashesError {
 
     #[error(transparent)]
     InvalidWorkingDirectory(#[from] InvalidWorkingDirectory),
+
+    #[error(transparent)]
+    FailedToParseShellScript(#[from] FailedToParseShellScript),
 }
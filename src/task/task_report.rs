@@ -0,0 +1,248 @@
+//! Implements the machine-readable report of a `pixi run` invocation.
+//!
+//! Every task in the task graph is recorded in a [`TaskRunReport`], together with the environment
+//! it ran in, its timing, its exit code and its hashes. The report can be written as JSON or as
+//! JUnit XML so it can be consumed by CI dashboards.
+
+use std::{collections::BTreeMap, fmt::Write, path::Path, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use miette::{Context, IntoDiagnostic};
+use serde::Serialize;
+
+use crate::task::{ComputationHash, ExecutableTask, FileHashes, TaskHash};
+
+/// The report of all the tasks of a `pixi run` invocation.
+#[derive(Debug, Default, Serialize)]
+pub struct TaskRunReport {
+    pub tasks: Vec<TaskReport>,
+}
+
+/// The status with which a task finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskStatus {
+    /// The task exited successfully.
+    Succeeded,
+
+    /// The task exited with a non-zero exit code.
+    Failed,
+
+    /// The task was killed because it exceeded its timeout.
+    TimedOut,
+
+    /// The task was skipped because its result was cached or because its condition was false.
+    Skipped,
+
+    /// The task was killed before it finished, e.g. because another task failed.
+    Cancelled,
+
+    /// The task was never started, e.g. because a task it depends on failed.
+    NotRun,
+}
+
+/// The record of a single task in a [`TaskRunReport`].
+#[derive(Debug, Serialize)]
+pub struct TaskReport {
+    pub name: Option<String>,
+    pub environment: String,
+    pub command: Option<String>,
+    pub status: TaskStatus,
+    pub cache_hit: bool,
+    pub exit_code: Option<i32>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The duration of the task in seconds.
+    pub duration: Option<f64>,
+    pub hash: Option<ComputationHash>,
+    pub inputs: Option<BTreeMap<PathBuf, String>>,
+    pub outputs: Option<BTreeMap<PathBuf, String>>,
+}
+
+impl TaskReport {
+    /// Constructs the record of a task from the `hash` that was computed when the task was run,
+    /// so the files of the task don't have to be hashed again.
+    pub fn new(task: &ExecutableTask<'_>, hash: Option<&TaskHash>, status: TaskStatus) -> Self {
+        let sorted = |files: &FileHashes| files.files.clone().into_iter().collect();
+        Self {
+            name: task.name().map(ToString::to_string),
+            environment: task.run_environment.name().to_string(),
+            // The arguments of the task were validated when the task graph was built.
+            command: task.full_command().ok().flatten(),
+            status,
+            cache_hit: false,
+            exit_code: match status {
+                TaskStatus::Succeeded => Some(0),
+                _ => None,
+            },
+            started_at: None,
+            finished_at: None,
+            duration: None,
+            hash: hash.map(TaskHash::computation_hash),
+            inputs: hash
+                .and_then(|hash| hash.inputs.as_ref())
+                .map(|inputs| sorted(&inputs.files)),
+            outputs: hash
+                .and_then(|hash| hash.outputs.as_ref())
+                .map(|outputs| sorted(&outputs.files)),
+        }
+    }
+
+    /// Marks the task as skipped because its result was cached.
+    pub fn with_cache_hit(self) -> Self {
+        Self {
+            cache_hit: true,
+            ..self
+        }
+    }
+
+    /// Sets the exit code of the task.
+    pub fn with_exit_code(self, exit_code: i32) -> Self {
+        Self {
+            exit_code: Some(exit_code),
+            ..self
+        }
+    }
+
+    /// Sets the time at which the task started and finished.
+    pub fn with_timing(self, started_at: DateTime<Utc>, finished_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at: Some(started_at),
+            finished_at: Some(finished_at),
+            duration: Some((finished_at - started_at).num_milliseconds() as f64 / 1000.0),
+            ..self
+        }
+    }
+}
+
+impl TaskRunReport {
+    /// Writes the report to the given path. If the path has an `xml` extension the report is
+    /// written in the JUnit XML format, otherwise it is written as JSON.
+    pub fn write(&self, path: &Path) -> miette::Result<()> {
+        let contents = if path.extension().is_some_and(|ext| ext == "xml") {
+            self.to_junit()
+        } else {
+            serde_json::to_string_pretty(self).into_diagnostic()?
+        };
+        fs_err::write(path, contents)
+            .into_diagnostic()
+            .wrap_err("failed to write the task report")
+    }
+
+    /// Renders the report in the JUnit XML format. Every task is a test case, cached tasks and
+    /// tasks that did not run are reported as skipped.
+    pub fn to_junit(&self) -> String {
+        let count = |status: &[TaskStatus]| {
+            self.tasks
+                .iter()
+                .filter(|task| status.contains(&task.status))
+                .count()
+        };
+        let failures = count(&[TaskStatus::Failed, TaskStatus::TimedOut]);
+        let errors = count(&[TaskStatus::Cancelled]);
+        let skipped = count(&[TaskStatus::Skipped, TaskStatus::NotRun]);
+        let time: f64 = self.tasks.iter().filter_map(|task| task.duration).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"pixi run\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+            tests = self.tasks.len(),
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"pixi run\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+            tests = self.tasks.len(),
+        );
+        for task in &self.tasks {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape_xml(task.name.as_deref().unwrap_or("unnamed")),
+                escape_xml(&task.environment),
+                task.duration.unwrap_or_default(),
+            );
+            let body = match task.status {
+                TaskStatus::Succeeded => None,
+                TaskStatus::Failed => Some(format!(
+                    "<failure message=\"the task exited with code {}\"/>",
+                    task.exit_code.unwrap_or_default()
+                )),
+                TaskStatus::TimedOut => {
+                    Some("<failure message=\"the task timed out\"/>".to_string())
+                }
+                TaskStatus::Cancelled => {
+                    Some("<error message=\"the task was cancelled\"/>".to_string())
+                }
+                TaskStatus::Skipped if task.cache_hit => {
+                    Some("<skipped message=\"cache hit\"/>".to_string())
+                }
+                TaskStatus::Skipped => {
+                    Some("<skipped message=\"the condition of the task is false\"/>".to_string())
+                }
+                TaskStatus::NotRun => Some("<skipped message=\"not run\"/>".to_string()),
+            };
+            match body {
+                Some(body) => {
+                    let _ = writeln!(xml, ">\n      {body}\n    </testcase>");
+                }
+                None => {
+                    let _ = writeln!(xml, "/>");
+                }
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Escapes the characters that are not allowed in XML attribute values.
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(name: &str, status: TaskStatus, exit_code: Option<i32>) -> TaskReport {
+        TaskReport {
+            name: Some(name.to_string()),
+            environment: "default".to_string(),
+            command: Some("echo hello".to_string()),
+            status,
+            cache_hit: status == TaskStatus::Skipped,
+            exit_code,
+            started_at: None,
+            finished_at: None,
+            duration: Some(1.5),
+            hash: None,
+            inputs: None,
+            outputs: None,
+        }
+    }
+
+    #[test]
+    fn test_junit() {
+        let report = TaskRunReport {
+            tasks: vec![
+                report("build", TaskStatus::Succeeded, Some(0)),
+                report("lint", TaskStatus::Skipped, None),
+                report("test<unit>", TaskStatus::Failed, Some(2)),
+                report("deploy", TaskStatus::NotRun, None),
+            ],
+        };
+        insta::assert_snapshot!(report.to_junit());
+    }
+}
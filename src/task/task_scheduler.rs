@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use crate::task::task_graph::{TaskGraph, TaskId};
+
+/// Keeps track of which tasks of a [`TaskGraph`] can be started, which are
+/// currently running and which have completed.
+///
+/// Tasks are handed out in topological order as soon as all the tasks they
+/// depend on have completed. This allows independent tasks to be executed
+/// concurrently while still respecting the `depends-on` relations between
+/// tasks.
+pub struct TaskScheduler<'g, 'p> {
+    graph: &'g TaskGraph<'p>,
+
+    /// The tasks that have not been started yet, in topological order.
+    pending: Vec<TaskId>,
+
+    /// The tasks that have been started but did not complete yet.
+    running: HashSet<TaskId>,
+
+    /// The tasks that have completed.
+    completed: HashSet<TaskId>,
+}
+
+impl<'g, 'p> TaskScheduler<'g, 'p> {
+    /// Constructs a new scheduler for all the tasks in the given graph.
+    pub fn new(graph: &'g TaskGraph<'p>) -> Self {
+        Self {
+            graph,
+            pending: graph.topological_order(),
+            running: HashSet::new(),
+            completed: HashSet::new(),
+        }
+    }
+
+    /// Returns the next task that is ready to be executed and marks it as
+    /// running. Returns `None` if no task can be started until one of the
+    /// running tasks completes, or if all tasks have been started.
+    pub fn next_ready(&mut self) -> Option<TaskId> {
+        let idx = self
+            .pending
+            .iter()
+            .position(|&id| {
+                self.graph[id]
+                    .dependencies
+                    .iter()
+                    .all(|dependency| self.completed.contains(dependency))
+            })
+            .or_else(|| {
+                // If nothing is running and no task is ready the graph contains a cycle. In that
+                // case we fall back to the topological order to ensure we always make progress.
+                (self.running.is_empty() && !self.pending.is_empty()).then_some(0)
+            })?;
+
+        let id = self.pending.remove(idx);
+        self.running.insert(id);
+        Some(id)
+    }
+
+    /// Marks a task as completed, this allows the tasks that depend on it to
+    /// be scheduled.
+    pub fn complete(&mut self, id: TaskId) {
+        self.running.remove(&id);
+        self.completed.insert(id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        task::{task_environment::SearchEnvironments, task_graph::TaskGraph},
+        Project,
+    };
+
+    use super::TaskScheduler;
+
+    const PROJECT: &str = r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        root = "echo root"
+        task1 = {cmd="echo task1", depends-on=["root"]}
+        task2 = {cmd="echo task2", depends-on=["root"]}
+        top = {cmd="echo top", depends-on=["task1","task2"]}
+    "#;
+
+    fn command(graph: &TaskGraph<'_>, id: super::TaskId) -> String {
+        graph[id].task.as_single_command().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_independent_tasks_are_ready_together() {
+        let project = Project::from_str(Path::new("pixi.toml"), PROJECT).unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None)
+            .with_ignore_system_requirements(true);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["top".to_string()]).unwrap();
+
+        let mut scheduler = TaskScheduler::new(&graph);
+
+        // Only the root task can be started.
+        let root = scheduler.next_ready().unwrap();
+        assert_eq!(command(&graph, root), "echo root");
+        assert!(scheduler.next_ready().is_none());
+
+        // Once the root completes both tasks that depend on it can run concurrently.
+        scheduler.complete(root);
+        let task1 = scheduler.next_ready().unwrap();
+        let task2 = scheduler.next_ready().unwrap();
+        assert_eq!(command(&graph, task1), "echo task1");
+        assert_eq!(command(&graph, task2), "echo task2");
+        assert!(scheduler.next_ready().is_none());
+
+        // The top task has to wait for both tasks to complete.
+        scheduler.complete(task1);
+        assert!(scheduler.next_ready().is_none());
+        scheduler.complete(task2);
+        let top = scheduler.next_ready().unwrap();
+        assert_eq!(command(&graph, top), "echo top");
+        scheduler.complete(top);
+        assert!(scheduler.next_ready().is_none());
+    }
+
+    #[test]
+    fn test_cycle_makes_progress() {
+        let project = Project::from_str(
+            Path::new("pixi.toml"),
+            r#"
+        [project]
+        name = "pixi"
+        channels = ["conda-forge"]
+        platforms = ["linux-64", "osx-64", "win-64", "osx-arm64"]
+        [tasks]
+        root = {cmd="echo root", depends-on=["task1"]}
+        task1 = {cmd="echo task1", depends-on=["root"]}
+    "#,
+        )
+        .unwrap();
+        let search_envs = SearchEnvironments::from_opt_env(&project, None, None)
+            .with_ignore_system_requirements(true);
+        let graph =
+            TaskGraph::from_cmd_args(&project, &search_envs, vec!["task1".to_string()]).unwrap();
+
+        let mut scheduler = TaskScheduler::new(&graph);
+        let mut order = Vec::new();
+        while let Some(id) = scheduler.next_ready() {
+            order.push(command(&graph, id));
+            scheduler.complete(id);
+        }
+        assert_eq!(order, vec!["echo root", "echo task1"]);
+    }
+}
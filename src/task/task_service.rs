@@ -0,0 +1,105 @@
+//! Implements the readiness probes of service tasks.
+//!
+//! A service is started in the background and the tasks that depend on it are only started once
+//! its [`ReadinessProbe`] succeeds: a port accepts connections, the service printed a matching
+//! log line or a command exits successfully.
+
+use std::{collections::HashMap, net::Ipv4Addr, path::Path, sync::Arc, time::Duration};
+
+use deno_task_shell::{execute_with_pipes, pipe, ShellPipeWriter, ShellState};
+use pixi_manifest::task::{ReadinessCheck, ReadinessProbe};
+use regex::Regex;
+use tokio::{net::TcpStream, sync::Notify};
+
+use crate::task::FailedToParseShellScript;
+
+/// The time between two attempts of a readiness probe.
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches the output of a service for a line that matches a regular expression.
+#[derive(Clone)]
+pub struct LogProbe {
+    regex: Regex,
+    matched: Arc<Notify>,
+}
+
+impl LogProbe {
+    /// Constructs a probe for the `log` check of a readiness probe. Returns `None` if the probe
+    /// does not check the output of the service.
+    pub fn from_probe(probe: &ReadinessProbe) -> Option<Self> {
+        let ReadinessCheck::Log(pattern) = &probe.check else {
+            return None;
+        };
+        Some(Self {
+            // The pattern is validated when the manifest is parsed.
+            regex: Regex::new(pattern).ok()?,
+            matched: Arc::new(Notify::new()),
+        })
+    }
+
+    /// Checks a line that was written by the service.
+    pub fn check_line(&self, line: &[u8]) {
+        if self.regex.is_match(&String::from_utf8_lossy(line)) {
+            self.matched.notify_one();
+        }
+    }
+}
+
+/// Waits until the service is ready. Returns `false` if the service did not become ready within
+/// the timeout of the probe.
+///
+/// The `log_probe` must be the probe that checks the output of the service if the probe is a
+/// `log` check. Commands of `cmd` checks are executed in `cwd` with the environment `env`.
+pub async fn wait_until_ready(
+    probe: &ReadinessProbe,
+    log_probe: Option<&LogProbe>,
+    cwd: &Path,
+    env: &HashMap<String, String>,
+) -> Result<bool, FailedToParseShellScript> {
+    let check = async {
+        match &probe.check {
+            ReadinessCheck::Port(port) => {
+                while TcpStream::connect((Ipv4Addr::LOCALHOST, *port))
+                    .await
+                    .is_err()
+                {
+                    tokio::time::sleep(PROBE_INTERVAL).await;
+                }
+            }
+            ReadinessCheck::Log(_) => match log_probe {
+                Some(log_probe) => log_probe.matched.notified().await,
+                None => futures::future::pending().await,
+            },
+            ReadinessCheck::Command(cmd) => {
+                let script =
+                    deno_task_shell::parser::parse(cmd).map_err(|e| FailedToParseShellScript {
+                        script: cmd.clone(),
+                        error: e.to_string(),
+                    })?;
+                loop {
+                    let state = ShellState::new(env.clone(), cwd, Default::default());
+                    // Dropping the writer closes the input of the command.
+                    let (stdin, _) = pipe();
+                    let code = execute_with_pipes(
+                        script.clone(),
+                        state,
+                        stdin,
+                        ShellPipeWriter::null(),
+                        ShellPipeWriter::null(),
+                    )
+                    .await;
+                    if code == 0 {
+                        break;
+                    }
+                    tokio::time::sleep(PROBE_INTERVAL).await;
+                }
+            }
+        }
+        Ok(())
+    };
+
+    match tokio::time::timeout(probe.timeout, check).await {
+        Ok(result) => result.map(|_| true),
+        Err(_) => Ok(false),
+    }
+}
@@ -0,0 +1,170 @@
+//! Implements watching the files matched by the `inputs` of tasks for changes.
+//!
+//! Changes are detected by periodically checking the modification times and sizes of all the
+//! inputs. Only when those changed, the [`FileHashes`] of the inputs are recomputed and compared
+//! with the previous hashes, so that touching a file without modifying it does not trigger a
+//! rerun. This reuses the same glob semantics as the task cache, so a file that would invalidate
+//! the cache of a task is also a file that triggers a rerun.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use itertools::Itertools;
+
+use super::file_hashes::{file_stamps, FileStamp};
+use crate::task::{FileHashes, FileHashesError, TaskGraph};
+
+/// The interval at which the inputs are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a set of glob patterns for changes to the files they match.
+pub struct InputWatcher {
+    /// The directory relative to which the globs are interpreted.
+    root: PathBuf,
+
+    /// The globs that are watched, including negated globs for files that should be ignored.
+    filters: Vec<String>,
+
+    /// The modification times and sizes of the files the last time they were checked.
+    stamps: HashMap<PathBuf, FileStamp>,
+
+    /// The hashes of the files the last time they changed.
+    hashes: FileHashes,
+}
+
+impl InputWatcher {
+    /// Constructs a new watcher for the files in `root` matched by the `inputs` globs. Files
+    /// matched by the `outputs` globs are ignored.
+    pub async fn new(
+        root: &Path,
+        inputs: impl IntoIterator<Item = String>,
+        outputs: impl IntoIterator<Item = String>,
+    ) -> Result<Self, FileHashesError> {
+        let filters = inputs
+            .into_iter()
+            .chain(outputs.into_iter().map(|output| format!("!{output}")))
+            .unique()
+            .collect_vec();
+        let stamps = file_stamps(root, &filters).await?;
+        let hashes = FileHashes::from_files(root, &filters).await?;
+        Ok(Self {
+            root: root.to_path_buf(),
+            filters,
+            stamps,
+            hashes,
+        })
+    }
+
+    /// Constructs a watcher for the `inputs` of all the tasks in the graph. The `outputs` of the
+    /// tasks are ignored to make sure that running the tasks does not trigger a rerun.
+    ///
+    /// Returns `None` if none of the tasks in the graph define any inputs.
+    pub async fn from_task_graph(
+        task_graph: &TaskGraph<'_>,
+    ) -> Result<Option<Self>, FileHashesError> {
+        let executes = task_graph
+            .topological_order()
+            .into_iter()
+            .filter_map(|id| task_graph[id].task.as_execute().cloned())
+            .collect_vec();
+
+        let inputs = executes
+            .iter()
+            .flat_map(|execute| execute.inputs.iter().flatten().cloned())
+            .collect_vec();
+        if inputs.is_empty() {
+            return Ok(None);
+        }
+
+        let outputs = executes
+            .iter()
+            .flat_map(|execute| execute.outputs.iter().flatten().cloned());
+
+        Self::new(task_graph.project().root(), inputs, outputs)
+            .await
+            .map(Some)
+    }
+
+    /// Waits until at least one of the watched files is added, removed or modified. Returns the
+    /// paths of the files that changed, relative to the root.
+    pub async fn wait_for_changes(&mut self) -> Result<Vec<PathBuf>, FileHashesError> {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            // Only hash the files when their modification times or sizes changed.
+            let stamps = file_stamps(&self.root, &self.filters).await?;
+            if stamps == self.stamps {
+                continue;
+            }
+            self.stamps = stamps;
+
+            let hashes = FileHashes::from_files(&self.root, &self.filters).await?;
+            let changed = changed_files(&self.hashes, &hashes);
+            if !changed.is_empty() {
+                self.hashes = hashes;
+                return Ok(changed);
+            }
+        }
+    }
+}
+
+/// Returns the sorted paths of the files that differ between two sets of hashes.
+fn changed_files(previous: &FileHashes, current: &FileHashes) -> Vec<PathBuf> {
+    let modified_or_removed = previous
+        .files
+        .iter()
+        .filter(|(path, hash)| current.files.get(*path) != Some(*hash))
+        .map(|(path, _)| path);
+    let added = current
+        .files
+        .keys()
+        .filter(|path| !previous.files.contains_key(*path));
+    modified_or_removed.chain(added).cloned().sorted().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{create_dir, write};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_wait_for_changes() {
+        let target_dir = tempdir().unwrap();
+        create_dir(target_dir.path().join("src")).unwrap();
+        create_dir(target_dir.path().join("build")).unwrap();
+        write(target_dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+        let mut watcher = InputWatcher::new(
+            target_dir.path(),
+            vec!["src/".to_string(), "build/".to_string()],
+            vec!["build/".to_string()],
+        )
+        .await
+        .unwrap();
+
+        // Changes to the outputs are ignored, changes to the inputs are reported.
+        write(target_dir.path().join("build/main"), "binary").unwrap();
+        write(target_dir.path().join("src/lib.rs"), "").unwrap();
+        write(
+            target_dir.path().join("src/main.rs"),
+            "fn main() { println!(\"Hello\") }",
+        )
+        .unwrap();
+
+        let changed = watcher.wait_for_changes().await.unwrap();
+        assert_eq!(
+            changed,
+            vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/main.rs")]
+        );
+
+        // Rewriting a file with the same content is not a change.
+        write(target_dir.path().join("src/lib.rs"), "").unwrap();
+        write(target_dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        let changed = watcher.wait_for_changes().await.unwrap();
+        assert_eq!(changed, vec![PathBuf::from("src/main.rs")]);
+    }
+}
@@ -440,6 +440,8 @@ async fn add_pypi_extra_functionality() {
                     PyPiRequirement::Version {
                         version: VersionOrStar::from_str("==24.8.0").unwrap(),
                         extras: vec![pep508_rs::ExtraName::from_str("cli").unwrap()],
+                        index: None,
+                        markers: pep508_rs::MarkerTree::default(),
                     }
                 );
             }
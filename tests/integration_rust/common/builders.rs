@@ -34,7 +34,8 @@ use std::{
 use futures::FutureExt;
 use pixi::{
     cli::{
-        add, cli_config::DependencyConfig, init, install, project, remove, search, task, update,
+        add, cli_config::DependencyConfig, init, install, lock, project, remove, search, task,
+        update,
     },
     task::TaskName,
     DependencyType,
@@ -516,3 +517,46 @@ impl IntoFuture for UpdateBuilder {
         update::execute(self.args).boxed_local()
     }
 }
+
+/// Contains the arguments to pass to [`lock::lock()`]. Call `.await` to call
+/// the CLI execute method and await the result at the same time. The result
+/// contains the changes to the lock file if `json` is set.
+pub struct LockBuilder {
+    pub args: lock::Args,
+}
+
+impl LockBuilder {
+    pub fn with_environment(mut self, env: impl Into<EnvironmentName>) -> Self {
+        self.args
+            .environments
+            .get_or_insert_with(Vec::new)
+            .push(env.into());
+        self
+    }
+
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.args
+            .platforms
+            .get_or_insert_with(Vec::new)
+            .push(platform);
+        self
+    }
+
+    pub fn check(mut self, check: bool) -> Self {
+        self.args.check = check;
+        self
+    }
+
+    pub fn json(mut self, json: bool) -> Self {
+        self.args.json = json;
+        self
+    }
+}
+
+impl IntoFuture for LockBuilder {
+    type Output = miette::Result<Option<serde_json::Value>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'static>>;
+    fn into_future(self) -> Self::IntoFuture {
+        lock::lock(self.args).boxed_local()
+    }
+}
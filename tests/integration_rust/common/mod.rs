@@ -19,7 +19,7 @@ use pixi::{
         cli_config::{PrefixUpdateConfig, ProjectConfig},
         init::{self, GitAttributes},
         install::Args,
-        project, remove, run,
+        lock, project, remove, run,
         task::{self, AddArgs, AliasArgs},
         update, LockFileUsageArgs,
     },
@@ -43,8 +43,9 @@ use thiserror::Error;
 
 use self::builders::{HasDependencyConfig, RemoveBuilder};
 use crate::common::builders::{
-    AddBuilder, InitBuilder, InstallBuilder, ProjectChannelAddBuilder, ProjectChannelRemoveBuilder,
-    ProjectEnvironmentAddBuilder, TaskAddBuilder, TaskAliasBuilder, UpdateBuilder,
+    AddBuilder, InitBuilder, InstallBuilder, LockBuilder, ProjectChannelAddBuilder,
+    ProjectChannelRemoveBuilder, ProjectEnvironmentAddBuilder, TaskAddBuilder, TaskAliasBuilder,
+    UpdateBuilder,
 };
 
 /// To control the pixi process
@@ -325,6 +326,7 @@ impl PixiControl {
                 project_config: ProjectConfig {
                     manifest_path: Some(self.manifest_path()),
                 },
+                config: Default::default(),
                 platform: Platform::current(),
                 limit: None,
                 channels: ChannelsConfig::default(),
@@ -494,10 +496,54 @@ impl PixiControl {
                 dry_run: false,
                 specs: Default::default(),
                 json: false,
+                exclude_newer: None,
+            },
+        }
+    }
+
+    /// Returns a [`LockBuilder`]. To execute the command and await the result
+    /// call `.await` on the return value.
+    pub fn lock(&self) -> LockBuilder {
+        LockBuilder {
+            args: lock::Args {
+                command: None,
+                config: Default::default(),
+                project_config: ProjectConfig {
+                    manifest_path: Some(self.manifest_path()),
+                },
+                environments: None,
+                platforms: None,
+                check: false,
+                explain: false,
+                json: false,
             },
         }
     }
 
+    /// Runs `pixi lock prune --check` on the project.
+    pub async fn lock_prune_check(&self) -> miette::Result<()> {
+        lock::prune::execute(lock::prune::Args {
+            project_config: ProjectConfig {
+                manifest_path: Some(self.manifest_path()),
+            },
+            dry_run: false,
+            check: true,
+        })
+        .await
+    }
+
+    /// Runs `pixi lock prune` on the project.
+    pub async fn lock_prune(&self, dry_run: bool) -> miette::Result<()> {
+        lock::prune::execute(lock::prune::Args {
+            project_config: ProjectConfig {
+                manifest_path: Some(self.manifest_path()),
+            },
+            dry_run,
+            check: false,
+        })
+        .await
+    }
+
     /// Load the current lock-file.
     ///
     /// If you want to lock-file to be up-to-date with the project call
@@ -0,0 +1,304 @@
+use std::path::Path;
+
+use pixi_consts::consts;
+use rattler_conda_types::Platform;
+use url::Url;
+
+use crate::common::{
+    package_database::{Package, PackageDatabase},
+    LockFileExt, PixiControl,
+};
+
+/// Returns a manifest that depends on `foo` from the given channel.
+fn foo_manifest(channel: &Url, spec: &str) -> String {
+    format!(
+        r#"
+    [project]
+    name = "test-lock"
+    channels = ["{channel}"]
+    platforms = ["{platform}"]
+
+    [dependencies]
+    foo = "{spec}"
+    "#,
+        platform = Platform::current(),
+    )
+}
+
+#[tokio::test]
+async fn test_lock_does_not_install() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    let channel = package_database.into_channel().await.unwrap();
+
+    let pixi = PixiControl::from_manifest(&foo_manifest(&channel.url(), "*")).unwrap();
+
+    // Locking creates the lock-file but never the environment.
+    pixi.lock().await.unwrap();
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+    assert!(!pixi.default_env_path().unwrap().exists());
+}
+
+#[tokio::test]
+async fn test_lock_check() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    package_database.add_package(Package::build("foo", "2").finish());
+    let channel = package_database.into_channel().await.unwrap();
+
+    let pixi = PixiControl::from_manifest(&foo_manifest(&channel.url(), "==1")).unwrap();
+
+    // Without a lock-file the check fails and nothing is written.
+    assert!(pixi.lock().check(true).await.is_err());
+    assert!(!pixi.project_path().join(consts::PROJECT_LOCK_FILE).exists());
+
+    // After locking the check succeeds.
+    pixi.lock().await.unwrap();
+    pixi.lock().check(true).await.unwrap();
+
+    // Changing the manifest makes the check fail again without updating the
+    // lock-file.
+    pixi.update_manifest(&foo_manifest(&channel.url(), "==2"))
+        .unwrap();
+    assert!(pixi.lock().check(true).await.is_err());
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+}
+
+#[tokio::test]
+async fn test_lock_json() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    package_database.add_package(Package::build("foo", "2").finish());
+    let channel = package_database.into_channel().await.unwrap();
+
+    let pixi = PixiControl::from_manifest(&foo_manifest(&channel.url(), "==1")).unwrap();
+    pixi.lock().await.unwrap();
+
+    // An up-to-date lock-file has no changes.
+    let json = pixi.lock().json(true).await.unwrap().unwrap();
+    assert_eq!(
+        json["environment"][consts::DEFAULT_ENVIRONMENT_NAME][Platform::current().as_str()],
+        serde_json::json!([])
+    );
+
+    pixi.update_manifest(&foo_manifest(&channel.url(), "==2"))
+        .unwrap();
+    let json = pixi.lock().json(true).await.unwrap().unwrap();
+    let changes =
+        &json["environment"][consts::DEFAULT_ENVIRONMENT_NAME][Platform::current().as_str()];
+    let changes = changes.as_array().expect("expected the changes of foo");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["name"], "foo");
+    assert_eq!(changes[0]["type"], "conda");
+    assert_eq!(changes[0]["explicit"], true);
+    assert!(changes[0]["before"].is_object());
+    assert!(changes[0]["after"].is_object());
+    assert_ne!(changes[0]["before"], changes[0]["after"]);
+
+    // The lock-file was updated.
+    pixi.lock().check(true).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_prune_environment() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    package_database.add_package(Package::build("bar", "1").finish());
+    let channel = package_database.into_channel().await.unwrap();
+
+    let manifest = foo_manifest(&channel.url(), "*");
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"{manifest}
+    [feature.test.dependencies]
+    bar = "*"
+
+    [environments]
+    test = ["test"]
+    "#
+    ))
+    .unwrap();
+    pixi.lock().await.unwrap();
+    pixi.lock_prune_check().await.unwrap();
+
+    // Pretend the test environment was installed before.
+    let test_prefix = pixi
+        .project_path()
+        .join(consts::PIXI_DIR)
+        .join(consts::ENVIRONMENTS_DIR)
+        .join("test");
+    std::fs::create_dir_all(&test_prefix).unwrap();
+    pixi.lock_prune_check().await.unwrap();
+
+    // Removing the environment from the manifest leaves stale content behind.
+    pixi.update_manifest(&manifest).unwrap();
+    let err = pixi.lock_prune_check().await.unwrap_err();
+    assert!(err.to_string().contains("pixi lock prune"));
+
+    // A dry run does not touch the lock-file or the prefixes.
+    let lock_file_path = pixi.project_path().join(consts::PROJECT_LOCK_FILE);
+    let locked = std::fs::read_to_string(&lock_file_path).unwrap();
+    pixi.lock_prune(true).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&lock_file_path).unwrap(), locked);
+    assert!(test_prefix.is_dir());
+    assert!(pixi.lock_prune_check().await.is_err());
+
+    // Pruning removes the environment from the lock-file and its prefix.
+    pixi.lock_prune(false).await.unwrap();
+    let lock = pixi.lock_file().await.unwrap();
+    assert!(lock.environment("test").is_none());
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+    assert!(!test_prefix.exists());
+    pixi.lock_prune_check().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_prune_platform() {
+    let other_platform = if Platform::current() == Platform::LinuxS390X {
+        Platform::LinuxPpc64le
+    } else {
+        Platform::LinuxS390X
+    };
+
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").finish());
+    package_database.add_package(
+        Package::build("bar", "1")
+            .with_subdir(other_platform)
+            .finish(),
+    );
+    let channel = package_database.into_channel().await.unwrap();
+
+    let manifest = foo_manifest(&channel.url(), "*");
+    let pixi = PixiControl::from_manifest(&manifest.replace(
+        &format!(r#"platforms = ["{}"]"#, Platform::current()),
+        &format!(
+            r#"platforms = ["{}", "{other_platform}"]"#,
+            Platform::current()
+        ),
+    ))
+    .unwrap();
+    pixi.lock().await.unwrap();
+    pixi.lock_prune_check().await.unwrap();
+
+    // Removing the platform from the manifest leaves it in the lock-file.
+    pixi.update_manifest(&manifest).unwrap();
+    let err = pixi.lock_prune_check().await.unwrap_err();
+    assert!(err.to_string().contains("pixi lock prune"));
+
+    // A dry run does not touch the lock-file.
+    let lock_file_path = pixi.project_path().join(consts::PROJECT_LOCK_FILE);
+    let locked = std::fs::read_to_string(&lock_file_path).unwrap();
+    pixi.lock_prune(true).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&lock_file_path).unwrap(), locked);
+
+    // Pruning removes the platform from the lock-file.
+    pixi.lock_prune(false).await.unwrap();
+    let lock = pixi.lock_file().await.unwrap();
+    let platforms = lock
+        .environment(consts::DEFAULT_ENVIRONMENT_NAME)
+        .unwrap()
+        .platforms()
+        .collect::<Vec<_>>();
+    assert_eq!(platforms, vec![Platform::current()]);
+    assert!(lock.contains_match_spec(
+        consts::DEFAULT_ENVIRONMENT_NAME,
+        Platform::current(),
+        "foo ==1"
+    ));
+    pixi.lock_prune_check().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lock_prune_packages() {
+    let mut package_database = PackageDatabase::default();
+    package_database.add_package(Package::build("foo", "1").with_dependency("baz").finish());
+    package_database.add_package(Package::build("baz", "1").finish());
+    package_database.add_package(Package::build("bar", "1").finish());
+    let channel = package_database.into_channel().await.unwrap();
+
+    let manifest = foo_manifest(&channel.url(), "*");
+    let pixi = PixiControl::from_manifest(&format!("{manifest}    bar = \"*\"\n")).unwrap();
+    pixi.lock().await.unwrap();
+    pixi.lock_prune_check().await.unwrap();
+
+    // Removing a dependency from the manifest leaves its package in the lock-file.
+    pixi.update_manifest(&manifest).unwrap();
+    let err = pixi.lock_prune_check().await.unwrap_err();
+    assert!(err.to_string().contains("pixi lock prune"));
+
+    // A dry run does not touch the lock-file.
+    let lock_file_path = pixi.project_path().join(consts::PROJECT_LOCK_FILE);
+    let locked = std::fs::read_to_string(&lock_file_path).unwrap();
+    pixi.lock_prune(true).await.unwrap();
+    assert_eq!(std::fs::read_to_string(&lock_file_path).unwrap(), locked);
+
+    // Pruning removes the package, but keeps the packages that are still required.
+    pixi.lock_prune(false).await.unwrap();
+    let lock = pixi.lock_file().await.unwrap();
+    for (name, locked) in [("foo", true), ("baz", true), ("bar", false)] {
+        assert_eq!(
+            lock.contains_conda_package(
+                consts::DEFAULT_ENVIRONMENT_NAME,
+                Platform::current(),
+                name
+            ),
+            locked,
+            "{name}"
+        );
+    }
+    pixi.lock_prune_check().await.unwrap();
+    pixi.lock().check(true).await.unwrap();
+}
+
+#[tokio::test]
+#[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
+async fn test_lock_pypi_does_not_install() {
+    let pypi_indexes = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/pypi-indexes");
+    let pypi_indexes_url = Url::from_directory_path(pypi_indexes).unwrap();
+
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+        [project]
+        name = "pypi-lock"
+        platforms = ["{platform}"]
+        channels = ["conda-forge"]
+
+        [dependencies]
+        python = "~=3.12.0"
+
+        [pypi-dependencies]
+        foo = "*"
+
+        [pypi-options]
+        index-url = "{pypi_indexes}multiple-indexes-a/index"
+        "#,
+        platform = Platform::current(),
+        pypi_indexes = pypi_indexes_url,
+    ))
+    .unwrap();
+
+    // Solving the pypi dependencies uses a temporary prefix, the environment
+    // itself is never installed.
+    pixi.lock().await.unwrap();
+    let lock = pixi.lock_file().await.unwrap();
+    assert_eq!(
+        lock.get_pypi_package_version(consts::DEFAULT_ENVIRONMENT_NAME, Platform::current(), "foo"),
+        Some("1.0.0".into())
+    );
+    assert!(!pixi.default_env_path().unwrap().exists());
+    pixi.lock().check(true).await.unwrap();
+}
@@ -82,3 +82,52 @@ async fn test_index_strategy() {
         Some("3.0.0".into())
     );
 }
+
+#[tokio::test]
+#[cfg_attr(not(feature = "slow_integration_tests"), ignore)]
+async fn test_pinned_index() {
+    let pypi_indexes = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/pypi-indexes");
+    let pypi_indexes_url = Url::from_directory_path(pypi_indexes).unwrap();
+
+    let pixi = PixiControl::from_manifest(&format!(
+        r#"
+        [project]
+        name = "pypi-pinned-index"
+        platforms = ["{platform}"]
+        channels = ["conda-forge"]
+
+        [dependencies]
+        python = "~=3.12.0"
+
+        [pypi-dependencies]
+        foo = {{ version = "*", index = "pinned" }}
+
+        [pypi-options]
+        extra-index-urls = [
+            "{pypi_indexes}multiple-indexes-a/index",
+            "{pypi_indexes}multiple-indexes-c/index",
+        ]
+        index-strategy = "unsafe-best-match"
+        indexes = {{ pinned = "{pypi_indexes}multiple-indexes-b/index" }}
+        "#,
+        platform = Platform::current(),
+        pypi_indexes = pypi_indexes_url,
+    ));
+
+    let lock_file = pixi.unwrap().update_lock_file().await.unwrap();
+
+    // Without the pin, the best match across the extra indexes would be 3.0.0.
+    assert_eq!(
+        lock_file.get_pypi_package_version("default", Platform::current(), "foo"),
+        Some("2.0.0".into())
+    );
+
+    // The named index is recorded in the lock-file so the package can be
+    // installed from it.
+    let environment = lock_file.environment("default").unwrap();
+    let indexes = environment.pypi_indexes().unwrap();
+    assert!(indexes
+        .indexes
+        .iter()
+        .any(|index| index.as_str().contains("multiple-indexes-b")));
+}